@@ -5,6 +5,7 @@ use std::env;
 
 fn main() {
     let filename = env::args().nth(1).expect("filename to compile");
-    let class = compiler::compile_path(filename).expect("class to compile");
+    let options = compiler::CompileOptions::default();
+    let class = compiler::compile_path(filename, &options).expect("class to compile");
     println!("{:#?}", class);
 }
@@ -0,0 +1,69 @@
+extern crate som;
+
+use som::compiler::CompileOptions;
+use som::interpreter::InterpreterError;
+use som::vm::{Universe, UniverseOptions};
+use std::env;
+use std::process;
+
+#[cfg_attr(tarpaulin, skip)]
+fn main() {
+    let mut classpath = vec![];
+    let mut quickening = true;
+    let mut dump_inline_caches = false;
+    let mut cascades = false;
+    let mut class_name = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-cp" => {
+                let paths = args.next().expect("classpath after -cp");
+                classpath.extend(env::split_paths(&paths));
+            }
+            "--no-quicken" => quickening = false,
+            "--dump-inline-caches" => dump_inline_caches = true,
+            "--cascades" => cascades = true,
+            _ => class_name = Some(arg),
+        }
+    }
+
+    let class_name = class_name.expect("class to run");
+    let mut universe = Universe::with_options(UniverseOptions {
+        classpath,
+        compile_options: CompileOptions {
+            cascades,
+            ..CompileOptions::default()
+        },
+        quickening,
+        ..UniverseOptions::default()
+    })
+    .expect("system classes");
+
+    let result = universe.run(&class_name);
+
+    if dump_inline_caches {
+        for site in universe.inline_cache_stats() {
+            eprintln!(
+                "{} @{} #{}: {:?} hits={} misses={}",
+                site.method, site.bytecode_index, site.selector, site.state, site.hits, site.misses
+            );
+        }
+    }
+
+    let _ = universe.output().flush();
+    match result {
+        Ok(_) => {}
+        Err(InterpreterError::Exit(status)) => process::exit(status),
+        Err(e) => {
+            match e {
+                InterpreterError::RuntimeError(message) => eprintln!("ERROR: {}", message),
+                e => eprintln!("{:?}", e),
+            }
+            for frame in universe.error_stack_trace().unwrap_or_default() {
+                eprintln!("    {}", frame);
+            }
+            process::exit(1);
+        }
+    }
+}
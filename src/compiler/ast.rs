@@ -1,3 +1,5 @@
+use crate::compiler::Location;
+use num_bigint::BigInt;
 use std::collections::HashMap;
 
 #[derive(Debug, PartialEq)]
@@ -15,35 +17,86 @@ pub enum Expression {
     Assignment {
         variable: String,
         value: Box<Expression>,
+        location: Location,
     },
     BinaryMessage {
         message: String,
         left: Box<Expression>,
         right: Box<Expression>,
+        location: Location,
     },
     Block {
         parameters: Vec<String>,
         locals: Vec<String>,
         body: Vec<Expression>,
+        location: Location,
+    },
+    /// `receiver foo; bar baz; qux: 1`. The first message of each part is
+    /// sent to the same receiver, and the rest of the part to its result.
+    /// The value is the result of the last part.
+    Cascade {
+        receiver: Box<Expression>,
+        parts: Vec<Vec<Message>>,
     },
     KeywordMessage {
         message: String,
         receiver: Box<Expression>,
         parameters: Vec<Expression>,
+        location: Location,
+    },
+    LiteralArray {
+        values: Vec<Expression>,
+        location: Location,
+    },
+    LiteralBoolean {
+        value: bool,
+        location: Location,
+    },
+    LiteralDouble {
+        value: f64,
+        location: Location,
+    },
+    LiteralInteger {
+        value: i64,
+        location: Location,
+    },
+    /// An integer literal too large for `i64`.
+    LiteralLargeInteger {
+        value: BigInt,
+        location: Location,
+    },
+    LiteralNil {
+        location: Location,
+    },
+    LiteralString {
+        value: String,
+        location: Location,
+    },
+    LiteralSymbol {
+        value: String,
+        location: Location,
+    },
+    Return {
+        value: Box<Expression>,
+        location: Location,
     },
-    LiteralArray(Vec<Expression>),
-    LiteralBoolean(bool),
-    LiteralDouble(f64),
-    LiteralInteger(i64),
-    LiteralNil,
-    LiteralString(String),
-    LiteralSymbol(String),
-    Return(Box<Expression>),
     UnaryMessage {
         message: String,
         receiver: Box<Expression>,
+        location: Location,
+    },
+    Variable {
+        name: String,
+        location: Location,
     },
-    Variable(String),
+}
+
+/// One message of a cascade part, without its receiver.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Message {
+    pub message: String,
+    pub parameters: Vec<Expression>,
+    pub location: Location,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -57,5 +110,6 @@ pub enum Method {
         parameters: Vec<String>,
         locals: Vec<String>,
         body: Vec<Expression>,
+        location: Location,
     },
 }
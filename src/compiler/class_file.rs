@@ -0,0 +1,843 @@
+use crate::compiler::compiled::{
+    CompiledClass, CompiledCode, CompiledMethod, InheritedFields, LineEntry, LineTable, Literal,
+};
+use crate::compiler::{CompileOptions, KnownPrimitives, Location, OptimizationLevel};
+use crate::interpreter::arity;
+use crate::interpreter::bytecode::{Bytecode, BytecodeIterator, BytecodeIteratorError};
+use num_bigint::BigInt;
+use std::io::{self, Read, Write};
+use std::result;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MAGIC: &[u8; 4] = b"SOMC";
+pub const VERSION: u16 = 7;
+
+const METHOD_PRIMITIVE: u8 = 0;
+const METHOD_BYTECODE: u8 = 1;
+
+const LITERAL_INTEGER: u8 = 0;
+const LITERAL_DOUBLE: u8 = 1;
+const LITERAL_STRING: u8 = 2;
+const LITERAL_SYMBOL: u8 = 3;
+const LITERAL_ARRAY: u8 = 4;
+const LITERAL_BOOLEAN: u8 = 5;
+const LITERAL_NIL: u8 = 6;
+const LITERAL_LARGE_INTEGER: u8 = 7;
+
+/// How deeply array literals may nest, so reading one cannot exhaust the
+/// native stack.
+const MAX_LITERAL_DEPTH: usize = 64;
+
+#[derive(Debug)]
+pub enum ClassFileError {
+    IoError(io::Error),
+    InvalidMagic,
+    UnsupportedVersion(u16),
+    InvalidBytecode(BytecodeIteratorError),
+    InvalidData(String),
+}
+
+impl From<io::Error> for ClassFileError {
+    fn from(source: io::Error) -> Self {
+        ClassFileError::IoError(source)
+    }
+}
+
+impl From<BytecodeIteratorError> for ClassFileError {
+    fn from(source: BytecodeIteratorError) -> Self {
+        ClassFileError::InvalidBytecode(source)
+    }
+}
+
+pub type Result<T> = result::Result<T, ClassFileError>;
+
+/// Identifies the source a class file was compiled from. The modification
+/// time is a cheap first check, the hash decides when the times differ.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct SourceStamp {
+    pub size: u64,
+    pub modified_secs: u64,
+    pub modified_nanos: u32,
+    pub hash: u64,
+}
+
+impl SourceStamp {
+    pub fn new(source: &[u8], modified: Option<SystemTime>) -> SourceStamp {
+        SourceStamp {
+            hash: hash_source(source),
+            ..SourceStamp::unhashed(source.len() as u64, modified)
+        }
+    }
+
+    /// Builds a stamp from file metadata alone, good enough for
+    /// `matches_time` without reading the source.
+    pub fn unhashed(size: u64, modified: Option<SystemTime>) -> SourceStamp {
+        let modified = modified
+            .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default();
+
+        SourceStamp {
+            size,
+            modified_secs: modified.as_secs(),
+            modified_nanos: modified.subsec_nanos(),
+            hash: 0,
+        }
+    }
+
+    pub fn matches_time(&self, other: &SourceStamp) -> bool {
+        self.size == other.size
+            && self.modified_secs == other.modified_secs
+            && self.modified_nanos == other.modified_nanos
+    }
+
+    pub fn matches_content(&self, other: &SourceStamp) -> bool {
+        self.size == other.size && self.hash == other.hash
+    }
+}
+
+fn hash_source(source: &[u8]) -> u64 {
+    // FNV-1a, stable across platforms and Rust versions unlike DefaultHasher
+    source.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClassFile {
+    pub stamp: SourceStamp,
+    pub options: CompileOptions,
+    pub class: CompiledClass,
+}
+
+impl ClassFile {
+    pub fn write_to<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut writer = ClassWriter { inner: writer };
+        writer.write_bytes(MAGIC)?;
+        writer.write_u16(VERSION)?;
+        writer.write_stamp(&self.stamp)?;
+        writer.write_options(&self.options)?;
+        writer.write_class(&self.class)
+    }
+
+    pub fn read_from<R: Read>(reader: R) -> Result<ClassFile> {
+        let mut reader = ClassReader { inner: reader };
+
+        let mut magic = [0; 4];
+        reader.inner.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(ClassFileError::InvalidMagic);
+        }
+
+        let version = reader.read_u16()?;
+        if version != VERSION {
+            return Err(ClassFileError::UnsupportedVersion(version));
+        }
+
+        Ok(ClassFile {
+            stamp: reader.read_stamp()?,
+            options: reader.read_options()?,
+            class: reader.read_class()?,
+        })
+    }
+}
+
+struct ClassWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> ClassWriter<W> {
+    fn write_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.inner.write_all(bytes)
+    }
+
+    fn write_u8(&mut self, value: u8) -> io::Result<()> {
+        self.write_bytes(&[value])
+    }
+
+    fn write_u16(&mut self, value: u16) -> io::Result<()> {
+        self.write_bytes(&value.to_le_bytes())
+    }
+
+    fn write_u32(&mut self, value: u32) -> io::Result<()> {
+        self.write_bytes(&value.to_le_bytes())
+    }
+
+    fn write_u64(&mut self, value: u64) -> io::Result<()> {
+        self.write_bytes(&value.to_le_bytes())
+    }
+
+    fn write_len(&mut self, len: usize) -> io::Result<()> {
+        if len > u32::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "length does not fit in class file",
+            ));
+        }
+
+        self.write_u32(len as u32)
+    }
+
+    fn write_string(&mut self, value: &str) -> io::Result<()> {
+        self.write_len(value.len())?;
+        self.write_bytes(value.as_bytes())
+    }
+
+    fn write_strings(&mut self, values: &[String]) -> io::Result<()> {
+        self.write_len(values.len())?;
+        for value in values {
+            self.write_string(value)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_stamp(&mut self, stamp: &SourceStamp) -> io::Result<()> {
+        self.write_u64(stamp.size)?;
+        self.write_u64(stamp.modified_secs)?;
+        self.write_u32(stamp.modified_nanos)?;
+        self.write_u64(stamp.hash)
+    }
+
+    fn write_options(&mut self, options: &CompileOptions) -> io::Result<()> {
+        let level = match options.optimization_level {
+            OptimizationLevel::None => 0,
+            OptimizationLevel::Peephole => 1,
+        };
+
+        self.write_u8(level)?;
+        self.write_u8(options.fold_constants as u8)?;
+        self.write_u8(options.cascades as u8)?;
+
+        let classes = options.known_primitives.classes();
+        self.write_len(classes.len())?;
+        for (class, selectors) in classes {
+            self.write_string(class)?;
+            self.write_len(selectors.len())?;
+            for selector in selectors {
+                self.write_string(selector)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_class(&mut self, class: &CompiledClass) -> io::Result<()> {
+        self.write_string(&class.name)?;
+        self.write_string(&class.filename)?;
+        match &class.superclass {
+            Some(superclass) => {
+                self.write_u8(1)?;
+                self.write_string(superclass)?;
+            }
+            None => self.write_u8(0)?,
+        }
+
+        self.write_strings(&class.inherited.instance_fields)?;
+        self.write_strings(&class.inherited.class_fields)?;
+        self.write_strings(&class.instance_fields)?;
+        self.write_methods(&class.instance_methods)?;
+        self.write_strings(&class.class_fields)?;
+        self.write_methods(&class.class_methods)
+    }
+
+    fn write_methods(&mut self, methods: &[CompiledMethod]) -> io::Result<()> {
+        self.write_len(methods.len())?;
+        for method in methods {
+            match method {
+                CompiledMethod::Primitive {
+                    signature,
+                    num_parameters,
+                } => {
+                    self.write_u8(METHOD_PRIMITIVE)?;
+                    self.write_string(signature)?;
+                    self.write_len(*num_parameters)?;
+                }
+                CompiledMethod::Bytecode { signature, code } => {
+                    self.write_u8(METHOD_BYTECODE)?;
+                    self.write_string(signature)?;
+                    self.write_code(code)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_code(&mut self, code: &CompiledCode) -> io::Result<()> {
+        self.write_len(code.num_parameters)?;
+        self.write_len(code.num_locals)?;
+
+        self.write_len(code.literals.len())?;
+        for literal in &code.literals {
+            self.write_literal(literal)?;
+        }
+
+        let bytes = code
+            .bytecodes
+            .iter()
+            .flat_map(|&bytecode| Vec::<u8>::from(bytecode))
+            .collect::<Vec<_>>();
+        self.write_len(bytes.len())?;
+        self.write_bytes(&bytes)?;
+
+        self.write_len(code.blocks.len())?;
+        for block in &code.blocks {
+            self.write_code(block)?;
+        }
+
+        self.write_line_table(&code.line_table)
+    }
+
+    fn write_line_table(&mut self, line_table: &LineTable) -> io::Result<()> {
+        self.write_len(line_table.entries().len())?;
+        for entry in line_table.entries() {
+            self.write_len(entry.bytecode_index)?;
+            self.write_len(entry.location.line)?;
+            self.write_len(entry.location.column)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_literal(&mut self, literal: &Literal) -> io::Result<()> {
+        match literal {
+            Literal::Integer(value) => {
+                self.write_u8(LITERAL_INTEGER)?;
+                self.write_bytes(&value.to_le_bytes())
+            }
+            Literal::LargeInteger(value) => {
+                self.write_u8(LITERAL_LARGE_INTEGER)?;
+                let bytes = value.to_signed_bytes_le();
+                self.write_len(bytes.len())?;
+                self.write_bytes(&bytes)
+            }
+            Literal::Double(value) => {
+                self.write_u8(LITERAL_DOUBLE)?;
+                self.write_u64(value.to_bits())
+            }
+            Literal::String(value) => {
+                self.write_u8(LITERAL_STRING)?;
+                self.write_string(value)
+            }
+            Literal::Symbol(value) => {
+                self.write_u8(LITERAL_SYMBOL)?;
+                self.write_string(value)
+            }
+            Literal::Array(values) => {
+                self.write_u8(LITERAL_ARRAY)?;
+                self.write_len(values.len())?;
+                for value in values {
+                    self.write_literal(value)?;
+                }
+
+                Ok(())
+            }
+            Literal::Boolean(value) => {
+                self.write_u8(LITERAL_BOOLEAN)?;
+                self.write_u8(*value as u8)
+            }
+            Literal::Nil => self.write_u8(LITERAL_NIL),
+        }
+    }
+}
+
+struct ClassReader<R: Read> {
+    inner: R,
+}
+
+impl<R: Read> ClassReader<R> {
+    fn read_array<T: Default + AsMut<[u8]>>(&mut self) -> Result<T> {
+        let mut buffer = T::default();
+        self.inner.read_exact(buffer.as_mut())?;
+        Ok(buffer)
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.read_array::<[u8; 1]>()?[0])
+    }
+
+    fn read_bool(&mut self) -> Result<bool> {
+        match self.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            value => Err(ClassFileError::InvalidData(format!(
+                "invalid boolean {}",
+                value
+            ))),
+        }
+    }
+
+    fn read_u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.read_array()?))
+    }
+
+    fn read_u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.read_array()?))
+    }
+
+    fn read_u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.read_array()?))
+    }
+
+    fn read_len(&mut self) -> Result<usize> {
+        Ok(self.read_u32()? as usize)
+    }
+
+    fn read_bytes(&mut self) -> Result<Vec<u8>> {
+        let len = self.read_len()?;
+        let mut bytes = vec![];
+        self.inner
+            .by_ref()
+            .take(len as u64)
+            .read_to_end(&mut bytes)?;
+        if bytes.len() != len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+
+        Ok(bytes)
+    }
+
+    fn read_string(&mut self) -> Result<String> {
+        String::from_utf8(self.read_bytes()?)
+            .map_err(|_| ClassFileError::InvalidData("string is not valid UTF-8".into()))
+    }
+
+    fn read_strings(&mut self) -> Result<Vec<String>> {
+        let len = self.read_len()?;
+        (0..len).map(|_| self.read_string()).collect()
+    }
+
+    fn read_stamp(&mut self) -> Result<SourceStamp> {
+        Ok(SourceStamp {
+            size: self.read_u64()?,
+            modified_secs: self.read_u64()?,
+            modified_nanos: self.read_u32()?,
+            hash: self.read_u64()?,
+        })
+    }
+
+    fn read_options(&mut self) -> Result<CompileOptions> {
+        let optimization_level = match self.read_u8()? {
+            0 => OptimizationLevel::None,
+            1 => OptimizationLevel::Peephole,
+            level => {
+                return Err(ClassFileError::InvalidData(format!(
+                    "invalid optimization level {}",
+                    level
+                )))
+            }
+        };
+
+        let fold_constants = self.read_bool()?;
+        let cascades = self.read_bool()?;
+
+        let mut known_primitives = KnownPrimitives::default();
+        for _ in 0..self.read_len()? {
+            let class = self.read_string()?;
+            for _ in 0..self.read_len()? {
+                known_primitives.insert(&class, &self.read_string()?);
+            }
+        }
+
+        Ok(CompileOptions {
+            optimization_level,
+            fold_constants,
+            known_primitives,
+            cascades,
+        })
+    }
+
+    fn read_class(&mut self) -> Result<CompiledClass> {
+        let name = self.read_string()?;
+        let filename = self.read_string()?;
+        let superclass = match self.read_u8()? {
+            0 => None,
+            1 => Some(self.read_string()?),
+            tag => {
+                return Err(ClassFileError::InvalidData(format!(
+                    "invalid superclass tag {}",
+                    tag
+                )))
+            }
+        };
+
+        let inherited = InheritedFields {
+            instance_fields: self.read_strings()?,
+            class_fields: self.read_strings()?,
+        };
+
+        let instance_fields = self.read_strings()?;
+        let num_fields = inherited.instance_fields.len() + instance_fields.len();
+        let instance_methods = self.read_methods(num_fields)?;
+        let class_fields = self.read_strings()?;
+        let num_fields = inherited.class_fields.len() + class_fields.len();
+        let class_methods = self.read_methods(num_fields)?;
+
+        Ok(CompiledClass {
+            name,
+            filename,
+            superclass,
+            inherited,
+            instance_fields,
+            instance_methods,
+            class_fields,
+            class_methods,
+        })
+    }
+
+    fn read_methods(&mut self, num_fields: usize) -> Result<Vec<CompiledMethod>> {
+        let len = self.read_len()?;
+        (0..len).map(|_| self.read_method(num_fields)).collect()
+    }
+
+    fn read_method(&mut self, num_fields: usize) -> Result<CompiledMethod> {
+        let tag = self.read_u8()?;
+        let signature = self.read_string()?;
+        match tag {
+            METHOD_PRIMITIVE => Ok(CompiledMethod::Primitive {
+                signature,
+                num_parameters: self.read_len()?,
+            }),
+            METHOD_BYTECODE => {
+                let code = self.read_code()?;
+                check_operands(&code, num_fields, &mut vec![])?;
+                Ok(CompiledMethod::Bytecode { signature, code })
+            }
+            tag => Err(ClassFileError::InvalidData(format!(
+                "invalid method tag {}",
+                tag
+            ))),
+        }
+    }
+
+    fn read_code(&mut self) -> Result<CompiledCode> {
+        let num_parameters = self.read_len()?;
+        let num_locals = self.read_len()?;
+
+        let len = self.read_len()?;
+        let literals = (0..len)
+            .map(|_| self.read_literal(0))
+            .collect::<Result<_>>()?;
+
+        let bytecodes =
+            BytecodeIterator::new(self.read_bytes()?).collect::<result::Result<_, _>>()?;
+
+        let len = self.read_len()?;
+        let blocks = (0..len).map(|_| self.read_code()).collect::<Result<_>>()?;
+
+        Ok(CompiledCode {
+            num_parameters,
+            num_locals,
+            literals,
+            bytecodes,
+            blocks,
+            line_table: self.read_line_table()?,
+        })
+    }
+
+    fn read_line_table(&mut self) -> Result<LineTable> {
+        let len = self.read_len()?;
+        let entries = (0..len)
+            .map(|_| {
+                Ok(LineEntry {
+                    bytecode_index: self.read_len()?,
+                    location: Location {
+                        line: self.read_len()?,
+                        column: self.read_len()?,
+                    },
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(LineTable::from_entries(entries))
+    }
+
+    fn read_literal(&mut self, depth: usize) -> Result<Literal> {
+        match self.read_u8()? {
+            LITERAL_INTEGER => Ok(Literal::Integer(i64::from_le_bytes(self.read_array()?))),
+            LITERAL_LARGE_INTEGER => Ok(Literal::LargeInteger(BigInt::from_signed_bytes_le(
+                &self.read_bytes()?,
+            ))),
+            LITERAL_DOUBLE => Ok(Literal::Double(f64::from_bits(self.read_u64()?))),
+            LITERAL_STRING => Ok(Literal::String(self.read_string()?)),
+            LITERAL_SYMBOL => Ok(Literal::Symbol(self.read_string()?)),
+            LITERAL_ARRAY if depth >= MAX_LITERAL_DEPTH => Err(ClassFileError::InvalidData(
+                format!("array literal nested deeper than {}", MAX_LITERAL_DEPTH),
+            )),
+            LITERAL_ARRAY => {
+                let len = self.read_len()?;
+                let values = (0..len)
+                    .map(|_| self.read_literal(depth + 1))
+                    .collect::<Result<_>>()?;
+                Ok(Literal::Array(values))
+            }
+            LITERAL_BOOLEAN => Ok(Literal::Boolean(self.read_bool()?)),
+            LITERAL_NIL => Ok(Literal::Nil),
+            tag => Err(ClassFileError::InvalidData(format!(
+                "invalid literal tag {}",
+                tag
+            ))),
+        }
+    }
+}
+
+/// Checks that every operand of `code` refers to something that exists and
+/// that no bytecode pops more values than are on the stack, so a corrupted
+/// file is rejected instead of failing at run time. `num_fields` counts the
+/// receiver's fields and `outer` holds the argument and local counts of the
+/// enclosing code.
+fn check_operands(
+    code: &CompiledCode,
+    num_fields: usize,
+    outer: &mut Vec<(usize, usize)>,
+) -> Result<()> {
+    // Index 0 of the arguments is the receiver or the block itself.
+    outer.push((code.num_parameters + 1, code.num_locals));
+    let contexts = &outer[..];
+    let variable = |index: u8, context: u8, locals: bool| {
+        let depth = context as usize;
+        depth < contexts.len() && {
+            let (arguments, num_locals) = contexts[contexts.len() - 1 - depth];
+            (index as usize) < if locals { num_locals } else { arguments }
+        }
+    };
+    let symbol = |index: u8| matches!(code.literals.get(index as usize), Some(Literal::Symbol(_)));
+
+    let mut depth = 0;
+    for (i, bytecode) in code.bytecodes.iter().enumerate() {
+        let valid = match *bytecode {
+            Bytecode::PushField { index } | Bytecode::PopField { index } => {
+                (index as usize) < num_fields
+            }
+            Bytecode::PushLocal { index, context } | Bytecode::PopLocal { index, context } => {
+                variable(index, context, true)
+            }
+            Bytecode::PushArgument { index, context }
+            | Bytecode::PopArgument { index, context } => variable(index, context, false),
+            Bytecode::PushBlock { index } => (index as usize) < code.blocks.len(),
+            Bytecode::PushConstant { index } => (index as usize) < code.literals.len(),
+            Bytecode::PushGlobal { index }
+            | Bytecode::Send { index }
+            | Bytecode::SuperSend { index } => symbol(index),
+            _ => true,
+        };
+
+        if !valid {
+            return Err(ClassFileError::InvalidData(format!(
+                "operand of bytecode {} out of range: {:?}",
+                i, bytecode
+            )));
+        }
+
+        let (pops, pushes) = stack_effect(code, *bytecode);
+        if depth < pops {
+            return Err(ClassFileError::InvalidData(format!(
+                "bytecode {} pops an empty stack: {:?}",
+                i, bytecode
+            )));
+        }
+        depth = depth - pops + pushes;
+    }
+
+    for block in &code.blocks {
+        check_operands(block, num_fields, outer)?;
+    }
+    outer.pop();
+
+    Ok(())
+}
+
+/// How many values `bytecode` pops and pushes. Sends pop their receiver and
+/// arguments; their selectors must already have been checked.
+fn stack_effect(code: &CompiledCode, bytecode: Bytecode) -> (usize, usize) {
+    match bytecode {
+        Bytecode::Halt | Bytecode::ReturnSelf => (0, 0),
+        Bytecode::Dup => (1, 2),
+        Bytecode::PushLocal { .. }
+        | Bytecode::PushArgument { .. }
+        | Bytecode::PushField { .. }
+        | Bytecode::PushBlock { .. }
+        | Bytecode::PushConstant { .. }
+        | Bytecode::PushGlobal { .. }
+        | Bytecode::PushSelf
+        | Bytecode::PushNil
+        | Bytecode::PushZero
+        | Bytecode::PushOne => (0, 1),
+        Bytecode::Pop
+        | Bytecode::PopLocal { .. }
+        | Bytecode::PopArgument { .. }
+        | Bytecode::PopField { .. }
+        | Bytecode::ReturnLocal
+        | Bytecode::ReturnNonLocal => (1, 0),
+        Bytecode::Send { index } | Bytecode::SuperSend { index } => {
+            match code.literals.get(index as usize) {
+                Some(Literal::Symbol(selector)) => (arity(selector) + 1, 1),
+                _ => (0, 1),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::sourcecode_compiler::compile_source;
+
+    fn class_file() -> ClassFile {
+        let source = b"
+        Counter = Object (
+            | count |
+            increment = ( count := count + 1. ^ [ :x | x * 2.5 ] value: 'a' )
+            primitiveFoo: a = primitive
+            ----
+            | instances |
+            new = ( ^ #(#new 1 2.5 'a' #(3 4)) + 99999999999999999999 )
+        )";
+
+        let mut options = CompileOptions {
+            fold_constants: true,
+            ..CompileOptions::default()
+        };
+        options.known_primitives.insert("Integer", "+");
+
+        ClassFile {
+            stamp: SourceStamp::new(source, None),
+            class: compile_source(source.as_ref(), "Counter.som", &options).unwrap(),
+            options,
+        }
+    }
+
+    #[test]
+    fn test_class_file_roundtrip() {
+        let class_file = class_file();
+        let mut bytes = vec![];
+        class_file.write_to(&mut bytes).unwrap();
+
+        let result = ClassFile::read_from(bytes.as_slice()).unwrap();
+        assert_eq!(class_file, result);
+    }
+
+    #[test]
+    fn test_class_file_invalid_magic() {
+        let result = ClassFile::read_from(b"SOMX\x01\x00".as_ref());
+        match result {
+            Err(ClassFileError::InvalidMagic) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_class_file_unsupported_version() {
+        let mut bytes = vec![];
+        class_file().write_to(&mut bytes).unwrap();
+        bytes[4] = 0xff;
+        bytes[5] = 0xff;
+
+        match ClassFile::read_from(bytes.as_slice()) {
+            Err(ClassFileError::UnsupportedVersion(v)) => assert_eq!(0xffff, v),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_class_file_truncated() {
+        let mut bytes = vec![];
+        class_file().write_to(&mut bytes).unwrap();
+        bytes.truncate(bytes.len() - 3);
+
+        match ClassFile::read_from(bytes.as_slice()) {
+            Err(ClassFileError::IoError(e)) => {
+                assert_eq!(io::ErrorKind::UnexpectedEof, e.kind())
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_class_file_operand_out_of_range() {
+        let corruptions = [
+            Bytecode::PushConstant { index: 200 },
+            Bytecode::Send { index: 200 },
+            Bytecode::PushBlock { index: 1 },
+            Bytecode::PushLocal {
+                index: 0,
+                context: 0,
+            },
+            Bytecode::PushArgument {
+                index: 0,
+                context: 1,
+            },
+            Bytecode::PushField { index: 1 },
+        ];
+
+        for &corruption in corruptions.iter() {
+            let mut class_file = class_file();
+            match &mut class_file.class.instance_methods[0] {
+                CompiledMethod::Bytecode { code, .. } => code.bytecodes[0] = corruption,
+                m => panic!("unexpected method {:?}", m),
+            }
+            let mut bytes = vec![];
+            class_file.write_to(&mut bytes).unwrap();
+
+            match ClassFile::read_from(bytes.as_slice()) {
+                Err(ClassFileError::InvalidData(e)) => assert!(e.contains("out of range"), "{}", e),
+                r => panic!("unexpected result {:?}", r),
+            }
+        }
+    }
+
+    #[test]
+    fn test_class_file_stack_underflow() {
+        for &corruption in [Bytecode::Pop, Bytecode::Dup, Bytecode::ReturnLocal].iter() {
+            let mut class_file = class_file();
+            match &mut class_file.class.instance_methods[0] {
+                CompiledMethod::Bytecode { code, .. } => code.bytecodes[0] = corruption,
+                m => panic!("unexpected method {:?}", m),
+            }
+            let mut bytes = vec![];
+            class_file.write_to(&mut bytes).unwrap();
+
+            match ClassFile::read_from(bytes.as_slice()) {
+                Err(ClassFileError::InvalidData(e)) => {
+                    assert!(e.contains("pops an empty stack"), "{}", e)
+                }
+                r => panic!("unexpected result {:?}", r),
+            }
+        }
+    }
+
+    #[test]
+    fn test_class_file_array_literal_depth() {
+        let mut literal = Literal::Nil;
+        for _ in 0..=MAX_LITERAL_DEPTH {
+            literal = Literal::Array(vec![literal]);
+        }
+
+        let mut class_file = class_file();
+        match &mut class_file.class.class_methods[0] {
+            CompiledMethod::Bytecode { code, .. } => code.literals.push(literal),
+            m => panic!("unexpected method {:?}", m),
+        }
+        let mut bytes = vec![];
+        class_file.write_to(&mut bytes).unwrap();
+
+        match ClassFile::read_from(bytes.as_slice()) {
+            Err(ClassFileError::InvalidData(e)) => assert!(e.contains("nested deeper"), "{}", e),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_source_stamp_matches() {
+        let stamp = SourceStamp::new(b"Foo = ()", None);
+        assert!(stamp.matches_content(&SourceStamp::new(b"Foo = ()", None)));
+        assert!(!stamp.matches_content(&SourceStamp::new(b"Bar = ()", None)));
+
+        let later = SourceStamp {
+            modified_secs: 1,
+            ..stamp
+        };
+        assert!(!stamp.matches_time(&later));
+        assert!(stamp.matches_content(&later));
+    }
+}
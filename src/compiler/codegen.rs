@@ -0,0 +1,829 @@
+use crate::compiler::compiled::{
+    CompiledClass, CompiledCode, CompiledMethod, InheritedFields, Literal,
+};
+use crate::compiler::{ast, Location};
+use crate::interpreter::Bytecode;
+use std::collections::HashMap;
+use std::result;
+use std::slice;
+
+#[derive(Debug, PartialEq)]
+pub struct CodegenError {
+    pub description: String,
+    pub method: String,
+}
+
+pub type Result<T> = result::Result<T, CodegenError>;
+
+enum Variable {
+    Argument { index: u8, context: u8 },
+    Local { index: u8, context: u8 },
+    Field { index: u8 },
+    Global,
+}
+
+struct Scope {
+    parameters: Vec<String>,
+    locals: Vec<String>,
+    location: Location,
+    code: CompiledCode,
+}
+
+struct MethodGenerator<'a> {
+    fields: &'a [String],
+    signature: &'a str,
+    scopes: Vec<Scope>,
+}
+
+/// Generates `class` as a subclass of a class with the `inherited` fields.
+pub fn generate_class(
+    class: &ast::Class,
+    filename: &str,
+    inherited: &InheritedFields,
+) -> Result<CompiledClass> {
+    let instance_fields = field_layout(
+        &class.name,
+        &inherited.instance_fields,
+        &class.instance_variables,
+    )?;
+    let class_fields = field_layout(&class.name, &inherited.class_fields, &class.class_variables)?;
+
+    Ok(CompiledClass {
+        name: class.name.clone(),
+        filename: filename.into(),
+        superclass: class.superclass.clone(),
+        inherited: inherited.clone(),
+        instance_fields: class.instance_variables.clone(),
+        instance_methods: generate_methods(&instance_fields, &class.instance_methods)?,
+        class_fields: class.class_variables.clone(),
+        class_methods: generate_methods(&class_fields, &class.class_methods)?,
+    })
+}
+
+/// All fields of an object, the inherited ones first. A field may not
+/// share its name with another field of the class or its superclasses.
+pub fn field_layout(class: &str, inherited: &[String], declared: &[String]) -> Result<Vec<String>> {
+    let mut fields = inherited.to_vec();
+    for field in declared {
+        if fields.contains(field) {
+            return Err(CodegenError {
+                description: format!("Field {} is already defined", field),
+                method: class.into(),
+            });
+        }
+
+        fields.push(field.clone());
+    }
+
+    Ok(fields)
+}
+
+fn generate_methods(
+    fields: &[String],
+    methods: &HashMap<String, ast::Method>,
+) -> Result<Vec<CompiledMethod>> {
+    let mut signatures = methods.keys().collect::<Vec<_>>();
+    signatures.sort();
+
+    signatures
+        .into_iter()
+        .map(|signature| generate_method(fields, &methods[signature]))
+        .collect()
+}
+
+pub fn generate_method(fields: &[String], method: &ast::Method) -> Result<CompiledMethod> {
+    match method {
+        ast::Method::Primitive { name, parameters } => Ok(CompiledMethod::Primitive {
+            signature: name.clone(),
+            num_parameters: parameters.len(),
+        }),
+        ast::Method::Native {
+            name,
+            parameters,
+            locals,
+            body,
+            location,
+        } => {
+            let mut generator = MethodGenerator {
+                fields,
+                signature: name,
+                scopes: vec![],
+            };
+
+            generator.push_scope("self", parameters, locals, *location);
+            generator.generate_method_body(body)?;
+            let code = generator.pop_scope();
+
+            Ok(CompiledMethod::Bytecode {
+                signature: name.clone(),
+                code,
+            })
+        }
+    }
+}
+
+impl<'a> MethodGenerator<'a> {
+    fn push_scope(
+        &mut self,
+        receiver: &str,
+        parameters: &[String],
+        locals: &[String],
+        location: Location,
+    ) {
+        let mut names = vec![receiver.to_string()];
+        names.extend(parameters.iter().cloned());
+
+        self.scopes.push(Scope {
+            parameters: names,
+            locals: locals.to_vec(),
+            location,
+            code: CompiledCode {
+                num_parameters: parameters.len(),
+                num_locals: locals.len(),
+                ..CompiledCode::default()
+            },
+        });
+    }
+
+    fn pop_scope(&mut self) -> CompiledCode {
+        self.scopes.pop().expect("scope to pop").code
+    }
+
+    fn current_scope(&mut self) -> &mut Scope {
+        self.scopes.last_mut().expect("current scope")
+    }
+
+    fn current_code(&mut self) -> &mut CompiledCode {
+        &mut self.current_scope().code
+    }
+
+    fn is_block(&self) -> bool {
+        self.scopes.len() > 1
+    }
+
+    fn error<T>(&self, description: String) -> Result<T> {
+        Err(CodegenError {
+            description,
+            method: self.signature.into(),
+        })
+    }
+
+    /// Attributes the bytecodes emitted next to `location`.
+    fn set_location(&mut self, location: Location) {
+        self.current_scope().location = location;
+    }
+
+    fn emit(&mut self, bytecode: Bytecode) {
+        let scope = self.current_scope();
+        let index = scope.code.bytecodes.len();
+        scope.code.line_table.push(index, scope.location);
+        scope.code.bytecodes.push(bytecode);
+    }
+
+    fn literal_index(&mut self, literal: Literal) -> Result<u8> {
+        let literals = &mut self.current_code().literals;
+        let index = match literals.iter().position(|l| *l == literal) {
+            Some(index) => index,
+            None => {
+                literals.push(literal);
+                literals.len() - 1
+            }
+        };
+
+        self.checked_index(index, "literals")
+    }
+
+    fn checked_index(&self, index: usize, kind: &str) -> Result<u8> {
+        if index > u8::MAX as usize {
+            self.error(format!("Too many {} in method", kind))
+        } else {
+            Ok(index as u8)
+        }
+    }
+
+    fn resolve(&self, name: &str) -> Result<Variable> {
+        let name = if name == "super" { "self" } else { name };
+
+        for (context, scope) in self.scopes.iter().rev().enumerate() {
+            let context = self.checked_index(context, "nested blocks")?;
+
+            if let Some(index) = scope.parameters.iter().position(|p| p == name) {
+                let index = self.checked_index(index, "parameters")?;
+                return Ok(Variable::Argument { index, context });
+            }
+
+            if let Some(index) = scope.locals.iter().position(|l| l == name) {
+                let index = self.checked_index(index, "locals")?;
+                return Ok(Variable::Local { index, context });
+            }
+        }
+
+        if let Some(index) = self.fields.iter().position(|f| f == name) {
+            let index = self.checked_index(index, "fields")?;
+            return Ok(Variable::Field { index });
+        }
+
+        Ok(Variable::Global)
+    }
+
+    fn generate_method_body(&mut self, body: &[ast::Expression]) -> Result<()> {
+        for expression in body {
+            self.generate_expression(expression)?;
+            if let ast::Expression::Return { .. } = expression {
+                return Ok(());
+            }
+
+            self.emit(Bytecode::Pop);
+        }
+
+        self.emit(Bytecode::PushArgument {
+            index: 0,
+            context: 0,
+        });
+        self.emit(Bytecode::ReturnLocal);
+
+        Ok(())
+    }
+
+    fn generate_block_body(&mut self, body: &[ast::Expression]) -> Result<()> {
+        if body.is_empty() {
+            self.generate_global("nil")?;
+            self.emit(Bytecode::ReturnLocal);
+            return Ok(());
+        }
+
+        for (i, expression) in body.iter().enumerate() {
+            self.generate_expression(expression)?;
+            if let ast::Expression::Return { .. } = expression {
+                return Ok(());
+            }
+
+            if i + 1 == body.len() {
+                self.emit(Bytecode::ReturnLocal);
+            } else {
+                self.emit(Bytecode::Pop);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn generate_expression(&mut self, expression: &ast::Expression) -> Result<()> {
+        match expression {
+            ast::Expression::Assignment {
+                variable,
+                value,
+                location,
+            } => {
+                self.generate_expression(value)?;
+                self.set_location(*location);
+                self.emit(Bytecode::Dup);
+                self.generate_store(variable)
+            }
+            ast::Expression::BinaryMessage {
+                message,
+                left,
+                right,
+                location,
+            } => self.generate_send(message, left, slice::from_ref(right.as_ref()), *location),
+            ast::Expression::Cascade { receiver, parts } => self.generate_cascade(receiver, parts),
+            ast::Expression::Block {
+                parameters,
+                locals,
+                body,
+                location,
+            } => {
+                self.push_scope("$block", parameters, locals, *location);
+                self.generate_block_body(body)?;
+                let block = self.pop_scope();
+
+                let blocks = &mut self.current_code().blocks;
+                blocks.push(block);
+                let index = blocks.len() - 1;
+                let index = self.checked_index(index, "blocks")?;
+                self.set_location(*location);
+                self.emit(Bytecode::PushBlock { index });
+
+                Ok(())
+            }
+            ast::Expression::KeywordMessage {
+                message,
+                receiver,
+                parameters,
+                location,
+            } => self.generate_send(message, receiver, parameters, *location),
+            ast::Expression::LiteralArray { values, location } => {
+                self.set_location(*location);
+                self.generate_array(values)
+            }
+            ast::Expression::LiteralBoolean { value, location } => {
+                self.set_location(*location);
+                self.generate_global(if *value { "true" } else { "false" })
+            }
+            ast::Expression::LiteralDouble { value, location } => {
+                self.set_location(*location);
+                self.generate_constant(Literal::Double(*value))
+            }
+            ast::Expression::LiteralInteger { value, location } => {
+                self.set_location(*location);
+                self.generate_constant(Literal::Integer(*value))
+            }
+            ast::Expression::LiteralLargeInteger { value, location } => {
+                self.set_location(*location);
+                self.generate_constant(Literal::LargeInteger(value.clone()))
+            }
+            ast::Expression::LiteralNil { location } => {
+                self.set_location(*location);
+                self.generate_global("nil")
+            }
+            ast::Expression::LiteralString { value, location } => {
+                self.set_location(*location);
+                self.generate_constant(Literal::String(value.clone()))
+            }
+            ast::Expression::LiteralSymbol { value, location } => {
+                self.set_location(*location);
+                self.generate_constant(Literal::Symbol(value.clone()))
+            }
+            ast::Expression::Return { value, location } => {
+                self.generate_expression(value)?;
+                self.set_location(*location);
+                if self.is_block() {
+                    self.emit(Bytecode::ReturnNonLocal);
+                } else {
+                    self.emit(Bytecode::ReturnLocal);
+                }
+
+                Ok(())
+            }
+            ast::Expression::UnaryMessage {
+                message,
+                receiver,
+                location,
+            } => self.generate_send(message, receiver, &[], *location),
+            ast::Expression::Variable { name, location } => {
+                self.set_location(*location);
+                self.generate_variable(name)
+            }
+        }
+    }
+
+    fn generate_array(&mut self, values: &[ast::Expression]) -> Result<()> {
+        match literal_array(values) {
+            Some(literal) => self.generate_constant(literal),
+            None => self.error("Literal array contains a non-literal expression".into()),
+        }
+    }
+
+    fn generate_constant(&mut self, literal: Literal) -> Result<()> {
+        let index = self.literal_index(literal)?;
+        self.emit(Bytecode::PushConstant { index });
+        Ok(())
+    }
+
+    fn generate_global(&mut self, name: &str) -> Result<()> {
+        let index = self.literal_index(Literal::Symbol(name.into()))?;
+        self.emit(Bytecode::PushGlobal { index });
+        Ok(())
+    }
+
+    fn generate_selector(&mut self, selector: &str, is_super: bool) -> Result<()> {
+        let index = self.literal_index(Literal::Symbol(selector.into()))?;
+        if is_super {
+            self.emit(Bytecode::SuperSend { index });
+        } else {
+            self.emit(Bytecode::Send { index });
+        }
+
+        Ok(())
+    }
+
+    fn generate_send(
+        &mut self,
+        selector: &str,
+        receiver: &ast::Expression,
+        parameters: &[ast::Expression],
+        location: Location,
+    ) -> Result<()> {
+        self.generate_expression(receiver)?;
+        for parameter in parameters {
+            self.generate_expression(parameter)?;
+        }
+
+        self.set_location(location);
+
+        let is_super = is_super(receiver);
+        self.generate_selector(selector, is_super)
+    }
+
+    /// Sends every message to the receiver kept on the stack, dropping all
+    /// results except the last.
+    fn generate_cascade(
+        &mut self,
+        receiver: &ast::Expression,
+        parts: &[Vec<ast::Message>],
+    ) -> Result<()> {
+        self.generate_expression(receiver)?;
+        let is_super = is_super(receiver);
+
+        for (i, part) in parts.iter().enumerate() {
+            let last = i == parts.len() - 1;
+            if !last {
+                self.emit(Bytecode::Dup);
+            }
+
+            // Only the first message goes to the receiver; the others are
+            // sent to the result of the one before.
+            for (j, message) in part.iter().enumerate() {
+                for parameter in &message.parameters {
+                    self.generate_expression(parameter)?;
+                }
+
+                self.set_location(message.location);
+                self.generate_selector(&message.message, is_super && j == 0)?;
+            }
+
+            if !last {
+                self.emit(Bytecode::Pop);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn generate_variable(&mut self, name: &str) -> Result<()> {
+        match self.resolve(name)? {
+            Variable::Argument { index, context } => {
+                self.emit(Bytecode::PushArgument { index, context })
+            }
+            Variable::Local { index, context } => self.emit(Bytecode::PushLocal { index, context }),
+            Variable::Field { index } => self.emit(Bytecode::PushField { index }),
+            Variable::Global => return self.generate_global(name),
+        }
+
+        Ok(())
+    }
+
+    fn generate_store(&mut self, name: &str) -> Result<()> {
+        match self.resolve(name)? {
+            Variable::Argument { index: 0, .. } => {
+                return self.error(format!("Cannot assign to {}", name))
+            }
+            Variable::Argument { index, context } => {
+                self.emit(Bytecode::PopArgument { index, context })
+            }
+            Variable::Local { index, context } => self.emit(Bytecode::PopLocal { index, context }),
+            Variable::Field { index } => self.emit(Bytecode::PopField { index }),
+            Variable::Global => return self.error(format!("Cannot assign to global {}", name)),
+        }
+
+        Ok(())
+    }
+}
+
+fn is_super(receiver: &ast::Expression) -> bool {
+    matches!(receiver, ast::Expression::Variable { name, .. } if name == "super")
+}
+
+/// Builds the literal for an array. The parser only accepts literals inside
+/// arrays, so this fails only for hand-built trees.
+fn literal_array(values: &[ast::Expression]) -> Option<Literal> {
+    values
+        .iter()
+        .map(|value| match value {
+            ast::Expression::LiteralArray { values, .. } => literal_array(values),
+            ast::Expression::LiteralBoolean { value, .. } => Some(Literal::Boolean(*value)),
+            ast::Expression::LiteralDouble { value, .. } => Some(Literal::Double(*value)),
+            ast::Expression::LiteralInteger { value, .. } => Some(Literal::Integer(*value)),
+            ast::Expression::LiteralLargeInteger { value, .. } => {
+                Some(Literal::LargeInteger(value.clone()))
+            }
+            ast::Expression::LiteralNil { .. } => Some(Literal::Nil),
+            ast::Expression::LiteralString { value, .. } => Some(Literal::String(value.clone())),
+            ast::Expression::LiteralSymbol { value, .. } => Some(Literal::Symbol(value.clone())),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()
+        .map(Literal::Array)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::Parser;
+
+    fn parse_method(source: &str) -> ast::Method {
+        let source = format!("Test = ( {} )", source);
+        let mut parser = Parser::new(source.as_bytes(), "test");
+        let class = parser.parse().unwrap();
+        class.instance_methods.values().next().unwrap().clone()
+    }
+
+    fn generate(source: &str, fields: &[&str]) -> CompiledCode {
+        let method = parse_method(source);
+        let fields = fields.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+        match generate_method(&fields, &method).unwrap() {
+            CompiledMethod::Bytecode { code, .. } => code,
+            m => panic!("unexpected method {:?}", m),
+        }
+    }
+
+    #[test]
+    fn test_generate_primitive_method() {
+        let method = parse_method("foo: a = primitive");
+        assert_eq!(
+            CompiledMethod::Primitive {
+                signature: "foo:".into(),
+                num_parameters: 1,
+            },
+            generate_method(&[], &method).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_generate_class_with_inherited_fields() {
+        let source = "Point3D = Point ( | z | z = ( ^ z ) x: value = ( x := value ) )";
+        let class = Parser::new(source.as_bytes(), "test").parse().unwrap();
+        let inherited = InheritedFields {
+            instance_fields: vec!["x".into(), "y".into()],
+            class_fields: vec![],
+        };
+        let class = generate_class(&class, "test", &inherited).unwrap();
+
+        assert_eq!(vec!["z"], class.instance_fields);
+        assert_eq!(inherited, class.inherited);
+        let bytecodes = class
+            .instance_methods
+            .iter()
+            .map(|method| match method {
+                CompiledMethod::Bytecode { code, .. } => code.bytecodes[..2].to_vec(),
+                m => panic!("unexpected method {:?}", m),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                vec![
+                    Bytecode::PushArgument {
+                        index: 1,
+                        context: 0
+                    },
+                    Bytecode::Dup,
+                ],
+                vec![Bytecode::PushField { index: 2 }, Bytecode::ReturnLocal],
+            ],
+            bytecodes
+        );
+    }
+
+    #[test]
+    fn test_generate_class_rejects_field_clash() {
+        let source = "Point3D = Point ( | y | )";
+        let class = Parser::new(source.as_bytes(), "test").parse().unwrap();
+        let inherited = InheritedFields {
+            instance_fields: vec!["x".into(), "y".into()],
+            class_fields: vec![],
+        };
+        assert_eq!(
+            CodegenError {
+                description: "Field y is already defined".into(),
+                method: "Point3D".into(),
+            },
+            generate_class(&class, "test", &inherited).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_generate_cascade() {
+        let source = "Test = ( foo = ( ^ self bar; baz: 1 ) )";
+        let mut parser = Parser::new(source.as_bytes(), "test");
+        parser.set_cascades_enabled(true);
+        let class = parser.parse().unwrap();
+        let method = class.instance_methods.values().next().unwrap();
+        let code = match generate_method(&[], method).unwrap() {
+            CompiledMethod::Bytecode { code, .. } => code,
+            m => panic!("unexpected method {:?}", m),
+        };
+        assert_eq!(
+            vec![
+                Bytecode::PushArgument {
+                    index: 0,
+                    context: 0
+                },
+                Bytecode::Dup,
+                Bytecode::Send { index: 0 },
+                Bytecode::Pop,
+                Bytecode::PushConstant { index: 1 },
+                Bytecode::Send { index: 2 },
+                Bytecode::ReturnLocal,
+            ],
+            code.bytecodes
+        );
+    }
+
+    #[test]
+    fn test_generate_implicit_return_self() {
+        let code = generate("foo = ( 1 )", &[]);
+        assert_eq!(
+            vec![
+                Bytecode::PushConstant { index: 0 },
+                Bytecode::Pop,
+                Bytecode::PushArgument {
+                    index: 0,
+                    context: 0
+                },
+                Bytecode::ReturnLocal,
+            ],
+            code.bytecodes
+        );
+        assert_eq!(vec![Literal::Integer(1)], code.literals);
+    }
+
+    #[test]
+    fn test_generate_variables() {
+        let code = generate("foo: a = ( | b | b := a. ^ c )", &["c"]);
+        assert_eq!(1, code.num_parameters);
+        assert_eq!(1, code.num_locals);
+        assert_eq!(
+            vec![
+                Bytecode::PushArgument {
+                    index: 1,
+                    context: 0
+                },
+                Bytecode::Dup,
+                Bytecode::PopLocal {
+                    index: 0,
+                    context: 0
+                },
+                Bytecode::Pop,
+                Bytecode::PushField { index: 0 },
+                Bytecode::ReturnLocal,
+            ],
+            code.bytecodes
+        );
+    }
+
+    #[test]
+    fn test_generate_sends_share_literals() {
+        let code = generate("foo = ( ^ self bar: 1 baz: (Global bar: 1 baz: 2) )", &[]);
+        assert_eq!(
+            vec![
+                Literal::Integer(1),
+                Literal::Symbol("Global".into()),
+                Literal::Integer(2),
+                Literal::Symbol("bar:baz:".into()),
+            ],
+            code.literals
+        );
+    }
+
+    #[test]
+    fn test_generate_super_send() {
+        let code = generate("foo = ( ^ super foo )", &[]);
+        assert_eq!(
+            vec![
+                Bytecode::PushArgument {
+                    index: 0,
+                    context: 0
+                },
+                Bytecode::SuperSend { index: 0 },
+                Bytecode::ReturnLocal,
+            ],
+            code.bytecodes
+        );
+    }
+
+    #[test]
+    fn test_generate_nested_blocks() {
+        let code = generate("foo: a = ( ^ [ :b | [ a + b. ^ self ] ] )", &[]);
+        assert_eq!(
+            vec![Bytecode::PushBlock { index: 0 }, Bytecode::ReturnLocal],
+            code.bytecodes
+        );
+
+        let outer = &code.blocks[0];
+        assert_eq!(1, outer.num_parameters);
+        assert_eq!(
+            vec![Bytecode::PushBlock { index: 0 }, Bytecode::ReturnLocal],
+            outer.bytecodes
+        );
+
+        let inner = &outer.blocks[0];
+        assert_eq!(
+            vec![
+                Bytecode::PushArgument {
+                    index: 1,
+                    context: 2
+                },
+                Bytecode::PushArgument {
+                    index: 1,
+                    context: 1
+                },
+                Bytecode::Send { index: 0 },
+                Bytecode::Pop,
+                Bytecode::PushArgument {
+                    index: 0,
+                    context: 2
+                },
+                Bytecode::ReturnNonLocal,
+            ],
+            inner.bytecodes
+        );
+    }
+
+    #[test]
+    fn test_generate_empty_block_returns_nil() {
+        let code = generate("foo = ( ^ [] )", &[]);
+        assert_eq!(
+            vec![Bytecode::PushGlobal { index: 0 }, Bytecode::ReturnLocal],
+            code.blocks[0].bytecodes
+        );
+        assert_eq!(vec![Literal::Symbol("nil".into())], code.blocks[0].literals);
+    }
+
+    #[test]
+    fn test_generate_literal_array() {
+        let code = generate("foo = ( ^ #(true 1 #(nil 2.5 'a') #b) )", &[]);
+        assert_eq!(
+            vec![Bytecode::PushConstant { index: 0 }, Bytecode::ReturnLocal],
+            code.bytecodes
+        );
+        assert_eq!(
+            vec![Literal::Array(vec![
+                Literal::Boolean(true),
+                Literal::Integer(1),
+                Literal::Array(vec![
+                    Literal::Nil,
+                    Literal::Double(2.5),
+                    Literal::String("a".into())
+                ]),
+                Literal::Symbol("b".into()),
+            ])],
+            code.literals
+        );
+    }
+
+    #[test]
+    fn test_generate_nested_literal_array() {
+        let code = generate("foo = ( ^ #(1 (2 + foo)) )", &[]);
+        assert_eq!(
+            vec![Bytecode::PushConstant { index: 0 }, Bytecode::ReturnLocal],
+            code.bytecodes
+        );
+        assert_eq!(
+            vec![Literal::Array(vec![
+                Literal::Integer(1),
+                Literal::Array(vec![
+                    Literal::Integer(2),
+                    Literal::Symbol("+".into()),
+                    Literal::Symbol("foo".into()),
+                ]),
+            ])],
+            code.literals
+        );
+    }
+
+    #[test]
+    fn test_generate_line_table() {
+        let code = generate("foo = (\n    1 bar.\n    ^ 2 baz: [ 3 qux ] )\n", &[]);
+        let location = |line, column| Some(Location { line, column });
+
+        assert_eq!(location(2, 4), code.location(0));
+        assert_eq!(location(2, 6), code.location(1));
+        assert_eq!(Bytecode::Send { index: 3 }, code.bytecodes[5]);
+        assert_eq!(location(3, 8), code.location(5));
+
+        let block = &code.blocks[0];
+        assert_eq!(location(3, 15), block.location(0));
+        assert_eq!(location(3, 17), block.location(1));
+    }
+
+    #[test]
+    fn test_generate_line_table_without_sends() {
+        let source = "foo = ( | x |\n    self bar.\n    x := 1.\n    ^ Missing )\n";
+        let code = generate(source, &[]);
+        let location = |line, column| Some(Location { line, column });
+        let index = |bytecode| code.bytecodes.iter().position(|&b| b == bytecode).unwrap();
+
+        let store = index(Bytecode::PopLocal {
+            index: 0,
+            context: 0,
+        });
+        assert_eq!(location(3, 4), code.location(store));
+        assert_eq!(
+            location(4, 6),
+            code.location(index(Bytecode::PushGlobal { index: 2 }))
+        );
+        assert_eq!(location(4, 4), code.location(index(Bytecode::ReturnLocal)));
+    }
+
+    #[test]
+    fn test_generate_assignment_to_global_fails() {
+        let method = parse_method("foo = ( Foo := 1 )");
+        assert_eq!(
+            CodegenError {
+                description: "Cannot assign to global Foo".into(),
+                method: "foo".into(),
+            },
+            generate_method(&[], &method).unwrap_err()
+        );
+    }
+}
@@ -0,0 +1,176 @@
+use crate::compiler::Location;
+use crate::interpreter::Bytecode;
+use num_bigint::BigInt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompiledClass {
+    pub name: String,
+    pub filename: String,
+    pub superclass: Option<String>,
+    /// The superclass fields the methods were compiled against. Field
+    /// indices count these first, followed by the fields declared here.
+    pub inherited: InheritedFields,
+    pub instance_fields: Vec<String>,
+    pub instance_methods: Vec<CompiledMethod>,
+    pub class_fields: Vec<String>,
+    pub class_methods: Vec<CompiledMethod>,
+}
+
+/// The fields of a superclass, on the instance side and on the class side.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InheritedFields {
+    pub instance_fields: Vec<String>,
+    pub class_fields: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CompiledMethod {
+    Primitive {
+        signature: String,
+        num_parameters: usize,
+    },
+    Bytecode {
+        signature: String,
+        code: CompiledCode,
+    },
+}
+
+impl CompiledMethod {
+    pub fn signature(&self) -> &str {
+        match self {
+            CompiledMethod::Primitive { signature, .. } => signature,
+            CompiledMethod::Bytecode { signature, .. } => signature,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CompiledCode {
+    pub num_parameters: usize,
+    pub num_locals: usize,
+    pub literals: Vec<Literal>,
+    pub bytecodes: Vec<Bytecode>,
+    pub blocks: Vec<CompiledCode>,
+    pub line_table: LineTable,
+}
+
+impl CompiledCode {
+    pub fn location(&self, bytecode_index: usize) -> Option<Location> {
+        self.line_table.location(bytecode_index)
+    }
+}
+
+/// Maps bytecode indices to source locations. Only the first bytecode of
+/// each run sharing a location gets an entry, lookups find the closest
+/// entry at or before the index.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LineTable {
+    entries: Vec<LineEntry>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LineEntry {
+    pub bytecode_index: usize,
+    pub location: Location,
+}
+
+impl LineTable {
+    pub fn from_entries(entries: Vec<LineEntry>) -> LineTable {
+        LineTable { entries }
+    }
+
+    pub fn entries(&self) -> &[LineEntry] {
+        &self.entries
+    }
+
+    pub fn push(&mut self, bytecode_index: usize, location: Location) {
+        match self.entries.last_mut() {
+            Some(last) if last.location == location => return,
+            Some(last) if last.bytecode_index == bytecode_index => {
+                last.location = location;
+                return;
+            }
+            _ => {}
+        }
+
+        self.entries.push(LineEntry {
+            bytecode_index,
+            location,
+        });
+    }
+
+    pub fn location(&self, bytecode_index: usize) -> Option<Location> {
+        let position = self
+            .entries
+            .partition_point(|entry| entry.bytecode_index <= bytecode_index);
+
+        if position == 0 {
+            None
+        } else {
+            Some(self.entries[position - 1].location)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Literal {
+    Array(Vec<Literal>),
+    Boolean(bool),
+    Double(f64),
+    Integer(i64),
+    LargeInteger(BigInt),
+    Nil,
+    String(String),
+    Symbol(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(line: usize, column: usize) -> Location {
+        Location { line, column }
+    }
+
+    #[test]
+    fn test_line_table_lookup() {
+        let mut table = LineTable::default();
+        table.push(0, location(1, 4));
+        table.push(3, location(2, 8));
+
+        assert_eq!(Some(location(1, 4)), table.location(0));
+        assert_eq!(Some(location(1, 4)), table.location(2));
+        assert_eq!(Some(location(2, 8)), table.location(3));
+        assert_eq!(Some(location(2, 8)), table.location(10));
+    }
+
+    #[test]
+    fn test_line_table_lookup_before_first_entry() {
+        let mut table = LineTable::default();
+        table.push(2, location(1, 4));
+        assert_eq!(None, table.location(1));
+    }
+
+    #[test]
+    fn test_line_table_compacts_runs() {
+        let mut table = LineTable::default();
+        table.push(0, location(1, 4));
+        table.push(1, location(1, 4));
+        table.push(2, location(3, 0));
+        table.push(2, location(3, 2));
+
+        assert_eq!(
+            &[
+                LineEntry {
+                    bytecode_index: 0,
+                    location: location(1, 4)
+                },
+                LineEntry {
+                    bytecode_index: 2,
+                    location: location(3, 2)
+                },
+            ],
+            table.entries()
+        );
+    }
+}
@@ -0,0 +1,336 @@
+use crate::compiler::compiled::{CompiledClass, CompiledMethod};
+use crate::compiler::{ast, Location};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The selectors each class implements with a primitive. Folding only
+/// trusts a selector when the receiver's class declares it `primitive`, so a
+/// class library that redefines `Integer>>+` in SOM keeps its semantics.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct KnownPrimitives {
+    classes: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl KnownPrimitives {
+    pub fn add_class(&mut self, class: &CompiledClass) {
+        for method in &class.instance_methods {
+            if let CompiledMethod::Primitive { signature, .. } = method {
+                self.insert(&class.name, signature);
+            }
+        }
+    }
+
+    pub fn insert(&mut self, class: &str, selector: &str) {
+        self.classes
+            .entry(class.into())
+            .or_default()
+            .insert(selector.into());
+    }
+
+    pub fn contains(&self, class: &str, selector: &str) -> bool {
+        self.classes
+            .get(class)
+            .is_some_and(|selectors| selectors.contains(selector))
+    }
+
+    pub fn classes(&self) -> &BTreeMap<String, BTreeSet<String>> {
+        &self.classes
+    }
+}
+
+#[derive(Copy, Clone)]
+enum Number {
+    Integer(i64),
+    Double(f64),
+}
+
+impl Number {
+    fn class_name(self) -> &'static str {
+        match self {
+            Number::Integer(_) => "Integer",
+            Number::Double(_) => "Double",
+        }
+    }
+
+    fn as_f64(self) -> f64 {
+        match self {
+            Number::Integer(value) => value as f64,
+            Number::Double(value) => value,
+        }
+    }
+}
+
+pub fn fold_class(class: &mut ast::Class, primitives: &KnownPrimitives) {
+    let methods = class
+        .instance_methods
+        .values_mut()
+        .chain(class.class_methods.values_mut());
+
+    for method in methods {
+        if let ast::Method::Native { body, .. } = method {
+            for expression in body {
+                fold_expression(expression, primitives);
+            }
+        }
+    }
+}
+
+fn fold_expression(expression: &mut ast::Expression, primitives: &KnownPrimitives) {
+    let folded = match expression {
+        ast::Expression::Assignment { value, .. } | ast::Expression::Return { value, .. } => {
+            fold_expression(value, primitives);
+            None
+        }
+        ast::Expression::BinaryMessage {
+            message,
+            left,
+            right,
+            ..
+        } => {
+            fold_expression(left, primitives);
+            fold_expression(right, primitives);
+            fold_binary(message, left, right, primitives)
+        }
+        ast::Expression::Block { body, .. } => {
+            for expression in body {
+                fold_expression(expression, primitives);
+            }
+            None
+        }
+        ast::Expression::Cascade { receiver, parts } => {
+            fold_expression(receiver, primitives);
+            let messages = parts.iter_mut().flatten();
+            for parameter in messages.flat_map(|m| m.parameters.iter_mut()) {
+                fold_expression(parameter, primitives);
+            }
+            None
+        }
+        ast::Expression::KeywordMessage {
+            receiver,
+            parameters,
+            ..
+        } => {
+            fold_expression(receiver, primitives);
+            for parameter in parameters {
+                fold_expression(parameter, primitives);
+            }
+            None
+        }
+        ast::Expression::UnaryMessage { receiver, .. } => {
+            fold_expression(receiver, primitives);
+            None
+        }
+        _ => None,
+    };
+
+    if let Some(folded) = folded {
+        *expression = folded;
+    }
+}
+
+fn number(expression: &ast::Expression) -> Option<(Number, Location)> {
+    match *expression {
+        ast::Expression::LiteralInteger { value, location } => {
+            Some((Number::Integer(value), location))
+        }
+        ast::Expression::LiteralDouble { value, location } => {
+            Some((Number::Double(value), location))
+        }
+        _ => None,
+    }
+}
+
+fn fold_binary(
+    selector: &str,
+    left: &ast::Expression,
+    right: &ast::Expression,
+    primitives: &KnownPrimitives,
+) -> Option<ast::Expression> {
+    // the folded literal takes the place of the whole send
+    let (left, location) = number(left)?;
+    let (right, _) = number(right)?;
+    if !primitives.contains(left.class_name(), selector) {
+        return None;
+    }
+
+    match (left, right) {
+        (Number::Integer(a), Number::Integer(b)) => fold_integers(selector, a, b, location),
+        (a, b) => fold_doubles(selector, a.as_f64(), b.as_f64(), location),
+    }
+}
+
+fn fold_integers(selector: &str, a: i64, b: i64, location: Location) -> Option<ast::Expression> {
+    let value = match selector {
+        "+" => a.checked_add(b)?,
+        "-" => a.checked_sub(b)?,
+        "*" => a.checked_mul(b)?,
+        _ => return fold_comparison(selector, a.cmp(&b), location),
+    };
+
+    Some(ast::Expression::LiteralInteger { value, location })
+}
+
+fn fold_doubles(selector: &str, a: f64, b: f64, location: Location) -> Option<ast::Expression> {
+    let result = match selector {
+        "+" => a + b,
+        "-" => a - b,
+        "*" => a * b,
+        "//" if b != 0.0 => a / b,
+        _ => return fold_comparison(selector, a.partial_cmp(&b)?, location),
+    };
+
+    if result.is_finite() {
+        Some(ast::Expression::LiteralDouble {
+            value: result,
+            location,
+        })
+    } else {
+        None
+    }
+}
+
+fn fold_comparison(
+    selector: &str,
+    ordering: std::cmp::Ordering,
+    location: Location,
+) -> Option<ast::Expression> {
+    use std::cmp::Ordering::*;
+
+    let result = match selector {
+        "<" => ordering == Less,
+        ">" => ordering == Greater,
+        "<=" => ordering != Greater,
+        ">=" => ordering != Less,
+        "=" => ordering == Equal,
+        "~=" => ordering != Equal,
+        _ => return None,
+    };
+
+    Some(ast::Expression::LiteralBoolean {
+        value: result,
+        location,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::Parser;
+
+    fn primitives() -> KnownPrimitives {
+        let mut primitives = KnownPrimitives::default();
+        for selector in &["+", "-", "*", "<", "="] {
+            primitives.insert("Integer", selector);
+        }
+        for selector in &["+", "//"] {
+            primitives.insert("Double", selector);
+        }
+
+        primitives
+    }
+
+    fn fold(source: &str) -> Vec<ast::Expression> {
+        let source = format!("Test = ( test = ( {} ) )", source);
+        let mut parser = Parser::new(source.as_bytes(), "test");
+        let mut class = parser.parse().unwrap();
+        fold_class(&mut class, &primitives());
+
+        match class.instance_methods.remove("test").unwrap() {
+            ast::Method::Native { body, .. } => body,
+            m => panic!("unexpected method {:?}", m),
+        }
+    }
+
+    /// The location of column `column` of the folded statement.
+    fn at(column: usize) -> Location {
+        Location {
+            line: 1,
+            column: 18 + column,
+        }
+    }
+
+    #[test]
+    fn test_fold_integer_arithmetic() {
+        assert_eq!(
+            vec![ast::Expression::Return {
+                value: Box::new(ast::Expression::LiteralInteger {
+                    value: 11,
+                    location: at(2)
+                }),
+                location: at(0)
+            }],
+            fold("^ 1 + 2 * 3 + 2")
+        );
+    }
+
+    #[test]
+    fn test_fold_nested_terms_and_comparisons() {
+        assert_eq!(
+            vec![ast::Expression::LiteralBoolean {
+                value: true,
+                location: at(1)
+            }],
+            fold("(3 - 1) < (2 * 2)")
+        );
+    }
+
+    #[test]
+    fn test_fold_mixed_arithmetic() {
+        let expected = vec![ast::Expression::LiteralDouble {
+            value: 3.5,
+            location: at(0),
+        }];
+        assert_eq!(expected, fold("1.5 + 2"));
+        assert_eq!(expected, fold("1 + 2.5"));
+    }
+
+    #[test]
+    fn test_fold_requires_known_primitive() {
+        match fold("1.5 - 2").remove(0) {
+            ast::Expression::BinaryMessage { message, .. } => assert_eq!("-", message),
+            e => panic!("unexpected expression {:?}", e),
+        }
+
+        match fold("4 // 2").remove(0) {
+            ast::Expression::BinaryMessage { message, .. } => assert_eq!("//", message),
+            e => panic!("unexpected expression {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_fold_skips_overflow_and_division_by_zero() {
+        match fold("9223372036854775807 + 1").remove(0) {
+            ast::Expression::BinaryMessage { message, .. } => assert_eq!("+", message),
+            e => panic!("unexpected expression {:?}", e),
+        }
+
+        match fold("1.0 // 0.0").remove(0) {
+            ast::Expression::BinaryMessage { message, .. } => assert_eq!("//", message),
+            e => panic!("unexpected expression {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_fold_inside_blocks_not_arrays() {
+        match fold("[ 1 + 1 ]. #(2 * 2)").as_slice() {
+            [ast::Expression::Block { body, .. }, ast::Expression::LiteralArray { values, .. }] => {
+                let integer = |value, column| ast::Expression::LiteralInteger {
+                    value,
+                    location: at(column),
+                };
+                assert_eq!(vec![integer(2, 2)], *body);
+                assert_eq!(
+                    vec![
+                        integer(2, 13),
+                        ast::Expression::LiteralSymbol {
+                            value: "*".into(),
+                            location: at(15)
+                        },
+                        integer(2, 17)
+                    ],
+                    *values
+                );
+            }
+            e => panic!("unexpected expressions {:?}", e),
+        }
+    }
+}
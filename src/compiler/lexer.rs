@@ -1,6 +1,9 @@
 use crate::compiler::{Location, Token, TokenKind};
+use num_bigint::BigInt;
 use std::collections::VecDeque;
-use std::io::{BufRead, Result};
+use std::error;
+use std::fmt;
+use std::io::{BufRead, Error, ErrorKind, Result};
 
 trait IsOperatorExt {
     fn is_operator(&self) -> bool;
@@ -8,27 +11,68 @@ trait IsOperatorExt {
 
 impl IsOperatorExt for char {
     fn is_operator(&self) -> bool {
-        match *self {
-            '~' | '&' | '|' | '*' | '/' | '\\' | '+' | '=' | '>' | '<' | ',' | '@' | '%' | '-' => {
-                true
-            }
-            _ => false,
-        }
+        matches!(
+            *self,
+            '~' | '&' | '|' | '*' | '/' | '\\' | '+' | '=' | '>' | '<' | ',' | '@' | '%' | '-'
+        )
+    }
+}
+
+fn is_decimal_digit(c: char) -> bool {
+    c.is_ascii_digit()
+}
+
+fn is_radix_digit(c: char) -> bool {
+    c.is_ascii_digit() || c.is_ascii_uppercase()
+}
+
+fn invalid_number(description: String) -> Error {
+    Error::new(ErrorKind::InvalidData, description)
+}
+
+fn invalid_string(description: String) -> Error {
+    Error::new(ErrorKind::InvalidData, description)
+}
+
+/// The decimal digits of `digits` read in base `radix`.
+fn radix_integer(radix: &str, digits: &str) -> Result<String> {
+    let base = match radix.parse::<u32>() {
+        Ok(base) if (2..=36).contains(&base) => base,
+        _ => return Err(invalid_number(format!("Invalid radix {}", radix))),
+    };
+
+    match BigInt::parse_bytes(digits.as_bytes(), base) {
+        Some(value) => Ok(value.to_string()),
+        None => Err(invalid_number(format!(
+            "Invalid digits {} for radix {}",
+            digits, base
+        ))),
     }
 }
 
+/// The decimal digits of `mantissa` times ten to the `exponent`.
+fn scaled_integer(mantissa: &str, exponent: &str) -> Result<String> {
+    let exponent = exponent
+        .parse::<u16>()
+        .map_err(|_| invalid_number(format!("Exponent {} is too large", exponent)))?;
+    let mantissa = mantissa.parse::<BigInt>().expect("decimal digits");
+
+    Ok((mantissa * BigInt::from(10).pow(u32::from(exponent))).to_string())
+}
+
 struct PeekableBuffer<R: BufRead> {
     reader: R,
     position: usize,
     line: usize,
-    buffer: String,
+    /// The current line, as characters so positions are columns.
+    buffer: Vec<char>,
 }
 
 impl<R: BufRead> PeekableBuffer<R> {
     fn new(reader: R) -> PeekableBuffer<R> {
         PeekableBuffer {
             reader,
-            buffer: String::new(),
+            buffer: vec![],
             position: 0,
             line: 0,
         }
@@ -36,8 +80,13 @@ impl<R: BufRead> PeekableBuffer<R> {
 
     fn peek(&mut self) -> Result<Option<char>> {
         self.fill_buffer()?;
-        let c = self.buffer.chars().nth(self.position);
-        Ok(c)
+        Ok(self.buffer.get(self.position).copied())
+    }
+
+    /// Looks `n` characters past the next one, without leaving the line.
+    fn peek_nth(&mut self, n: usize) -> Result<Option<char>> {
+        self.fill_buffer()?;
+        Ok(self.buffer.get(self.position + n).copied())
     }
 
     fn consume(&mut self) -> Result<()> {
@@ -49,8 +98,9 @@ impl<R: BufRead> PeekableBuffer<R> {
 
     fn fill_buffer(&mut self) -> Result<()> {
         if self.position >= self.buffer.len() {
-            self.buffer.clear();
-            self.reader.read_line(&mut self.buffer)?;
+            let mut line = String::new();
+            self.reader.read_line(&mut line)?;
+            self.buffer = line.chars().collect();
             self.line += 1;
             self.position = 0;
         }
@@ -66,6 +116,26 @@ impl<R: BufRead> PeekableBuffer<R> {
     }
 }
 
+/// The payload of the errors the lexer returns: what it could not read and
+/// where it stopped.
+#[derive(Debug)]
+pub struct LexerError {
+    pub description: String,
+    pub location: Location,
+}
+
+impl fmt::Display for LexerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at {}:{}",
+            self.description, self.location.line, self.location.column
+        )
+    }
+}
+
+impl error::Error for LexerError {}
+
 pub struct Lexer<R: BufRead> {
     buffer: PeekableBuffer<R>,
     queue: VecDeque<Token>,
@@ -78,7 +148,13 @@ impl<R: BufRead> Iterator for Lexer<R> {
         match self.read_token() {
             Ok(Some(t)) => Some(Ok(t)),
             Ok(None) => None,
-            Err(e) => Some(Err(e)),
+            Err(e) => Some(Err(Error::new(
+                e.kind(),
+                LexerError {
+                    description: e.to_string(),
+                    location: self.buffer.current_location(),
+                },
+            ))),
         }
     }
 }
@@ -117,6 +193,7 @@ impl<R: BufRead> Lexer<R> {
             '#' => self.read_symbol(TokenKind::Pound),
             '^' => self.read_symbol(TokenKind::Exit),
             '.' => self.read_symbol(TokenKind::Period),
+            ';' => self.read_symbol(TokenKind::Semicolon),
             ':' => self.read_colon(),
             '\'' => self.read_string(),
             c if c.is_ascii_digit() => self.read_number(),
@@ -183,49 +260,72 @@ impl<R: BufRead> Lexer<R> {
         Ok(Some(token))
     }
 
-    fn read_number(&mut self) -> Result<Option<Token>> {
-        let location = self.buffer.current_location();
-        let mut text = String::new();
-
+    fn read_digits(&mut self, is_digit: fn(char) -> bool) -> Result<String> {
+        let mut digits = String::new();
         loop {
             match self.buffer.peek()? {
-                Some(c) if c.is_ascii_digit() => {
-                    text.push(c);
+                Some(c) if is_digit(c) => {
+                    digits.push(c);
                     self.buffer.consume()?;
                 }
                 _ => break,
             }
         }
 
-        if let Some('.') = self.buffer.peek()? {
-            let period_location = self.buffer.current_location();
-            self.buffer.consume()?;
+        Ok(digits)
+    }
 
-            match self.buffer.peek()? {
-                Some(c) if c.is_ascii_digit() => {
-                    text.push('.');
+    /// Reads `123`, `3.14`, radix integers like `16r1F` and exponents like
+    /// `1.5e10` or `2e-3`. Radix integers and integers with a positive
+    /// exponent are normalised to decimal digits, so the parser only ever
+    /// sees plain integers and text Rust can parse as a double.
+    fn read_number(&mut self) -> Result<Option<Token>> {
+        let location = self.buffer.current_location();
+        let mut text = self.read_digits(is_decimal_digit)?;
 
-                    loop {
-                        match self.buffer.peek()? {
-                            Some(c) if c.is_ascii_digit() => {
-                                text.push(c);
-                                self.buffer.consume()?;
-                            }
-                            _ => break,
-                        }
-                    }
+        if let Some('r') = self.buffer.peek()? {
+            if self.buffer.peek_nth(1)?.is_some_and(is_radix_digit) {
+                self.buffer.consume()?;
+                let digits = self.read_digits(is_radix_digit)?;
+                let text = radix_integer(&text, &digits)?;
+                return Ok(Some(Token::new(TokenKind::Integer, Some(text), location)));
+            }
+        }
 
-                    Ok(Some(Token::new(TokenKind::Double, Some(text), location)))
+        let mut kind = TokenKind::Integer;
+        if let Some('.') = self.buffer.peek()? {
+            if self.buffer.peek_nth(1)?.is_some_and(is_decimal_digit) {
+                self.buffer.consume()?;
+                text.push('.');
+                text.push_str(&self.read_digits(is_decimal_digit)?);
+                kind = TokenKind::Double;
+            }
+        }
+
+        if let Some('e') = self.buffer.peek()? {
+            let negative = self.buffer.peek_nth(1)? == Some('-');
+            let first_digit = if negative { 2 } else { 1 };
+            if self
+                .buffer
+                .peek_nth(first_digit)?
+                .is_some_and(is_decimal_digit)
+            {
+                for _ in 0..first_digit {
+                    self.buffer.consume()?;
                 }
-                _ => {
-                    self.queue
-                        .push_back(Token::new(TokenKind::Period, None, period_location));
-                    Ok(Some(Token::new(TokenKind::Integer, Some(text), location)))
+
+                let exponent = self.read_digits(is_decimal_digit)?;
+                if kind == TokenKind::Integer && !negative {
+                    text = scaled_integer(&text, &exponent)?;
+                } else {
+                    let sign = if negative { "-" } else { "" };
+                    text = format!("{}e{}{}", text, sign, exponent);
+                    kind = TokenKind::Double;
                 }
             }
-        } else {
-            Ok(Some(Token::new(TokenKind::Integer, Some(text), location)))
         }
+
+        Ok(Some(Token::new(kind, Some(text), location)))
     }
 
     fn read_operator(&mut self) -> Result<Option<Token>> {
@@ -286,10 +386,11 @@ impl<R: BufRead> Lexer<R> {
             let c = self.buffer.peek()?;
             self.buffer.consume()?;
             match c {
-                Some('\\') => {
-                    if let Some(c) = self.read_string_escape()? {
-                        text.push(c)
-                    }
+                Some('\\') => text.push(self.read_string_escape()?),
+                // a doubled quote stands for one quote, as in Smalltalk
+                Some('\'') if self.buffer.peek()? == Some('\'') => {
+                    self.buffer.consume()?;
+                    text.push('\'');
                 }
                 Some(c) if c != '\'' => text.push(c),
                 _ => break,
@@ -299,22 +400,57 @@ impl<R: BufRead> Lexer<R> {
         Ok(Some(Token::new(TokenKind::String, Some(text), location)))
     }
 
-    fn read_string_escape(&mut self) -> Result<Option<char>> {
+    fn read_string_escape(&mut self) -> Result<char> {
         let c = self.buffer.peek()?;
         self.buffer.consume()?;
         let result = match c {
-            Some('\'') => Some('\''),
-            Some('\\') => Some('\\'),
-            Some('b') => Some('\x08'),
-            Some('f') => Some('\x0c'),
-            Some('n') => Some('\n'),
-            Some('r') => Some('\r'),
-            Some('t') => Some('\t'),
-            _ => None,
+            Some('\'') => '\'',
+            Some('\\') => '\\',
+            Some('0') => '\0',
+            Some('b') => '\x08',
+            Some('f') => '\x0c',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('u') => self.read_unicode_escape()?,
+            Some(c) => return Err(invalid_string(format!("Unknown escape \\{}", c))),
+            None => return Err(invalid_string("Unterminated escape".into())),
         };
         Ok(result)
     }
 
+    /// Reads the code point of `\uXXXX` or `\u{X...}`, after the `u`.
+    fn read_unicode_escape(&mut self) -> Result<char> {
+        let mut digits = String::new();
+        if self.buffer.peek()? == Some('{') {
+            self.buffer.consume()?;
+            loop {
+                let c = self.buffer.peek()?;
+                self.buffer.consume()?;
+                match c {
+                    Some('}') => break,
+                    Some(c) if c.is_ascii_hexdigit() && digits.len() < 6 => digits.push(c),
+                    _ => return Err(invalid_string("Invalid \\u{...} escape".into())),
+                }
+            }
+        } else {
+            for _ in 0..4 {
+                match self.buffer.peek()? {
+                    Some(c) if c.is_ascii_hexdigit() => {
+                        digits.push(c);
+                        self.buffer.consume()?;
+                    }
+                    _ => return Err(invalid_string("Invalid \\uXXXX escape".into())),
+                }
+            }
+        }
+
+        u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(std::char::from_u32)
+            .ok_or_else(|| invalid_string(format!("Invalid code point {}", digits)))
+    }
+
     fn read_symbol(&mut self, kind: TokenKind) -> Result<Option<Token>> {
         let location = self.buffer.current_location();
         self.buffer.consume()?;
@@ -341,7 +477,7 @@ mod tests {
     #[test]
     fn test_next_skips_whitespace() {
         let source = b"\n Hello \n Test";
-        let mut lexer = Lexer::new(source.as_ref());;
+        let mut lexer = Lexer::new(source.as_ref());
 
         let token = lexer.next().unwrap().unwrap();
         assert_eq!(TokenKind::Identifier, token.kind);
@@ -355,7 +491,7 @@ mod tests {
     #[test]
     fn test_next_skips_comments() {
         let source = b"\"Test\" Hello \"123\"Test";
-        let mut lexer = Lexer::new(source.as_ref());;
+        let mut lexer = Lexer::new(source.as_ref());
 
         let token = lexer.next().unwrap().unwrap();
         assert_eq!(TokenKind::Identifier, token.kind);
@@ -369,7 +505,7 @@ mod tests {
     #[test]
     fn test_next_saves_current_location() {
         let source = b" \n  World";
-        let mut lexer = Lexer::new(source.as_ref());;
+        let mut lexer = Lexer::new(source.as_ref());
         let token = lexer.next().unwrap().unwrap();
         assert_eq!(Location { line: 2, column: 2 }, token.location);
     }
@@ -377,7 +513,7 @@ mod tests {
     #[test]
     fn test_next_reads_identifier() {
         let source = b"Test";
-        let mut lexer = Lexer::new(source.as_ref());;
+        let mut lexer = Lexer::new(source.as_ref());
         let token = lexer.next().unwrap().unwrap();
         assert_eq!(TokenKind::Identifier, token.kind);
         assert_eq!("Test", token.text.unwrap());
@@ -386,7 +522,7 @@ mod tests {
     #[test]
     fn test_next_reads_keyword() {
         let source = b"test:";
-        let mut lexer = Lexer::new(source.as_ref());;
+        let mut lexer = Lexer::new(source.as_ref());
         let token = lexer.next().unwrap().unwrap();
         assert_eq!(TokenKind::Keyword, token.kind);
         assert_eq!("test:", token.text.unwrap());
@@ -395,7 +531,7 @@ mod tests {
     #[test]
     fn test_next_reads_two_keyword_sequence() {
         let source = b"foo:bar:";
-        let mut lexer = Lexer::new(source.as_ref());;
+        let mut lexer = Lexer::new(source.as_ref());
         let token = lexer.next().unwrap().unwrap();
         assert_eq!(TokenKind::KeywordSequence, token.kind);
         assert_eq!("foo:bar:", token.text.unwrap());
@@ -404,7 +540,7 @@ mod tests {
     #[test]
     fn test_next_reads_three_keyword_sequence() {
         let source = b"foo:bar:baz:";
-        let mut lexer = Lexer::new(source.as_ref());;
+        let mut lexer = Lexer::new(source.as_ref());
         let token = lexer.next().unwrap().unwrap();
         assert_eq!(TokenKind::KeywordSequence, token.kind);
         assert_eq!("foo:bar:baz:", token.text.unwrap());
@@ -413,7 +549,7 @@ mod tests {
     #[test]
     fn test_next_reads_primitive() {
         let source = b"primitive";
-        let mut lexer = Lexer::new(source.as_ref());;
+        let mut lexer = Lexer::new(source.as_ref());
         let token = lexer.next().unwrap().unwrap();
         assert_eq!(TokenKind::Primitive, token.kind);
         assert_eq!(None, token.text);
@@ -422,7 +558,7 @@ mod tests {
     #[test]
     fn test_next_reads_minus() {
         let source = b"-";
-        let mut lexer = Lexer::new(source.as_ref());;
+        let mut lexer = Lexer::new(source.as_ref());
         let token = lexer.next().unwrap().unwrap();
         assert_eq!(TokenKind::Minus, token.kind);
     }
@@ -430,7 +566,7 @@ mod tests {
     #[test]
     fn test_next_reads_two_minus() {
         let source = b"--";
-        let mut lexer = Lexer::new(source.as_ref());;
+        let mut lexer = Lexer::new(source.as_ref());
         let token = lexer.next().unwrap().unwrap();
         assert_eq!(TokenKind::OperatorSequence, token.kind);
         assert_eq!("--", token.text.unwrap());
@@ -439,7 +575,7 @@ mod tests {
     #[test]
     fn test_next_reads_three_minus() {
         let source = b"---";
-        let mut lexer = Lexer::new(source.as_ref());;
+        let mut lexer = Lexer::new(source.as_ref());
         let token = lexer.next().unwrap().unwrap();
         assert_eq!(TokenKind::OperatorSequence, token.kind);
         assert_eq!("---", token.text.unwrap());
@@ -448,7 +584,7 @@ mod tests {
     #[test]
     fn test_next_reads_minus_operator_sequence() {
         let source = b"-->";
-        let mut lexer = Lexer::new(source.as_ref());;
+        let mut lexer = Lexer::new(source.as_ref());
         let token = lexer.next().unwrap().unwrap();
         assert_eq!(TokenKind::OperatorSequence, token.kind);
         assert_eq!("-->", token.text.unwrap());
@@ -457,7 +593,7 @@ mod tests {
     #[test]
     fn test_next_reads_separator() {
         let source = b"----";
-        let mut lexer = Lexer::new(source.as_ref());;
+        let mut lexer = Lexer::new(source.as_ref());
         let token = lexer.next().unwrap().unwrap();
         assert_eq!(TokenKind::Separator, token.kind);
     }
@@ -465,7 +601,7 @@ mod tests {
     #[test]
     fn test_next_reads_long_separator() {
         let source = b"----------------\ntest";
-        let mut lexer = Lexer::new(source.as_ref());;
+        let mut lexer = Lexer::new(source.as_ref());
 
         let token = lexer.next().unwrap().unwrap();
         assert_eq!(TokenKind::Separator, token.kind);
@@ -478,7 +614,7 @@ mod tests {
     #[test]
     fn test_next_reads_integer() {
         let source = b"1";
-        let mut lexer = Lexer::new(source.as_ref());;
+        let mut lexer = Lexer::new(source.as_ref());
         let token = lexer.next().unwrap().unwrap();
         assert_eq!(TokenKind::Integer, token.kind);
         assert_eq!("1", token.text.unwrap());
@@ -487,7 +623,7 @@ mod tests {
     #[test]
     fn test_next_reads_integer_and_period() {
         let source = b"1.";
-        let mut lexer = Lexer::new(source.as_ref());;
+        let mut lexer = Lexer::new(source.as_ref());
 
         let token = lexer.next().unwrap().unwrap();
         assert_eq!(TokenKind::Integer, token.kind);
@@ -500,16 +636,77 @@ mod tests {
     #[test]
     fn test_next_reads_double() {
         let source = b"3.14";
-        let mut lexer = Lexer::new(source.as_ref());;
+        let mut lexer = Lexer::new(source.as_ref());
         let token = lexer.next().unwrap().unwrap();
         assert_eq!(TokenKind::Double, token.kind);
         assert_eq!("3.14", token.text.unwrap());
     }
 
+    fn numbers(source: &str) -> Vec<(TokenKind, String)> {
+        Lexer::new(source.as_bytes())
+            .map(|token| {
+                let token = token.unwrap();
+                (token.kind, token.text.unwrap_or_default())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_next_reads_radix_integers() {
+        assert_eq!(
+            vec![
+                (TokenKind::Integer, "31".to_string()),
+                (TokenKind::Integer, "10".to_string()),
+                (TokenKind::Integer, "1294".to_string()),
+            ],
+            numbers("16r1F 2r1010 36rZY")
+        );
+        assert_eq!(
+            vec![(
+                TokenKind::Integer,
+                "340282366920938463463374607431768211455".to_string()
+            )],
+            numbers("16rFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF")
+        );
+    }
+
+    #[test]
+    fn test_next_reads_exponents() {
+        assert_eq!(
+            vec![
+                (TokenKind::Double, "1.5e10".to_string()),
+                (TokenKind::Double, "2e-3".to_string()),
+                (TokenKind::Integer, "2000".to_string()),
+            ],
+            numbers("1.5e10 2e-3 2e3")
+        );
+    }
+
+    #[test]
+    fn test_next_leaves_incomplete_number_suffixes() {
+        assert_eq!(
+            vec![
+                (TokenKind::Integer, "2".to_string()),
+                (TokenKind::Identifier, "e".to_string()),
+                (TokenKind::Integer, "3".to_string()),
+                (TokenKind::Identifier, "r".to_string()),
+                (TokenKind::Integer, "1".to_string()),
+                (TokenKind::Period, String::new()),
+            ],
+            numbers("2e 3r 1.")
+        );
+    }
+
+    #[test]
+    fn test_next_rejects_invalid_radix_numbers() {
+        assert!(Lexer::new(b"37r1".as_ref()).next().unwrap().is_err());
+        assert!(Lexer::new(b"2r102".as_ref()).next().unwrap().is_err());
+    }
+
     #[test]
     fn test_next_reads_string() {
         let source = b"'Hello'";
-        let mut lexer = Lexer::new(source.as_ref());;
+        let mut lexer = Lexer::new(source.as_ref());
         let token = lexer.next().unwrap().unwrap();
         assert_eq!(TokenKind::String, token.kind);
         assert_eq!("Hello", token.text.unwrap());
@@ -527,16 +724,71 @@ mod tests {
     #[test]
     fn test_next_reads_string_with_escape() {
         let source = b"'\\t \\b \\n \\r \\f \\' \\\\'";
-        let mut lexer = Lexer::new(source.as_ref());;
+        let mut lexer = Lexer::new(source.as_ref());
         let token = lexer.next().unwrap().unwrap();
         assert_eq!(TokenKind::String, token.kind);
         assert_eq!("\t \x08 \n \r \x0c ' \\", token.text.unwrap());
     }
 
+    #[test]
+    fn test_next_reads_string_with_nul_and_unicode_escapes() {
+        let source = "'a\\0b \\u00e9 \\u{1F600} \\u{41}'";
+        let mut lexer = Lexer::new(source.as_bytes());
+        let token = lexer.next().unwrap().unwrap();
+        assert_eq!("a\0b \u{e9} \u{1F600} A", token.text.unwrap());
+    }
+
+    #[test]
+    fn test_next_reads_string_with_doubled_quotes() {
+        let source = b"'it''s' '' ''''";
+        let texts = Lexer::new(source.as_ref())
+            .map(|token| token.unwrap().text.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["it's", "", "'"], texts);
+    }
+
+    #[test]
+    fn test_next_rejects_invalid_escapes() {
+        for source in &[
+            "'\\q'",
+            "'\\u12'",
+            "'\\u{}'",
+            "'\\u{D800}'",
+            "'\\u{1234567}'",
+        ] {
+            let mut lexer = Lexer::new(source.as_bytes());
+            assert!(lexer.next().unwrap().is_err(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_next_reads_lines_with_non_ascii_text() {
+        let source = "'héllo' world \"ö\"\n'ü' next\n";
+        let tokens = Lexer::new(source.as_bytes())
+            .map(|token| token.unwrap())
+            .map(|token| {
+                (
+                    token.text.unwrap(),
+                    token.location.line,
+                    token.location.column,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                ("héllo".to_string(), 1, 0),
+                ("world".to_string(), 1, 8),
+                ("ü".to_string(), 2, 0),
+                ("next".to_string(), 2, 4),
+            ],
+            tokens
+        );
+    }
+
     #[test]
     fn test_next_reads_colon() {
         let source = b":";
-        let mut lexer = Lexer::new(source.as_ref());;
+        let mut lexer = Lexer::new(source.as_ref());
         let token = lexer.next().unwrap().unwrap();
         assert_eq!(TokenKind::Colon, token.kind);
     }
@@ -544,7 +796,7 @@ mod tests {
     #[test]
     fn test_next_reads_assignment() {
         let source = b"foo := 'Hello'";
-        let mut lexer = Lexer::new(source.as_ref());;
+        let mut lexer = Lexer::new(source.as_ref());
 
         let token = lexer.next().unwrap().unwrap();
         assert_eq!(TokenKind::Identifier, token.kind);
@@ -560,8 +812,8 @@ mod tests {
 
     #[test]
     fn test_next_reads_simple_symbols() {
-        let source = b"[]()#^.";
-        let mut lexer = Lexer::new(source.as_ref());;
+        let source = b"[]()#^.;";
+        let mut lexer = Lexer::new(source.as_ref());
 
         assert_eq!(TokenKind::NewBlock, lexer.next().unwrap().unwrap().kind);
         assert_eq!(TokenKind::EndBlock, lexer.next().unwrap().unwrap().kind);
@@ -570,12 +822,13 @@ mod tests {
         assert_eq!(TokenKind::Pound, lexer.next().unwrap().unwrap().kind);
         assert_eq!(TokenKind::Exit, lexer.next().unwrap().unwrap().kind);
         assert_eq!(TokenKind::Period, lexer.next().unwrap().unwrap().kind);
+        assert_eq!(TokenKind::Semicolon, lexer.next().unwrap().unwrap().kind);
     }
 
     #[test]
     fn test_next_reads_simple_operators() {
         let source = b"~ & | * / \\ + = < > , @ %";
-        let mut lexer = Lexer::new(source.as_ref());;
+        let mut lexer = Lexer::new(source.as_ref());
 
         assert_eq!(TokenKind::Not, lexer.next().unwrap().unwrap().kind);
         assert_eq!(TokenKind::And, lexer.next().unwrap().unwrap().kind);
@@ -595,7 +848,7 @@ mod tests {
     #[test]
     fn test_next_reads_operator_sequence() {
         let source = b"<=";
-        let mut lexer = Lexer::new(source.as_ref());;
+        let mut lexer = Lexer::new(source.as_ref());
         let token = lexer.next().unwrap().unwrap();
         assert_eq!(TokenKind::OperatorSequence, token.kind);
         assert_eq!("<=", token.text.unwrap());
@@ -609,7 +862,7 @@ mod tests {
             run = ('Hello, World from SOM' println)
         )
         ";
-        let mut lexer = Lexer::new(source.as_ref());;
+        let mut lexer = Lexer::new(source.as_ref());
 
         let token = lexer.next().unwrap().unwrap();
         assert_eq!(TokenKind::Identifier, token.kind);
@@ -1,13 +1,27 @@
 pub mod ast;
+pub mod class_file;
+pub mod codegen;
+pub mod compiled;
+pub mod folding;
 mod lexer;
+pub mod optimizer;
 mod parser;
 pub mod sourcecode_compiler;
 mod token;
 
+pub use self::class_file::{ClassFile, SourceStamp};
+pub use self::compiled::{
+    CompiledClass, CompiledCode, CompiledMethod, InheritedFields, LineTable, Literal,
+};
+pub use self::folding::KnownPrimitives;
 pub use self::lexer::Lexer;
+pub use self::optimizer::OptimizationLevel;
 pub use self::parser::{ParseError, Parser};
-pub use self::sourcecode_compiler::compile_path;
+pub use self::sourcecode_compiler::{
+    compile_path, compile_source, compile_source_with_fields, CompileError, CompileOptions,
+};
 pub use self::token::{Token, TokenKind};
+use std::fmt;
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Location {
@@ -15,6 +29,13 @@ pub struct Location {
     pub column: usize,
 }
 
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // columns are stored zero based but reported one based like editors do
+        write!(f, "{}:{}", self.line, self.column + 1)
+    }
+}
+
 impl Default for Location {
     fn default() -> Self {
         Location { line: 1, column: 0 }
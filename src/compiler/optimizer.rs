@@ -0,0 +1,415 @@
+use crate::compiler::compiled::{CompiledClass, CompiledCode, CompiledMethod, LineTable, Literal};
+use crate::interpreter::Bytecode;
+use std::mem;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OptimizationLevel {
+    None,
+    #[default]
+    Peephole,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Instruction {
+    bytecode: Bytecode,
+    origin: usize,
+}
+
+pub fn optimize_class(class: &mut CompiledClass) {
+    let methods = class
+        .instance_methods
+        .iter_mut()
+        .chain(class.class_methods.iter_mut());
+
+    for method in methods {
+        if let CompiledMethod::Bytecode { code, .. } = method {
+            optimize_code(code, 0);
+        }
+    }
+}
+
+/// Runs the peephole pass over a method (`depth` 0) or a block nested
+/// `depth` levels inside its method. Every rewritten instruction keeps the
+/// bytecode index it came from so the line table can be rebuilt afterwards.
+/// The instruction set has no branches, so no jump offsets need patching.
+pub fn optimize_code(code: &mut CompiledCode, depth: u8) {
+    for block in &mut code.blocks {
+        optimize_code(block, depth + 1);
+    }
+
+    let mut instructions = code
+        .bytecodes
+        .iter()
+        .enumerate()
+        .map(|(origin, &bytecode)| Instruction {
+            bytecode: specialize(bytecode, &code.literals, depth),
+            origin,
+        })
+        .collect::<Vec<_>>();
+
+    while let Some(rewritten) = rewrite_pass(&instructions, depth) {
+        instructions = rewritten;
+    }
+
+    let mut line_table = LineTable::default();
+    for (index, instruction) in instructions.iter().enumerate() {
+        if let Some(location) = code.line_table.location(instruction.origin) {
+            line_table.push(index, location);
+        }
+    }
+
+    code.bytecodes = instructions.iter().map(|i| i.bytecode).collect();
+    code.line_table = line_table;
+    compact_literals(code);
+}
+
+fn specialize(bytecode: Bytecode, literals: &[Literal], depth: u8) -> Bytecode {
+    match bytecode {
+        Bytecode::PushArgument { index: 0, context } if context == depth => Bytecode::PushSelf,
+        Bytecode::PushGlobal { index } => match &literals[index as usize] {
+            Literal::Symbol(name) if name == "nil" => Bytecode::PushNil,
+            _ => bytecode,
+        },
+        Bytecode::PushConstant { index } => match literals[index as usize] {
+            Literal::Integer(0) => Bytecode::PushZero,
+            Literal::Integer(1) => Bytecode::PushOne,
+            _ => bytecode,
+        },
+        _ => bytecode,
+    }
+}
+
+fn rewrite_pass(instructions: &[Instruction], depth: u8) -> Option<Vec<Instruction>> {
+    let mut result = Vec::with_capacity(instructions.len());
+    let mut changed = false;
+    let mut i = 0;
+
+    while i < instructions.len() {
+        let window = &instructions[i..];
+        match rewrite_window(window, depth) {
+            Some((consumed, replacement)) => {
+                let origin = window[0].origin;
+                result.extend(
+                    replacement
+                        .into_iter()
+                        .map(|bytecode| Instruction { bytecode, origin }),
+                );
+                i += consumed;
+                changed = true;
+            }
+            None => {
+                result.push(window[0]);
+                i += 1;
+            }
+        }
+    }
+
+    if changed {
+        Some(result)
+    } else {
+        None
+    }
+}
+
+fn rewrite_window(window: &[Instruction], depth: u8) -> Option<(usize, Vec<Bytecode>)> {
+    let codes = window
+        .iter()
+        .take(3)
+        .map(|i| i.bytecode)
+        .collect::<Vec<_>>();
+
+    match codes.as_slice() {
+        [Bytecode::Dup, store, Bytecode::Pop] if is_store(*store) => Some((3, vec![*store])),
+        [push, Bytecode::Pop, ..] if is_pure_push(*push) => Some((2, vec![])),
+        // Same length, but the interpreter fuses `Dup; PopLocal` and
+        // `Dup; PopField` into a single store-and-keep instruction.
+        [store, push, ..] if stores_to_pushed(*store, *push) => {
+            Some((2, vec![Bytecode::Dup, *store]))
+        }
+        [Bytecode::PushSelf, Bytecode::ReturnLocal, ..] if depth == 0 => {
+            Some((2, vec![Bytecode::ReturnSelf]))
+        }
+        _ => None,
+    }
+}
+
+fn is_store(bytecode: Bytecode) -> bool {
+    matches!(
+        bytecode,
+        Bytecode::PopLocal { .. } | Bytecode::PopArgument { .. } | Bytecode::PopField { .. }
+    )
+}
+
+fn is_pure_push(bytecode: Bytecode) -> bool {
+    matches!(
+        bytecode,
+        Bytecode::Dup
+            | Bytecode::PushLocal { .. }
+            | Bytecode::PushArgument { .. }
+            | Bytecode::PushField { .. }
+            | Bytecode::PushBlock { .. }
+            | Bytecode::PushConstant { .. }
+            | Bytecode::PushSelf
+            | Bytecode::PushNil
+            | Bytecode::PushZero
+            | Bytecode::PushOne
+    )
+}
+
+fn stores_to_pushed(store: Bytecode, push: Bytecode) -> bool {
+    match (store, push) {
+        (
+            Bytecode::PopLocal { index, context },
+            Bytecode::PushLocal {
+                index: i,
+                context: c,
+            },
+        ) => index == i && context == c,
+        (
+            Bytecode::PopArgument { index, context },
+            Bytecode::PushArgument {
+                index: i,
+                context: c,
+            },
+        ) => index == i && context == c,
+        (Bytecode::PopField { index }, Bytecode::PushField { index: i }) => index == i,
+        _ => false,
+    }
+}
+
+fn literal_index(bytecode: Bytecode) -> Option<u8> {
+    match bytecode {
+        Bytecode::PushConstant { index }
+        | Bytecode::PushGlobal { index }
+        | Bytecode::Send { index }
+        | Bytecode::SuperSend { index } => Some(index),
+        _ => None,
+    }
+}
+
+fn with_literal_index(bytecode: Bytecode, index: u8) -> Bytecode {
+    match bytecode {
+        Bytecode::PushConstant { .. } => Bytecode::PushConstant { index },
+        Bytecode::PushGlobal { .. } => Bytecode::PushGlobal { index },
+        Bytecode::Send { .. } => Bytecode::Send { index },
+        Bytecode::SuperSend { .. } => Bytecode::SuperSend { index },
+        _ => bytecode,
+    }
+}
+
+/// Drops literals no longer referenced after specialization, e.g. the `nil`
+/// symbol once every `PushGlobal nil` became `PushNil`.
+fn compact_literals(code: &mut CompiledCode) {
+    let old_literals = mem::take(&mut code.literals);
+    let mut mapping = vec![None; old_literals.len()];
+    let mut literals = vec![];
+
+    for bytecode in &mut code.bytecodes {
+        if let Some(index) = literal_index(*bytecode) {
+            let new_index = *mapping[index as usize].get_or_insert_with(|| {
+                literals.push(old_literals[index as usize].clone());
+                (literals.len() - 1) as u8
+            });
+
+            *bytecode = with_literal_index(*bytecode, new_index);
+        }
+    }
+
+    code.literals = literals;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::Location;
+
+    fn optimize(bytecodes: Vec<Bytecode>, literals: Vec<Literal>) -> CompiledCode {
+        let mut code = CompiledCode {
+            bytecodes,
+            literals,
+            ..CompiledCode::default()
+        };
+        optimize_code(&mut code, 0);
+        code
+    }
+
+    #[test]
+    fn test_optimize_return_self() {
+        let code = optimize(
+            vec![
+                Bytecode::PushArgument {
+                    index: 0,
+                    context: 0,
+                },
+                Bytecode::ReturnLocal,
+            ],
+            vec![],
+        );
+        assert_eq!(vec![Bytecode::ReturnSelf], code.bytecodes);
+    }
+
+    #[test]
+    fn test_optimize_pure_push_and_pop() {
+        let code = optimize(
+            vec![
+                Bytecode::PushLocal {
+                    index: 0,
+                    context: 0,
+                },
+                Bytecode::Pop,
+                Bytecode::Dup,
+                Bytecode::Pop,
+                Bytecode::ReturnLocal,
+            ],
+            vec![],
+        );
+        assert_eq!(vec![Bytecode::ReturnLocal], code.bytecodes);
+    }
+
+    #[test]
+    fn test_optimize_keeps_global_push_and_pop() {
+        let code = optimize(
+            vec![
+                Bytecode::PushGlobal { index: 0 },
+                Bytecode::Pop,
+                Bytecode::ReturnLocal,
+            ],
+            vec![Literal::Symbol("Foo".into())],
+        );
+        assert_eq!(
+            vec![
+                Bytecode::PushGlobal { index: 0 },
+                Bytecode::Pop,
+                Bytecode::ReturnLocal
+            ],
+            code.bytecodes
+        );
+    }
+
+    #[test]
+    fn test_optimize_assignment_statement() {
+        let local = Bytecode::PopLocal {
+            index: 1,
+            context: 0,
+        };
+        let code = optimize(
+            vec![
+                Bytecode::PushConstant { index: 0 },
+                Bytecode::Dup,
+                local,
+                Bytecode::Pop,
+                Bytecode::PushLocal {
+                    index: 1,
+                    context: 0,
+                },
+                Bytecode::ReturnLocal,
+            ],
+            vec![Literal::Integer(5)],
+        );
+        assert_eq!(
+            vec![
+                Bytecode::PushConstant { index: 0 },
+                Bytecode::Dup,
+                local,
+                Bytecode::ReturnLocal
+            ],
+            code.bytecodes
+        );
+    }
+
+    #[test]
+    fn test_optimize_specializes_constants() {
+        let code = optimize(
+            vec![
+                Bytecode::PushConstant { index: 0 },
+                Bytecode::PushConstant { index: 1 },
+                Bytecode::PushGlobal { index: 2 },
+                Bytecode::PushConstant { index: 3 },
+                Bytecode::Send { index: 4 },
+                Bytecode::ReturnLocal,
+            ],
+            vec![
+                Literal::Integer(0),
+                Literal::Integer(1),
+                Literal::Symbol("nil".into()),
+                Literal::Integer(42),
+                Literal::Symbol("foo:bar:baz:".into()),
+            ],
+        );
+        assert_eq!(
+            vec![
+                Bytecode::PushZero,
+                Bytecode::PushOne,
+                Bytecode::PushNil,
+                Bytecode::PushConstant { index: 0 },
+                Bytecode::Send { index: 1 },
+                Bytecode::ReturnLocal,
+            ],
+            code.bytecodes
+        );
+        assert_eq!(
+            vec![Literal::Integer(42), Literal::Symbol("foo:bar:baz:".into())],
+            code.literals
+        );
+    }
+
+    #[test]
+    fn test_optimize_self_in_blocks() {
+        let mut code = CompiledCode {
+            bytecodes: vec![Bytecode::PushBlock { index: 0 }, Bytecode::ReturnLocal],
+            blocks: vec![CompiledCode {
+                bytecodes: vec![
+                    Bytecode::PushArgument {
+                        index: 0,
+                        context: 0,
+                    },
+                    Bytecode::PushArgument {
+                        index: 0,
+                        context: 1,
+                    },
+                    Bytecode::ReturnLocal,
+                ],
+                ..CompiledCode::default()
+            }],
+            ..CompiledCode::default()
+        };
+        optimize_code(&mut code, 0);
+
+        assert_eq!(
+            vec![
+                Bytecode::PushArgument {
+                    index: 0,
+                    context: 0
+                },
+                Bytecode::PushSelf,
+                Bytecode::ReturnLocal
+            ],
+            code.blocks[0].bytecodes
+        );
+    }
+
+    #[test]
+    fn test_optimize_remaps_line_table() {
+        let location = |line| Location { line, column: 0 };
+        let mut code = CompiledCode {
+            bytecodes: vec![
+                Bytecode::PushConstant { index: 0 },
+                Bytecode::Pop,
+                Bytecode::PushConstant { index: 0 },
+                Bytecode::Send { index: 1 },
+                Bytecode::ReturnLocal,
+            ],
+            literals: vec![Literal::Integer(5), Literal::Symbol("foo".into())],
+            ..CompiledCode::default()
+        };
+        code.line_table.push(0, location(1));
+        code.line_table.push(2, location(2));
+        code.line_table.push(3, location(3));
+        optimize_code(&mut code, 0);
+
+        assert_eq!(3, code.bytecodes.len());
+        assert_eq!(Some(location(2)), code.location(0));
+        assert_eq!(Some(location(3)), code.location(1));
+        assert_eq!(Some(location(3)), code.location(2));
+    }
+}
@@ -1,6 +1,9 @@
+use crate::compiler::lexer::LexerError;
 use crate::compiler::{ast, Lexer, Location, Token, TokenKind};
+use num_bigint::BigInt;
 use std::collections::HashMap;
-use std::io::BufRead;
+use std::convert::TryFrom;
+use std::io::{self, BufRead};
 use std::iter::Peekable;
 use std::path::Path;
 use std::result;
@@ -40,6 +43,7 @@ pub struct Parser<R: BufRead> {
     lexer: Peekable<Lexer<R>>,
     filename: String,
     last_location: Location,
+    cascades: bool,
 }
 
 impl<R: BufRead> Parser<R> {
@@ -48,19 +52,18 @@ impl<R: BufRead> Parser<R> {
             lexer: Lexer::new(reader).peekable(),
             last_location: Location::default(),
             filename: filename.as_ref().to_string_lossy().into_owned(),
+            cascades: false,
         }
     }
 
-    pub fn parse(&mut self) -> Result<ast::Class> {
-        let name = self.expect_token(TokenKind::Identifier)?.text.unwrap();
-        let _ = self.expect_token(TokenKind::Equal)?;
-
-        let superclass = if self.peek_token_kind()? == TokenKind::Identifier {
-            self.expect_token(TokenKind::Identifier)?.text
-        } else {
-            None
-        };
+    /// Accepts the Smalltalk cascade syntax `receiver foo; bar`, which is a
+    /// language extension strict SOM rejects.
+    pub fn set_cascades_enabled(&mut self, enabled: bool) {
+        self.cascades = enabled;
+    }
 
+    pub fn parse(&mut self) -> Result<ast::Class> {
+        let (name, superclass) = self.parse_header()?;
         let _ = self.expect_token(TokenKind::NewTerm)?;
 
         let instance_variables = self.parse_locals()?;
@@ -87,6 +90,20 @@ impl<R: BufRead> Parser<R> {
         })
     }
 
+    /// Parses only `Name = Superclass`, the start of a class definition.
+    pub fn parse_header(&mut self) -> Result<(String, Option<String>)> {
+        let name = self.expect_token(TokenKind::Identifier)?.text.unwrap();
+        let _ = self.expect_token(TokenKind::Equal)?;
+
+        let superclass = if self.peek_token_kind()? == TokenKind::Identifier {
+            self.expect_token(TokenKind::Identifier)?.text
+        } else {
+            None
+        };
+
+        Ok((name, superclass))
+    }
+
     fn parse_block_parameters(&mut self) -> Result<Vec<String>> {
         let mut parameters = vec![];
 
@@ -122,10 +139,21 @@ impl<R: BufRead> Parser<R> {
     }
 
     fn parse_expression(&mut self) -> Result<ast::Expression> {
-        let mut expression = self.parse_expression_primary()?;
+        let primary = self.parse_expression_primary()?;
+        self.parse_expression_rest(primary)
+    }
+
+    /// Parses the messages and assignment following `expression`.
+    fn parse_expression_rest(
+        &mut self,
+        mut expression: ast::Expression,
+    ) -> Result<ast::Expression> {
         loop {
             expression = match self.peek_token_kind()? {
                 TokenKind::Assign => self.parse_expression_assignment(expression)?,
+                TokenKind::Semicolon if self.cascades => {
+                    self.parse_expression_cascade(expression)?
+                }
                 TokenKind::Identifier => self.parse_expression_messages(expression)?,
                 TokenKind::Keyword => self.parse_expression_messages(expression)?,
                 TokenKind::OperatorSequence => self.parse_expression_messages(expression)?,
@@ -140,27 +168,78 @@ impl<R: BufRead> Parser<R> {
     fn parse_expression_array(&mut self) -> Result<ast::Expression> {
         let mut values = vec![];
 
-        let _ = self.expect_token(TokenKind::NewTerm)?;
+        let location = self.expect_token(TokenKind::NewTerm)?.location;
         loop {
             match self.peek_token_kind()? {
                 TokenKind::EndTerm => break,
-                _ => values.push(self.parse_expression()?),
+                _ => values.push(self.parse_array_element()?),
             }
         }
 
         let _ = self.expect_token(TokenKind::EndTerm)?;
 
-        Ok(ast::Expression::LiteralArray(values))
+        Ok(ast::Expression::LiteralArray { values, location })
+    }
+
+    /// Array elements follow the Smalltalk literal rules: bare identifiers,
+    /// keywords and operators are symbols, `true`, `false` and `nil` are
+    /// constants and parentheses nest arrays. A `-` directly before a number
+    /// makes it negative.
+    fn parse_array_element(&mut self) -> Result<ast::Expression> {
+        match self.peek_token_kind()? {
+            TokenKind::Integer | TokenKind::Double => self.parse_expression_number(false),
+            TokenKind::Minus => {
+                let minus = self.expect_token(TokenKind::Minus)?;
+                match self.peek_token_kind()? {
+                    TokenKind::Integer | TokenKind::Double => self.parse_expression_number(true),
+                    _ => Ok(ast::Expression::LiteralSymbol {
+                        value: minus.text.unwrap(),
+                        location: minus.location,
+                    }),
+                }
+            }
+            TokenKind::NewTerm => self.parse_expression_array(),
+            TokenKind::Pound => self.parse_expression_pound(),
+            TokenKind::String => self.parse_expression_string(),
+            TokenKind::Identifier => {
+                let token = self.expect_token(TokenKind::Identifier)?;
+                let (name, location) = (token.text.unwrap(), token.location);
+                let expression = match name.as_str() {
+                    "false" => ast::Expression::LiteralBoolean {
+                        value: false,
+                        location,
+                    },
+                    "nil" => ast::Expression::LiteralNil { location },
+                    "true" => ast::Expression::LiteralBoolean {
+                        value: true,
+                        location,
+                    },
+                    _ => ast::Expression::LiteralSymbol {
+                        value: name,
+                        location,
+                    },
+                };
+
+                Ok(expression)
+            }
+            kind if SYMBOL_KINDS.contains(&kind) => self.parse_expression_symbol(),
+            kind => Err(ParseError {
+                description: format!("Expected a literal in literal array, found {:?}", kind),
+                filename: self.filename.clone(),
+                location: self.last_location,
+            }),
+        }
     }
 
     fn parse_expression_assignment(&mut self, left: ast::Expression) -> Result<ast::Expression> {
         let token = self.expect_token(TokenKind::Assign)?;
 
-        if let ast::Expression::Variable(name) = left {
+        if let ast::Expression::Variable { name, location } = left {
             let right = self.parse_expression()?;
             let expression = ast::Expression::Assignment {
                 variable: name,
                 value: Box::new(right),
+                location,
             };
 
             Ok(expression)
@@ -180,12 +259,13 @@ impl<R: BufRead> Parser<R> {
         left: ast::Expression,
     ) -> Result<ast::Expression> {
         let kind = self.peek_token_kind()?;
-        let message = self.expect_token(kind)?.text.unwrap();
+        let token = self.expect_token(kind)?;
         let right = self.parse_expression_binary_operand()?;
         let expression = ast::Expression::BinaryMessage {
-            message,
+            message: token.text.unwrap(),
             left: Box::new(left),
             right: Box::new(right),
+            location: token.location,
         };
 
         Ok(expression)
@@ -219,13 +299,90 @@ impl<R: BufRead> Parser<R> {
         Ok(value)
     }
 
+    /// Parses `; messages` after `expression`, which must be a message send.
+    /// The part is sent to the receiver of the last message of `expression`.
+    fn parse_expression_cascade(&mut self, expression: ast::Expression) -> Result<ast::Expression> {
+        let (receiver, mut parts) = match expression {
+            ast::Expression::Cascade { receiver, parts } => (receiver, parts),
+            expression => match split_message(expression) {
+                Some((receiver, message)) => (receiver, vec![vec![message]]),
+                None => {
+                    return Err(ParseError {
+                        description: "Cascade must follow a message send".into(),
+                        filename: self.filename.clone(),
+                        location: self.last_location,
+                    })
+                }
+            },
+        };
+
+        let _ = self.expect_token(TokenKind::Semicolon)?;
+        let part = self.parse_cascade_part()?;
+        if part.is_empty() {
+            return Err(ParseError {
+                description: format!(
+                    "Expected a message after ';', found {:?}",
+                    self.peek_token_kind()?
+                ),
+                filename: self.filename.clone(),
+                location: self.last_location,
+            });
+        }
+        parts.push(part);
+
+        Ok(ast::Expression::Cascade { receiver, parts })
+    }
+
+    /// Parses the messages of one cascade part like the reference grammar:
+    /// unary messages, then binary ones, then an optional keyword message.
+    /// They are built on a placeholder receiver and then unwound into the
+    /// order they are sent in.
+    fn parse_cascade_part(&mut self) -> Result<Vec<ast::Message>> {
+        let mut expression = ast::Expression::LiteralNil {
+            location: Location::default(),
+        };
+        while let TokenKind::Identifier = self.peek_token_kind()? {
+            expression = self.parse_expression_unary_message(expression)?;
+        }
+        loop {
+            match self.peek_token_kind()? {
+                TokenKind::OperatorSequence => {
+                    expression = self.parse_expression_binary_message(expression)?
+                }
+                kind if kind.is_binary_operator() => {
+                    expression = self.parse_expression_binary_message(expression)?
+                }
+                _ => break,
+            }
+        }
+        if let TokenKind::Keyword = self.peek_token_kind()? {
+            expression = self.parse_expression_keyword_message(expression)?;
+        }
+
+        let mut messages = vec![];
+        while let Some((receiver, message)) = split_message(expression) {
+            messages.push(message);
+            expression = *receiver;
+        }
+        messages.reverse();
+
+        Ok(messages)
+    }
+
     fn parse_expression_identifier(&mut self) -> Result<ast::Expression> {
-        let name = self.expect_token(TokenKind::Identifier)?.text.unwrap();
+        let token = self.expect_token(TokenKind::Identifier)?;
+        let (name, location) = (token.text.unwrap(), token.location);
         let expression = match name.as_str() {
-            "false" => ast::Expression::LiteralBoolean(false),
-            "nil" => ast::Expression::LiteralNil,
-            "true" => ast::Expression::LiteralBoolean(true),
-            _ => ast::Expression::Variable(name),
+            "false" => ast::Expression::LiteralBoolean {
+                value: false,
+                location,
+            },
+            "nil" => ast::Expression::LiteralNil { location },
+            "true" => ast::Expression::LiteralBoolean {
+                value: true,
+                location,
+            },
+            _ => ast::Expression::Variable { name, location },
         };
 
         Ok(expression)
@@ -237,6 +394,7 @@ impl<R: BufRead> Parser<R> {
     ) -> Result<ast::Expression> {
         let mut message = String::new();
         let mut parameters = Vec::new();
+        let location = self.peek_token_location()?;
 
         while let TokenKind::Keyword = self.peek_token_kind()? {
             let keyword = self.expect_token(TokenKind::Keyword)?.text.unwrap();
@@ -250,6 +408,7 @@ impl<R: BufRead> Parser<R> {
             receiver: Box::new(value),
             message,
             parameters,
+            location,
         })
     }
 
@@ -269,11 +428,12 @@ impl<R: BufRead> Parser<R> {
     }
 
     fn parse_expression_nested_block(&mut self) -> Result<ast::Expression> {
-        let _ = self.expect_token(TokenKind::NewBlock)?;
+        let location = self.expect_token(TokenKind::NewBlock)?.location;
         let expression = ast::Expression::Block {
             parameters: self.parse_block_parameters()?,
             locals: self.parse_locals()?,
             body: self.parse_body()?,
+            location,
         };
 
         let _ = self.expect_token(TokenKind::EndBlock)?;
@@ -295,26 +455,30 @@ impl<R: BufRead> Parser<R> {
             Token {
                 kind: TokenKind::Integer,
                 text: Some(text),
-                ..
+                location,
             } => {
-                let mut value: i64 = text.parse().unwrap();
+                // the lexer only produces digits, so this cannot fail
+                let mut value: BigInt = text.parse().expect("integer literal");
                 if negative {
                     value = -value;
                 }
 
-                Ok(ast::Expression::LiteralInteger(value))
+                match i64::try_from(&value) {
+                    Ok(value) => Ok(ast::Expression::LiteralInteger { value, location }),
+                    Err(_) => Ok(ast::Expression::LiteralLargeInteger { value, location }),
+                }
             }
             Token {
                 kind: TokenKind::Double,
                 text: Some(text),
-                ..
+                location,
             } => {
                 let mut value: f64 = text.parse().unwrap();
                 if negative {
                     value = -value;
                 }
 
-                Ok(ast::Expression::LiteralDouble(value))
+                Ok(ast::Expression::LiteralDouble { value, location })
             }
             _ => unreachable!(),
         }
@@ -348,21 +512,27 @@ impl<R: BufRead> Parser<R> {
     }
 
     fn parse_expression_result(&mut self) -> Result<ast::Expression> {
-        let _ = self.expect_token(TokenKind::Exit)?;
-        let statement = Box::new(self.parse_expression()?);
-        Ok(ast::Expression::Return(statement))
+        let location = self.expect_token(TokenKind::Exit)?.location;
+        let value = Box::new(self.parse_expression()?);
+        Ok(ast::Expression::Return { value, location })
     }
 
     fn parse_expression_string(&mut self) -> Result<ast::Expression> {
-        let value = self.expect_token(TokenKind::String)?.text.unwrap();
-        let expression = ast::Expression::LiteralString(value);
+        let token = self.expect_token(TokenKind::String)?;
+        let expression = ast::Expression::LiteralString {
+            value: token.text.unwrap(),
+            location: token.location,
+        };
 
         Ok(expression)
     }
 
     fn parse_expression_symbol(&mut self) -> Result<ast::Expression> {
-        let value = self.expect_token_one_of(&SYMBOL_KINDS)?.text.unwrap();
-        let expression = ast::Expression::LiteralSymbol(value);
+        let token = self.expect_token_one_of(&SYMBOL_KINDS)?;
+        let expression = ast::Expression::LiteralSymbol {
+            value: token.text.unwrap(),
+            location: token.location,
+        };
 
         Ok(expression)
     }
@@ -371,10 +541,11 @@ impl<R: BufRead> Parser<R> {
         &mut self,
         value: ast::Expression,
     ) -> Result<ast::Expression> {
-        let name = self.expect_token(TokenKind::Identifier)?.text.unwrap();
+        let token = self.expect_token(TokenKind::Identifier)?;
         let expression = ast::Expression::UnaryMessage {
             receiver: Box::new(value),
-            message: name,
+            message: token.text.unwrap(),
+            location: token.location,
         };
 
         Ok(expression)
@@ -401,10 +572,10 @@ impl<R: BufRead> Parser<R> {
 
         loop {
             let method = match self.peek_token_kind()? {
-                TokenKind::Identifier => try!(self.parse_method()),
-                TokenKind::Keyword => try!(self.parse_method()),
-                TokenKind::OperatorSequence => try!(self.parse_method()),
-                kind if kind.is_binary_operator() => try!(self.parse_method()),
+                TokenKind::Identifier => self.parse_method()?,
+                TokenKind::Keyword => self.parse_method()?,
+                TokenKind::OperatorSequence => self.parse_method()?,
+                kind if kind.is_binary_operator() => self.parse_method()?,
                 _ => break,
             };
 
@@ -420,6 +591,7 @@ impl<R: BufRead> Parser<R> {
     }
 
     fn parse_method(&mut self) -> Result<ast::Method> {
+        let location = self.peek_token_location()?;
         let (name, parameters) = self.parse_pattern()?;
         let _ = self.expect_token(TokenKind::Equal)?;
 
@@ -431,8 +603,9 @@ impl<R: BufRead> Parser<R> {
             let method = ast::Method::Native {
                 name,
                 parameters,
-                locals: try!(self.parse_locals()),
-                body: try!(self.parse_body()),
+                locals: self.parse_locals()?,
+                body: self.parse_body()?,
+                location,
             };
 
             let _ = self.expect_token(TokenKind::EndTerm)?;
@@ -488,7 +661,8 @@ impl<R: BufRead> Parser<R> {
                 self.last_location = t.location;
                 Ok(t.kind)
             }
-            _ => Err(ParseError {
+            Some(Err(e)) => Err(lexer_error(e, &self.filename, self.last_location)),
+            None => Err(ParseError {
                 description: "Unexpected end of program".into(),
                 filename: self.filename.clone(),
                 location: self.last_location,
@@ -496,6 +670,11 @@ impl<R: BufRead> Parser<R> {
         }
     }
 
+    fn peek_token_location(&mut self) -> Result<Location> {
+        let _ = self.peek_token_kind()?;
+        Ok(self.last_location)
+    }
+
     fn expect_token(&mut self, kind: TokenKind) -> Result<Token> {
         self.expect_token_one_of(&[kind])
     }
@@ -515,11 +694,7 @@ impl<R: BufRead> Parser<R> {
                     })
                 }
             }
-            Some(Err(e)) => Err(ParseError {
-                description: format!("Lexer error: {:?}", e),
-                filename: self.filename.clone(),
-                location: self.last_location,
-            }),
+            Some(Err(e)) => Err(lexer_error(&e, &self.filename, self.last_location)),
             None => Err(ParseError {
                 description: "Unexpected end of program".into(),
                 filename: self.filename.clone(),
@@ -529,14 +704,129 @@ impl<R: BufRead> Parser<R> {
     }
 }
 
+/// The parse error for a token the lexer rejected, at the lexer's location
+/// when it has one.
+fn lexer_error(error: &io::Error, filename: &str, last_location: Location) -> ParseError {
+    match error.get_ref().and_then(|e| e.downcast_ref::<LexerError>()) {
+        Some(e) => ParseError {
+            description: e.description.clone(),
+            filename: filename.into(),
+            location: e.location,
+        },
+        None => ParseError {
+            description: format!("Lexer error: {}", error),
+            filename: filename.into(),
+            location: last_location,
+        },
+    }
+}
+
+/// Separates a message send into its receiver and the message.
+fn split_message(expression: ast::Expression) -> Option<(Box<ast::Expression>, ast::Message)> {
+    match expression {
+        ast::Expression::UnaryMessage {
+            message,
+            receiver,
+            location,
+        } => Some((
+            receiver,
+            ast::Message {
+                message,
+                parameters: vec![],
+                location,
+            },
+        )),
+        ast::Expression::BinaryMessage {
+            message,
+            left,
+            right,
+            location,
+        } => Some((
+            left,
+            ast::Message {
+                message,
+                parameters: vec![*right],
+                location,
+            },
+        )),
+        ast::Expression::KeywordMessage {
+            message,
+            receiver,
+            parameters,
+            location,
+        } => Some((
+            receiver,
+            ast::Message {
+                message,
+                parameters,
+                location,
+            },
+        )),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn at(column: usize) -> Location {
+        Location { line: 1, column }
+    }
+
+    fn integer(value: i64, column: usize) -> ast::Expression {
+        ast::Expression::LiteralInteger {
+            value,
+            location: at(column),
+        }
+    }
+
+    fn symbol(value: &str, column: usize) -> ast::Expression {
+        ast::Expression::LiteralSymbol {
+            value: value.into(),
+            location: at(column),
+        }
+    }
+
+    fn variable(name: &str, column: usize) -> ast::Expression {
+        ast::Expression::Variable {
+            name: name.into(),
+            location: at(column),
+        }
+    }
+
+    fn string(value: &str, column: usize) -> ast::Expression {
+        ast::Expression::LiteralString {
+            value: value.into(),
+            location: at(column),
+        }
+    }
+
+    fn double(value: f64, column: usize) -> ast::Expression {
+        ast::Expression::LiteralDouble {
+            value,
+            location: at(column),
+        }
+    }
+
+    fn boolean(value: bool, column: usize) -> ast::Expression {
+        ast::Expression::LiteralBoolean {
+            value,
+            location: at(column),
+        }
+    }
+
+    fn array(values: Vec<ast::Expression>, column: usize) -> ast::Expression {
+        ast::Expression::LiteralArray {
+            values,
+            location: at(column),
+        }
+    }
+
     #[test]
     fn test_parse_with_simple_class() {
         let source = b"Hello = ()";
-        let mut parser = Parser::new(source.as_ref(), "test");;
+        let mut parser = Parser::new(source.as_ref(), "test");
 
         let class = parser.parse().unwrap();
         assert_eq!("Hello", class.name);
@@ -546,7 +836,7 @@ mod tests {
     #[test]
     fn test_parse_with_superclass() {
         let source = b"Hello = Test ()";
-        let mut parser = Parser::new(source.as_ref(), "test");;
+        let mut parser = Parser::new(source.as_ref(), "test");
 
         let class = parser.parse().unwrap();
         assert_eq!("Hello", class.name);
@@ -561,7 +851,7 @@ mod tests {
             ----
             | baz qux |
         )";
-        let mut parser = Parser::new(source.as_ref(), "test");;
+        let mut parser = Parser::new(source.as_ref(), "test");
 
         let class = parser.parse().unwrap();
         assert_eq!(vec!["foo", "bar"], class.instance_variables);
@@ -576,7 +866,7 @@ mod tests {
             ----
             bar: a baz: b = primitive
         )";
-        let mut parser = Parser::new(source.as_ref(), "test");;
+        let mut parser = Parser::new(source.as_ref(), "test");
         let class = parser.parse().unwrap();
 
         let method = class.instance_methods.get("foo").unwrap();
@@ -601,9 +891,28 @@ mod tests {
     #[test]
     fn test_parse_expression_integer_literal() {
         let source = b"1.";
-        let mut parser = Parser::new(source.as_ref(), "test");;
+        let mut parser = Parser::new(source.as_ref(), "test");
+        let expression = parser.parse_expression().unwrap();
+        assert_eq!(integer(1, 0), expression);
+    }
+
+    #[test]
+    fn test_parse_expression_large_integer_literal() {
+        let source = b"99999999999999999999.";
+        let mut parser = Parser::new(source.as_ref(), "test");
+        let expression = parser.parse_expression().unwrap();
+        assert_eq!(
+            ast::Expression::LiteralLargeInteger {
+                value: "99999999999999999999".parse().unwrap(),
+                location: at(0),
+            },
+            expression
+        );
+
+        let source = b"-9223372036854775808.";
+        let mut parser = Parser::new(source.as_ref(), "test");
         let expression = parser.parse_expression().unwrap();
-        assert_eq!(ast::Expression::LiteralInteger(1), expression);
+        assert_eq!(integer(i64::MIN, 1), expression);
     }
 
     // #[test]
@@ -617,74 +926,124 @@ mod tests {
     #[test]
     fn test_parse_expression_negative_integer_literal() {
         let source = b"-1.";
-        let mut parser = Parser::new(source.as_ref(), "test");;
+        let mut parser = Parser::new(source.as_ref(), "test");
         let expression = parser.parse_expression().unwrap();
-        assert_eq!(ast::Expression::LiteralInteger(-1), expression);
+        assert_eq!(integer(-1, 1), expression);
     }
 
     #[test]
     fn test_parse_expression_double_literal() {
         let source = b"1.23.";
-        let mut parser = Parser::new(source.as_ref(), "test");;
+        let mut parser = Parser::new(source.as_ref(), "test");
         let expression = parser.parse_expression().unwrap();
-        assert_eq!(ast::Expression::LiteralDouble(1.23), expression);
+        assert_eq!(double(1.23, 0), expression);
     }
 
     #[test]
     fn test_parse_expression_negative_double_literal() {
         let source = b"-1.23.";
-        let mut parser = Parser::new(source.as_ref(), "test");;
+        let mut parser = Parser::new(source.as_ref(), "test");
         let expression = parser.parse_expression().unwrap();
-        assert_eq!(ast::Expression::LiteralDouble(-1.23), expression);
+        assert_eq!(double(-1.23, 1), expression);
     }
 
     #[test]
     fn test_parse_expression_variable() {
         let source = b"a.";
-        let mut parser = Parser::new(source.as_ref(), "test");;
+        let mut parser = Parser::new(source.as_ref(), "test");
         let expression = parser.parse_expression().unwrap();
-        assert_eq!(ast::Expression::Variable("a".into()), expression);
+        assert_eq!(variable("a", 0), expression);
     }
 
     #[test]
     fn test_parse_expression_string_literal() {
         let source = b"'test'.";
-        let mut parser = Parser::new(source.as_ref(), "test");;
+        let mut parser = Parser::new(source.as_ref(), "test");
         let expression = parser.parse_expression().unwrap();
-        assert_eq!(expression, ast::Expression::LiteralString("test".into()));
+        assert_eq!(expression, string("test", 0));
     }
 
     #[test]
     fn test_parse_expression_nil_literal() {
         let source = b"nil.";
-        let mut parser = Parser::new(source.as_ref(), "test");;
+        let mut parser = Parser::new(source.as_ref(), "test");
         let expression = parser.parse_expression().unwrap();
-        assert_eq!(ast::Expression::LiteralNil, expression);
+        assert_eq!(ast::Expression::LiteralNil { location: at(0) }, expression);
     }
 
     #[test]
     fn test_parse_expression_array_literal() {
         let source = b"#(1 2).";
-        let mut parser = Parser::new(source.as_ref(), "test");;
+        let mut parser = Parser::new(source.as_ref(), "test");
+        let expression = parser.parse_expression().unwrap();
+        assert_eq!(array(vec![integer(1, 2), integer(2, 4)], 1), expression);
+    }
+
+    #[test]
+    fn test_parse_expression_array_literal_with_negative_numbers() {
+        let source = b"#(-1 2 -3.5 16r1F -2e-3).";
+        let mut parser = Parser::new(source.as_ref(), "test");
         let expression = parser.parse_expression().unwrap();
         assert_eq!(
-            ast::Expression::LiteralArray(vec![
-                ast::Expression::LiteralInteger(1),
-                ast::Expression::LiteralInteger(2)
-            ]),
+            array(
+                vec![
+                    integer(-1, 3),
+                    integer(2, 5),
+                    double(-3.5, 8),
+                    integer(31, 12),
+                    double(-0.002, 19),
+                ],
+                1
+            ),
             expression
         );
     }
 
+    #[test]
+    fn test_parse_expression_array_literal_rules() {
+        let source = b"#(foo at:put: bar: + - true false nil (1 (2)) #baz #(3) 'a').";
+        let mut parser = Parser::new(source.as_ref(), "test");
+        let expression = parser.parse_expression().unwrap();
+        assert_eq!(
+            array(
+                vec![
+                    symbol("foo", 2),
+                    symbol("at:put:", 6),
+                    symbol("bar:", 14),
+                    symbol("+", 19),
+                    symbol("-", 21),
+                    boolean(true, 23),
+                    boolean(false, 28),
+                    ast::Expression::LiteralNil { location: at(34) },
+                    array(vec![integer(1, 39), array(vec![integer(2, 42)], 41)], 38),
+                    symbol("baz", 47),
+                    array(vec![integer(3, 53)], 52),
+                    string("a", 56),
+                ],
+                1
+            ),
+            expression
+        );
+    }
+
+    #[test]
+    fn test_parse_expression_array_literal_rejects_expressions() {
+        for source in &["#([ 1 ]).", "#(^ 1).", "#(a := 1)."] {
+            let mut parser = Parser::new(source.as_bytes(), "test");
+            assert!(parser.parse_expression().is_err(), "{}", source);
+        }
+    }
+
     #[test]
     fn test_parse_expression_unary_message() {
         let source = b"1 println.";
-        let mut parser = Parser::new(source.as_ref(), "test");;
+        let mut parser = Parser::new(source.as_ref(), "test");
         let expression = parser.parse_expression().unwrap();
         assert_eq!(
             ast::Expression::UnaryMessage {
                 message: "println".into(),
-                receiver: Box::new(ast::Expression::LiteralInteger(1)),
+                receiver: Box::new(integer(1, 0)),
+                location: Location { line: 1, column: 2 },
             },
             expression
         );
@@ -693,15 +1052,17 @@ mod tests {
     #[test]
     fn test_parse_expression_multiple_unary_messages() {
         let source = b"1 test println.";
-        let mut parser = Parser::new(source.as_ref(), "test");;
+        let mut parser = Parser::new(source.as_ref(), "test");
         let expression = parser.parse_expression().unwrap();
         assert_eq!(
             ast::Expression::UnaryMessage {
                 message: "println".into(),
                 receiver: Box::new(ast::Expression::UnaryMessage {
                     message: "test".into(),
-                    receiver: Box::new(ast::Expression::LiteralInteger(1)),
+                    receiver: Box::new(integer(1, 0)),
+                    location: Location { line: 1, column: 2 },
                 }),
+                location: Location { line: 1, column: 7 },
             },
             expression
         );
@@ -710,13 +1071,14 @@ mod tests {
     #[test]
     fn test_parse_expression_binary_operator() {
         let source = b"1 + 2.";
-        let mut parser = Parser::new(source.as_ref(), "test");;
+        let mut parser = Parser::new(source.as_ref(), "test");
         let expression = parser.parse_expression().unwrap();
         assert_eq!(
             ast::Expression::BinaryMessage {
                 message: "+".into(),
-                left: Box::new(ast::Expression::LiteralInteger(1)),
-                right: Box::new(ast::Expression::LiteralInteger(2)),
+                left: Box::new(integer(1, 0)),
+                right: Box::new(integer(2, 4)),
+                location: Location { line: 1, column: 2 },
             },
             expression
         );
@@ -725,13 +1087,14 @@ mod tests {
     #[test]
     fn test_parse_expression_operator_sequence() {
         let source = b"1 <= 2.";
-        let mut parser = Parser::new(source.as_ref(), "test");;
+        let mut parser = Parser::new(source.as_ref(), "test");
         let expression = parser.parse_expression().unwrap();
         assert_eq!(
             ast::Expression::BinaryMessage {
                 message: "<=".into(),
-                left: Box::new(ast::Expression::LiteralInteger(1)),
-                right: Box::new(ast::Expression::LiteralInteger(2)),
+                left: Box::new(integer(1, 0)),
+                right: Box::new(integer(2, 5)),
+                location: Location { line: 1, column: 2 },
             },
             expression
         );
@@ -740,13 +1103,14 @@ mod tests {
     #[test]
     fn test_parse_expression_boolean_literals() {
         let source = b"true || false.";
-        let mut parser = Parser::new(source.as_ref(), "test");;
+        let mut parser = Parser::new(source.as_ref(), "test");
         let expression = parser.parse_expression().unwrap();
         assert_eq!(
             ast::Expression::BinaryMessage {
                 message: "||".into(),
-                left: Box::new(ast::Expression::LiteralBoolean(true)),
-                right: Box::new(ast::Expression::LiteralBoolean(false)),
+                left: Box::new(boolean(true, 0)),
+                right: Box::new(boolean(false, 8)),
+                location: Location { line: 1, column: 5 },
             },
             expression
         );
@@ -755,7 +1119,7 @@ mod tests {
     #[test]
     fn test_parse_expression_complex_messages() {
         let source = b"1 with: a length and: 1 + 2.";
-        let mut parser = Parser::new(source.as_ref(), "test");;
+        let mut parser = Parser::new(source.as_ref(), "test");
         let expression = parser.parse_expression().unwrap();
         assert_eq!(
             ast::Expression::KeywordMessage {
@@ -763,15 +1127,24 @@ mod tests {
                 parameters: vec![
                     ast::Expression::UnaryMessage {
                         message: "length".into(),
-                        receiver: Box::new(ast::Expression::Variable("a".into())),
+                        receiver: Box::new(variable("a", 8)),
+                        location: Location {
+                            line: 1,
+                            column: 10
+                        },
                     },
                     ast::Expression::BinaryMessage {
                         message: "+".into(),
-                        left: Box::new(ast::Expression::LiteralInteger(1)),
-                        right: Box::new(ast::Expression::LiteralInteger(2)),
+                        left: Box::new(integer(1, 22)),
+                        right: Box::new(integer(2, 26)),
+                        location: Location {
+                            line: 1,
+                            column: 24
+                        },
                     },
                 ],
-                receiver: Box::new(ast::Expression::LiteralInteger(1)),
+                receiver: Box::new(integer(1, 0)),
+                location: Location { line: 1, column: 2 },
             },
             expression
         );
@@ -780,12 +1153,13 @@ mod tests {
     #[test]
     fn test_parse_expression_assignment() {
         let source = b"a := 'test'.";
-        let mut parser = Parser::new(source.as_ref(), "test");;
+        let mut parser = Parser::new(source.as_ref(), "test");
         let expression = parser.parse_expression().unwrap();
         assert_eq!(
             ast::Expression::Assignment {
                 variable: "a".into(),
-                value: Box::new(ast::Expression::LiteralString("test".into())),
+                value: Box::new(string("test", 5)),
+                location: at(0),
             },
             expression
         );
@@ -794,7 +1168,7 @@ mod tests {
     #[test]
     fn test_parse_assignment_error() {
         let source = b"1 := 'test'.";
-        let mut parser = Parser::new(source.as_ref(), "test");;
+        let mut parser = Parser::new(source.as_ref(), "test");
         let result = parser.parse_expression().unwrap_err();
         assert_eq!(
             ParseError {
@@ -808,18 +1182,116 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_reports_unknown_escapes() {
+        let source = b"x := 'a\\qb'.";
+        let mut parser = Parser::new(source.as_ref(), "test");
+        let result = parser.parse_expression().unwrap_err();
+        assert_eq!(
+            ParseError {
+                description: "Unknown escape \\q".into(),
+                filename: "test".into(),
+                location: Location { column: 9, line: 1 },
+            },
+            result
+        );
+    }
+
+    #[test]
+    fn test_parse_reports_invalid_radix_literals() {
+        for &(source, description) in &[
+            ("x := 37r1.", "Invalid radix 37"),
+            ("x := 2r102.", "Invalid digits 102 for radix 2"),
+        ] {
+            let mut parser = Parser::new(source.as_bytes(), "test");
+            let result = parser.parse_expression().unwrap_err();
+            assert_eq!(description, result.description, "{}", source);
+            assert_eq!(1, result.location.line, "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_parse_expression_cascade() {
+        let source = b"a foo; + 1; at: 2 put: 3.";
+        let mut parser = Parser::new(source.as_ref(), "test");
+        parser.set_cascades_enabled(true);
+        let expression = parser.parse_expression().unwrap();
+        assert_eq!(
+            ast::Expression::Cascade {
+                receiver: Box::new(variable("a", 0)),
+                parts: vec![
+                    vec![ast::Message {
+                        message: "foo".into(),
+                        parameters: vec![],
+                        location: Location { line: 1, column: 2 },
+                    }],
+                    vec![ast::Message {
+                        message: "+".into(),
+                        parameters: vec![integer(1, 9)],
+                        location: Location { line: 1, column: 7 },
+                    }],
+                    vec![ast::Message {
+                        message: "at:put:".into(),
+                        parameters: vec![integer(2, 16), integer(3, 23)],
+                        location: Location {
+                            line: 1,
+                            column: 12
+                        },
+                    }],
+                ],
+            },
+            expression
+        );
+    }
+
+    #[test]
+    fn test_parse_expression_cascade_with_message_chains() {
+        let source = b"a foo; bar baz + 1 at: 2; qux.";
+        let mut parser = Parser::new(source.as_ref(), "test");
+        parser.set_cascades_enabled(true);
+        let selectors = match parser.parse_expression().unwrap() {
+            ast::Expression::Cascade { receiver, parts } => {
+                assert_eq!(variable("a", 0), *receiver);
+                parts
+                    .iter()
+                    .map(|part| part.iter().map(|m| m.message.as_str()).collect::<Vec<_>>())
+                    .map(|part| part.join(" "))
+                    .collect::<Vec<_>>()
+            }
+            e => panic!("unexpected expression {:?}", e),
+        };
+        assert_eq!(vec!["foo", "bar baz + at:", "qux"], selectors);
+    }
+
+    #[test]
+    fn test_parse_expression_cascade_errors() {
+        let source = b"Test = ( run = ( a foo; bar ) )";
+        let mut parser = Parser::new(source.as_ref(), "test");
+        assert!(parser.parse().is_err());
+
+        let source = b"a; bar.";
+        let mut parser = Parser::new(source.as_ref(), "test");
+        parser.set_cascades_enabled(true);
+        assert_eq!(
+            "Cascade must follow a message send",
+            parser.parse_expression().unwrap_err().description
+        );
+    }
+
     #[test]
     fn test_parse_multiple_assignment() {
         let source = b"a := b := 'test'.";
-        let mut parser = Parser::new(source.as_ref(), "test");;
+        let mut parser = Parser::new(source.as_ref(), "test");
         let expression = parser.parse_expression().unwrap();
         assert_eq!(
             ast::Expression::Assignment {
                 variable: "a".into(),
                 value: Box::new(ast::Expression::Assignment {
                     variable: "b".into(),
-                    value: Box::new(ast::Expression::LiteralString("test".into())),
+                    value: Box::new(string("test", 10)),
+                    location: at(5),
                 }),
+                location: at(0),
             },
             expression
         );
@@ -828,17 +1300,19 @@ mod tests {
     #[test]
     fn test_parse_expression_nested_terms() {
         let source = b"1 + (2 - 1).";
-        let mut parser = Parser::new(source.as_ref(), "test");;
+        let mut parser = Parser::new(source.as_ref(), "test");
         let expression = parser.parse_expression().unwrap();
         assert_eq!(
             ast::Expression::BinaryMessage {
                 message: "+".into(),
-                left: Box::new(ast::Expression::LiteralInteger(1)),
+                left: Box::new(integer(1, 0)),
                 right: Box::new(ast::Expression::BinaryMessage {
                     message: "-".into(),
-                    left: Box::new(ast::Expression::LiteralInteger(2)),
-                    right: Box::new(ast::Expression::LiteralInteger(1)),
+                    left: Box::new(integer(2, 5)),
+                    right: Box::new(integer(1, 9)),
+                    location: Location { line: 1, column: 7 },
                 }),
+                location: Location { line: 1, column: 2 },
             },
             expression
         );
@@ -847,16 +1321,18 @@ mod tests {
     #[test]
     fn test_parse_expression_unary_message_binds_highest() {
         let source = b"1 test + 2.";
-        let mut parser = Parser::new(source.as_ref(), "test");;
+        let mut parser = Parser::new(source.as_ref(), "test");
         let expression = parser.parse_expression().unwrap();
         assert_eq!(
             ast::Expression::BinaryMessage {
                 message: "+".into(),
                 left: Box::new(ast::Expression::UnaryMessage {
-                    receiver: Box::new(ast::Expression::LiteralInteger(1)),
+                    receiver: Box::new(integer(1, 0)),
                     message: "test".into(),
+                    location: Location { line: 1, column: 2 },
                 }),
-                right: Box::new(ast::Expression::LiteralInteger(2)),
+                right: Box::new(integer(2, 9)),
+                location: Location { line: 1, column: 7 },
             },
             expression
         );
@@ -865,22 +1341,16 @@ mod tests {
     #[test]
     fn test_parse_expression_literal_symbols() {
         let source = b"#test #'test-case' #run:with:.";
-        let mut parser = Parser::new(source.as_ref(), "test");;
+        let mut parser = Parser::new(source.as_ref(), "test");
 
         let expression = parser.parse_expression().unwrap();
-        assert_eq!(ast::Expression::LiteralSymbol("test".into()), expression);
+        assert_eq!(symbol("test", 1), expression);
 
         let expression = parser.parse_expression().unwrap();
-        assert_eq!(
-            ast::Expression::LiteralSymbol("test-case".into()),
-            expression
-        );
+        assert_eq!(symbol("test-case", 7), expression);
 
         let expression = parser.parse_expression().unwrap();
-        assert_eq!(
-            ast::Expression::LiteralSymbol("run:with:".into()),
-            expression
-        );
+        assert_eq!(symbol("run:with:", 20), expression);
     }
 
     #[test]
@@ -889,20 +1359,41 @@ mod tests {
         test = (
             ^ 1 + 1.
         )";
-        let mut parser = Parser::new(source.as_ref(), "test");;
+        let mut parser = Parser::new(source.as_ref(), "test");
         let method = parser.parse_method().unwrap();
         assert_eq!(
             ast::Method::Native {
                 name: "test".into(),
                 parameters: vec![],
                 locals: vec![],
-                body: vec![ast::Expression::Return(Box::new(
-                    ast::Expression::BinaryMessage {
+                body: vec![ast::Expression::Return {
+                    value: Box::new(ast::Expression::BinaryMessage {
                         message: "+".into(),
-                        left: Box::new(ast::Expression::LiteralInteger(1)),
-                        right: Box::new(ast::Expression::LiteralInteger(1)),
+                        left: Box::new(ast::Expression::LiteralInteger {
+                            value: 1,
+                            location: Location {
+                                line: 3,
+                                column: 14
+                            },
+                        }),
+                        right: Box::new(ast::Expression::LiteralInteger {
+                            value: 1,
+                            location: Location {
+                                line: 3,
+                                column: 18
+                            },
+                        }),
+                        location: Location {
+                            line: 3,
+                            column: 16
+                        },
+                    }),
+                    location: Location {
+                        line: 3,
+                        column: 12
                     },
-                )),],
+                }],
+                location: Location { line: 2, column: 8 },
             },
             method
         );
@@ -917,7 +1408,7 @@ mod tests {
                 '' println.
             )
         )";
-        let mut parser = Parser::new(source.as_ref(), "test");;
+        let mut parser = Parser::new(source.as_ref(), "test");
 
         let class = parser.parse().unwrap();
         assert_eq!("Echo", class.name);
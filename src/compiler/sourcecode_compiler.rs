@@ -1,13 +1,16 @@
-use crate::compiler::{ast, ParseError, Parser};
-use crate::vmobjects::SClass;
-use std::collections::HashMap;
+use crate::compiler::codegen::{self, CodegenError};
+use crate::compiler::compiled::{CompiledClass, InheritedFields};
+use crate::compiler::folding::{self, KnownPrimitives};
+use crate::compiler::optimizer::{self, OptimizationLevel};
+use crate::compiler::{ParseError, Parser};
 use std::fs::File;
-use std::io::{self, BufReader};
+use std::io::{self, BufRead, BufReader};
 use std::path::Path;
 
 #[derive(Debug)]
 pub enum CompileError {
     ParseError(ParseError),
+    CodegenError(CodegenError),
     IoError(io::Error),
 }
 
@@ -17,33 +20,158 @@ impl From<ParseError> for CompileError {
     }
 }
 
+impl From<CodegenError> for CompileError {
+    fn from(source: CodegenError) -> Self {
+        CompileError::CodegenError(source)
+    }
+}
+
 impl From<io::Error> for CompileError {
     fn from(source: io::Error) -> Self {
         CompileError::IoError(source)
     }
 }
 
-pub fn compile_path<P: AsRef<Path>>(path: P) -> Result<SClass, CompileError> {
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CompileOptions {
+    pub optimization_level: OptimizationLevel,
+    /// Evaluate arithmetic and comparisons between numeric literals at
+    /// compile time. Off by default since it changes what a debugger or a
+    /// redefined `Integer>>+` observes.
+    pub fold_constants: bool,
+    /// Selectors folding may assume behave like the built-in primitives.
+    pub known_primitives: KnownPrimitives,
+    /// Accept the Smalltalk cascade syntax `receiver foo; bar`. Strict SOM
+    /// sources never use it, so it is off by default.
+    pub cascades: bool,
+}
+
+pub fn compile_path<P: AsRef<Path>>(
+    path: P,
+    options: &CompileOptions,
+) -> Result<CompiledClass, CompileError> {
     let file = File::open(&path)?;
     let reader = BufReader::new(file);
-    let mut parser = Parser::new(reader, path);
-    let class = parser.parse()?;
+    compile_source(reader, path, options)
+}
 
-    compile(class)
+/// Compiles a class whose superclass declares no fields.
+pub fn compile_source<R: BufRead, P: AsRef<Path>>(
+    reader: R,
+    filename: P,
+    options: &CompileOptions,
+) -> Result<CompiledClass, CompileError> {
+    compile_source_with_fields(reader, filename, options, &InheritedFields::default())
 }
 
-fn compile(class: ast::Class) -> Result<SClass, CompileError> {
-    Ok(SClass {
-        name: class.name,
-        superclass: None,
-        invokables: HashMap::new(),
-    })
+/// Compiles a class whose methods can access the `inherited` fields.
+pub fn compile_source_with_fields<R: BufRead, P: AsRef<Path>>(
+    reader: R,
+    filename: P,
+    options: &CompileOptions,
+    inherited: &InheritedFields,
+) -> Result<CompiledClass, CompileError> {
+    let filename = filename.as_ref().to_string_lossy();
+    let mut parser = Parser::new(reader, filename.as_ref());
+    parser.set_cascades_enabled(options.cascades);
+    let mut class = parser.parse()?;
+    if options.fold_constants {
+        folding::fold_class(&mut class, &options.known_primitives);
+    }
+
+    let mut class = codegen::generate_class(&class, &filename, inherited)?;
+    if options.optimization_level >= OptimizationLevel::Peephole {
+        optimizer::optimize_class(&mut class);
+    }
+
+    Ok(class)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::compiler::{CompiledMethod, Literal};
+    use crate::interpreter::Bytecode;
+
+    #[test]
+    fn test_compile_simple_class() {
+        let source = b"
+        Hello = Object (
+            | greeting |
+            run = ( greeting println )
+            ----
+            new = primitive
+        )";
+        let class =
+            compile_source(source.as_ref(), "Hello.som", &CompileOptions::default()).unwrap();
+        assert_eq!("Hello", class.name);
+        assert_eq!(Some("Object".into()), class.superclass);
+        assert_eq!(vec!["greeting"], class.instance_fields);
+        assert_eq!("run", class.instance_methods[0].signature());
+        assert_eq!("new", class.class_methods[0].signature());
+    }
 
     #[test]
-    fn test_compile_simple_class() {}
+    fn test_compile_missing_file() {
+        match compile_path("does/not/exist.som", &CompileOptions::default()) {
+            Err(CompileError::IoError(e)) => assert_eq!(io::ErrorKind::NotFound, e.kind()),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_compile_optimization_levels() {
+        let source = b"Hello = ( run = ( ^ self ) )";
+        let options = CompileOptions {
+            optimization_level: OptimizationLevel::None,
+            ..CompileOptions::default()
+        };
+
+        let class = compile_source(source.as_ref(), "Hello.som", &options).unwrap();
+        match &class.instance_methods[0] {
+            CompiledMethod::Bytecode { code, .. } => assert_eq!(2, code.bytecodes.len()),
+            m => panic!("unexpected method {:?}", m),
+        }
+
+        let options = CompileOptions {
+            optimization_level: OptimizationLevel::Peephole,
+            ..CompileOptions::default()
+        };
+        let class = compile_source(source.as_ref(), "Hello.som", &options).unwrap();
+        match &class.instance_methods[0] {
+            CompiledMethod::Bytecode { code, .. } => {
+                assert_eq!(vec![Bytecode::ReturnSelf], code.bytecodes)
+            }
+            m => panic!("unexpected method {:?}", m),
+        }
+    }
+
+    #[test]
+    fn test_compile_fold_constants() {
+        let source = b"Hello = ( run = ( ^ 2 + 3 ) )".as_ref();
+
+        let mut options = CompileOptions::default();
+        let class = compile_source(source, "Hello.som", &options).unwrap();
+        match &class.instance_methods[0] {
+            CompiledMethod::Bytecode { code, .. } => assert_eq!(4, code.bytecodes.len()),
+            m => panic!("unexpected method {:?}", m),
+        }
+
+        let integer = b"Integer = ( + other = primitive )";
+        let integer = compile_source(integer.as_ref(), "Integer.som", &options).unwrap();
+        options.fold_constants = true;
+        options.known_primitives.add_class(&integer);
+
+        let class = compile_source(source, "Hello.som", &options).unwrap();
+        match &class.instance_methods[0] {
+            CompiledMethod::Bytecode { code, .. } => {
+                assert_eq!(vec![Literal::Integer(5)], code.literals);
+                assert_eq!(
+                    vec![Bytecode::PushConstant { index: 0 }, Bytecode::ReturnLocal],
+                    code.bytecodes
+                );
+            }
+            m => panic!("unexpected method {:?}", m),
+        }
+    }
 }
@@ -31,6 +31,7 @@ pub enum TokenKind {
     Plus,
     Pound,
     Primitive,
+    Semicolon,
     Separator,
     Star,
     String,
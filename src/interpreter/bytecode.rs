@@ -18,6 +18,11 @@ pub enum Bytecode {
     SuperSend { index: u8 },
     ReturnLocal,
     ReturnNonLocal,
+    PushSelf,
+    PushNil,
+    PushZero,
+    PushOne,
+    ReturnSelf,
 }
 
 impl From<Bytecode> for Vec<u8> {
@@ -39,6 +44,11 @@ impl From<Bytecode> for Vec<u8> {
             Bytecode::SuperSend { index } => vec![13, index],
             Bytecode::ReturnLocal => vec![14],
             Bytecode::ReturnNonLocal => vec![15],
+            Bytecode::PushSelf => vec![16],
+            Bytecode::PushNil => vec![17],
+            Bytecode::PushZero => vec![18],
+            Bytecode::PushOne => vec![19],
+            Bytecode::ReturnSelf => vec![20],
         }
     }
 }
@@ -114,6 +124,11 @@ impl<I: Iterator<Item = u8>> BytecodeIterator<I> {
             },
             14 => Bytecode::ReturnLocal,
             15 => Bytecode::ReturnNonLocal,
+            16 => Bytecode::PushSelf,
+            17 => Bytecode::PushNil,
+            18 => Bytecode::PushZero,
+            19 => Bytecode::PushOne,
+            20 => Bytecode::ReturnSelf,
             c => return Err(BytecodeIteratorError::UnknownBytecode(c)),
         };
 
@@ -164,12 +179,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_bytecode_roundtrip() {
+        let bytecodes = vec![
+            Bytecode::PushSelf,
+            Bytecode::PushNil,
+            Bytecode::PushZero,
+            Bytecode::PushOne,
+            Bytecode::PopField { index: 7 },
+            Bytecode::ReturnSelf,
+        ];
+        let encoded = bytecodes
+            .iter()
+            .flat_map(|&b| Vec::<u8>::from(b))
+            .collect::<Vec<_>>();
+        let decoded = BytecodeIterator::new(encoded)
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(bytecodes, decoded);
+    }
+
     #[test]
     fn test_bytecode_iterator_unknown_bytecode() {
-        let error = BytecodeIterator::new(vec![16])
+        let error = BytecodeIterator::new(vec![255])
             .collect::<Result<Vec<_>>>()
             .unwrap_err();
-        assert_eq!(BytecodeIteratorError::UnknownBytecode(16), error);
+        assert_eq!(BytecodeIteratorError::UnknownBytecode(255), error);
     }
 
     #[test]
@@ -0,0 +1,161 @@
+use crate::compiler::{CompiledCode, LineTable, Literal, Location};
+use crate::interpreter::{self, Bytecode, InlineCache, Instruction};
+use crate::vm::Universe;
+use crate::vmobjects::{SSymbol, SymbolTable, Value};
+use std::cell::{Cell, OnceCell};
+use std::rc::Rc;
+
+/// The executable form of a method or block body. The compiled bytecodes
+/// are kept untouched so quickened instructions can always be reset.
+pub struct Code {
+    num_parameters: usize,
+    num_locals: usize,
+    literals: Vec<Literal>,
+    /// The runtime values of the literals, created on first use.
+    constants: Vec<OnceCell<Value>>,
+    symbols: Vec<Option<Rc<SSymbol>>>,
+    bytecodes: Vec<Bytecode>,
+    instructions: Vec<Cell<Instruction>>,
+    inline_caches: Vec<Option<InlineCache>>,
+    blocks: Vec<Rc<Code>>,
+    line_table: LineTable,
+}
+
+impl Code {
+    /// Builds the runtime form of `code`, interning its symbol literals so
+    /// sends can use them as lookup keys directly.
+    pub fn new(code: CompiledCode, symbols: &mut SymbolTable) -> Code {
+        let instructions = code
+            .bytecodes
+            .iter()
+            .map(|&bytecode| Cell::new(Instruction::Bytecode(bytecode)))
+            .collect();
+
+        Code {
+            num_parameters: code.num_parameters,
+            num_locals: code.num_locals,
+            symbols: code
+                .literals
+                .iter()
+                .map(|literal| match literal {
+                    Literal::Symbol(text) => Some(symbols.intern(text)),
+                    _ => None,
+                })
+                .collect(),
+            constants: code.literals.iter().map(|_| OnceCell::new()).collect(),
+            literals: code.literals,
+            inline_caches: code
+                .bytecodes
+                .iter()
+                .map(|bytecode| match bytecode {
+                    Bytecode::Send { .. } => Some(InlineCache::new()),
+                    _ => None,
+                })
+                .collect(),
+            bytecodes: code.bytecodes,
+            instructions,
+            blocks: code
+                .blocks
+                .into_iter()
+                .map(|block| Rc::new(Code::new(block, symbols)))
+                .collect(),
+            line_table: code.line_table,
+        }
+    }
+
+    pub fn num_parameters(&self) -> usize {
+        self.num_parameters
+    }
+
+    pub fn num_locals(&self) -> usize {
+        self.num_locals
+    }
+
+    pub fn literal(&self, index: u8) -> &Literal {
+        &self.literals[index as usize]
+    }
+
+    /// The value of the literal at `index`. It is created once, so every
+    /// evaluation of a literal pushes the same object.
+    pub fn constant(&self, index: u8, universe: &mut Universe) -> interpreter::Result<Value> {
+        let cell = &self.constants[index as usize];
+        if let Some(value) = cell.get() {
+            return Ok(value.clone());
+        }
+
+        let value = universe.literal_value(&self.literals[index as usize])?;
+        Ok(cell.get_or_init(|| value).clone())
+    }
+
+    /// The interned symbol literal at `index`.
+    pub fn symbol(&self, index: u8) -> &Rc<SSymbol> {
+        self.symbols[index as usize]
+            .as_ref()
+            .expect("symbol literal")
+    }
+
+    pub fn bytecode_count(&self) -> usize {
+        self.bytecodes.len()
+    }
+
+    pub fn bytecode(&self, index: usize) -> Option<Bytecode> {
+        self.bytecodes.get(index).cloned()
+    }
+
+    pub fn instruction(&self, index: usize) -> Option<Instruction> {
+        self.instructions.get(index).map(Cell::get)
+    }
+
+    pub fn rewrite(&self, index: usize, instruction: Instruction) {
+        self.instructions[index].set(instruction);
+    }
+
+    pub fn block(&self, index: u8) -> &Rc<Code> {
+        &self.blocks[index as usize]
+    }
+
+    pub fn location(&self, index: usize) -> Option<Location> {
+        self.line_table.location(index)
+    }
+
+    /// The inline cache of the `Send` at `index`.
+    pub fn inline_cache(&self, index: usize) -> &InlineCache {
+        self.inline_caches[index]
+            .as_ref()
+            .expect("inline cache of a send")
+    }
+
+    /// Every `Send` with its bytecode index, selector and inline cache.
+    pub fn call_sites(&self) -> impl Iterator<Item = (usize, &str, &InlineCache)> {
+        self.bytecodes
+            .iter()
+            .zip(&self.inline_caches)
+            .enumerate()
+            .filter_map(move |(index, (bytecode, cache))| match (bytecode, cache) {
+                (Bytecode::Send { index: literal }, Some(cache)) => {
+                    Some((index, self.symbol(*literal).as_str(), cache))
+                }
+                _ => None,
+            })
+    }
+
+    pub fn blocks(&self) -> &[Rc<Code>] {
+        &self.blocks
+    }
+
+    /// Undoes all quickening and empties the inline caches, here and in
+    /// nested blocks.
+    pub fn reset(&self) {
+        for (instruction, &bytecode) in self.instructions.iter().zip(&self.bytecodes) {
+            instruction.set(Instruction::Bytecode(bytecode));
+        }
+
+        for cache in self.inline_caches.iter().flatten() {
+            cache.clear();
+        }
+
+        for block in &self.blocks {
+            block.reset();
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use crate::compiler::Location;
+use crate::interpreter::Code;
+use crate::vmobjects::{SMethod, Value};
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::rc::Rc;
+
+/// The activation of a method or block. Argument 0 holds the receiver for
+/// methods and the block itself for blocks.
+pub struct Frame {
+    method: Rc<SMethod>,
+    code: Rc<Code>,
+    arguments: RefCell<Vec<Value>>,
+    locals: RefCell<Vec<Value>>,
+    outer: Option<Rc<Frame>>,
+    active: Cell<bool>,
+    pc: Cell<usize>,
+}
+
+impl Frame {
+    pub fn new(
+        method: Rc<SMethod>,
+        code: Rc<Code>,
+        arguments: Vec<Value>,
+        outer: Option<Rc<Frame>>,
+    ) -> Frame {
+        let locals = vec![Value::Nil; code.num_locals()];
+
+        Frame {
+            method,
+            code,
+            arguments: RefCell::new(arguments),
+            locals: RefCell::new(locals),
+            outer,
+            active: Cell::new(true),
+            pc: Cell::new(0),
+        }
+    }
+
+    pub fn method(&self) -> &Rc<SMethod> {
+        &self.method
+    }
+
+    pub fn code(&self) -> &Rc<Code> {
+        &self.code
+    }
+
+    /// The frame `depth` lexical levels out, 0 being this frame.
+    pub fn context(&self, depth: u8) -> &Frame {
+        let mut frame = self;
+        for _ in 0..depth {
+            frame = frame.outer.as_ref().expect("outer context");
+        }
+
+        frame
+    }
+
+    /// The frame of the method a block frame was created in, or `None` for
+    /// a method frame.
+    pub fn home(&self) -> Option<&Rc<Frame>> {
+        let mut home = self.outer.as_ref()?;
+        while let Some(outer) = &home.outer {
+            home = outer;
+        }
+
+        Some(home)
+    }
+
+    pub fn is_block(&self) -> bool {
+        self.outer.is_some()
+    }
+
+    /// Whether the frame is still running. Blocks can only return
+    /// non-locally to an active home frame.
+    pub fn is_active(&self) -> bool {
+        self.active.get()
+    }
+
+    pub fn deactivate(&self) {
+        self.active.set(false);
+    }
+
+    /// The index of the instruction being executed.
+    pub fn pc(&self) -> usize {
+        self.pc.get()
+    }
+
+    pub fn set_pc(&self, pc: usize) {
+        self.pc.set(pc);
+    }
+
+    /// How many blocks this frame is nested in, 0 for a method frame.
+    pub fn block_depth(&self) -> usize {
+        let mut depth = 0;
+        let mut frame = self;
+        while let Some(outer) = &frame.outer {
+            depth += 1;
+            frame = outer;
+        }
+
+        depth
+    }
+
+    /// Describes the frame for a stack trace.
+    pub fn info(&self) -> FrameInfo {
+        let holder = self.method.holder();
+        FrameInfo {
+            class: holder.map_or("?".into(), |holder| holder.name().to_string()),
+            selector: self.method.signature().as_str().into(),
+            block_depth: self.block_depth(),
+            location: self.code.location(self.pc()),
+        }
+    }
+
+    pub fn receiver(&self) -> Value {
+        let mut frame = self;
+        while let Some(outer) = &frame.outer {
+            frame = outer;
+        }
+
+        frame.argument(0)
+    }
+
+    pub fn argument(&self, index: u8) -> Value {
+        self.arguments.borrow()[index as usize].clone()
+    }
+
+    pub fn set_argument(&self, index: u8, value: Value) {
+        self.arguments.borrow_mut()[index as usize] = value;
+    }
+
+    pub fn local(&self, index: u8) -> Value {
+        self.locals.borrow()[index as usize].clone()
+    }
+
+    pub fn set_local(&self, index: u8, value: Value) {
+        self.locals.borrow_mut()[index as usize] = value;
+    }
+}
+
+/// One line of a stack trace.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FrameInfo {
+    /// The holder of the method, `Foo class` for class-side methods.
+    pub class: String,
+    pub selector: String,
+    /// 0 for a method, otherwise how deeply nested the block is.
+    pub block_depth: usize,
+    pub location: Option<Location>,
+}
+
+impl fmt::Display for FrameInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}>>{}", self.class, self.selector)?;
+        if self.block_depth > 0 {
+            write!(f, " (block depth {})", self.block_depth)?;
+        }
+        if let Some(location) = self.location {
+            write!(f, " at {}", location)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_block() {
+            write!(f, "Block in {:?}", self.method)
+        } else {
+            write!(f, "{:?}", self.method)
+        }
+    }
+}
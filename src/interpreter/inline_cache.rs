@@ -0,0 +1,174 @@
+use crate::vmobjects::{SClass, SMethod};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+/// How many receiver classes a call site remembers before it gives up and
+/// goes megamorphic.
+pub const POLYMORPHIC_LIMIT: usize = 4;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CacheState {
+    Empty,
+    Monomorphic,
+    Polymorphic(usize),
+    Megamorphic,
+}
+
+struct Entry {
+    class: Rc<SClass>,
+    method: Rc<SMethod>,
+}
+
+/// The cache of one `Send` call site. Entries are only valid for the method
+/// epoch they were filled in, so changing any method dictionary empties
+/// every cache on its next use.
+pub struct InlineCache {
+    entries: RefCell<Vec<Entry>>,
+    megamorphic: Cell<bool>,
+    epoch: Cell<u64>,
+    hits: Cell<u64>,
+    misses: Cell<u64>,
+}
+
+impl InlineCache {
+    pub fn new() -> InlineCache {
+        InlineCache {
+            entries: RefCell::new(vec![]),
+            megamorphic: Cell::new(false),
+            epoch: Cell::new(0),
+            hits: Cell::new(0),
+            misses: Cell::new(0),
+        }
+    }
+
+    pub fn lookup(&self, class: &Rc<SClass>, epoch: u64) -> Option<Rc<SMethod>> {
+        if self.epoch.get() != epoch {
+            self.clear();
+            self.epoch.set(epoch);
+        }
+
+        let found = self
+            .entries
+            .borrow()
+            .iter()
+            .find(|entry| Rc::ptr_eq(&entry.class, class))
+            .map(|entry| entry.method.clone());
+
+        match found {
+            Some(_) => self.hits.set(self.hits.get() + 1),
+            None => self.misses.set(self.misses.get() + 1),
+        }
+
+        found
+    }
+
+    /// Remembers the method found after a miss, unless the call site has
+    /// already seen too many classes.
+    pub fn insert(&self, class: Rc<SClass>, method: Rc<SMethod>) {
+        if self.megamorphic.get() {
+            return;
+        }
+
+        let mut entries = self.entries.borrow_mut();
+        if entries.len() == POLYMORPHIC_LIMIT {
+            entries.clear();
+            self.megamorphic.set(true);
+        } else {
+            entries.push(Entry { class, method });
+        }
+    }
+
+    pub fn clear(&self) {
+        self.entries.borrow_mut().clear();
+        self.megamorphic.set(false);
+    }
+
+    pub fn state(&self) -> CacheState {
+        match self.entries.borrow().len() {
+            _ if self.megamorphic.get() => CacheState::Megamorphic,
+            0 => CacheState::Empty,
+            1 => CacheState::Monomorphic,
+            n => CacheState::Polymorphic(n),
+        }
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits.get()
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses.get()
+    }
+}
+
+impl Default for InlineCache {
+    fn default() -> Self {
+        InlineCache::new()
+    }
+}
+
+/// A snapshot of one call site's cache, for tuning.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CallSiteStats {
+    pub method: String,
+    pub bytecode_index: usize,
+    pub selector: String,
+    pub state: CacheState,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vmobjects::{MethodBody, SymbolTable};
+
+    fn class(name: &str) -> (Rc<SClass>, Rc<SMethod>) {
+        let class = Rc::new(SClass::new(name, None));
+        let method = Rc::new(SMethod::new(
+            SymbolTable::new().intern("foo"),
+            &class,
+            MethodBody::MissingPrimitive,
+        ));
+        (class, method)
+    }
+
+    #[test]
+    fn test_inline_cache_transitions() {
+        let cache = InlineCache::new();
+        assert_eq!(CacheState::Empty, cache.state());
+
+        let classes = (0..=POLYMORPHIC_LIMIT)
+            .map(|i| class(&format!("Class{}", i)))
+            .collect::<Vec<_>>();
+
+        let (first, method) = &classes[0];
+        assert!(cache.lookup(first, 0).is_none());
+        cache.insert(first.clone(), method.clone());
+        assert_eq!(CacheState::Monomorphic, cache.state());
+        assert!(Rc::ptr_eq(method, &cache.lookup(first, 0).unwrap()));
+
+        let (second, method) = &classes[1];
+        cache.insert(second.clone(), method.clone());
+        assert_eq!(CacheState::Polymorphic(2), cache.state());
+
+        for (class, method) in &classes[2..] {
+            cache.insert(class.clone(), method.clone());
+        }
+        assert_eq!(CacheState::Megamorphic, cache.state());
+        assert!(cache.lookup(first, 0).is_none());
+
+        assert_eq!(1, cache.hits());
+        assert_eq!(2, cache.misses());
+    }
+
+    #[test]
+    fn test_inline_cache_epoch_invalidates() {
+        let cache = InlineCache::new();
+        let (class, method) = class("Foo");
+        cache.insert(class.clone(), method);
+        assert!(cache.lookup(&class, 0).is_some());
+        assert!(cache.lookup(&class, 1).is_none());
+        assert_eq!(CacheState::Empty, cache.state());
+    }
+}
@@ -1 +1,1014 @@
-mod bytecode;
+pub mod bytecode;
+mod code;
+mod frame;
+mod inline_cache;
+mod quicken;
+
+pub use self::bytecode::Bytecode;
+pub use self::code::Code;
+pub use self::frame::{Frame, FrameInfo};
+pub use self::inline_cache::{CacheState, CallSiteStats, InlineCache, POLYMORPHIC_LIMIT};
+pub use self::quicken::{Instruction, IntegerOp};
+
+use crate::vm::{LoadError, Universe};
+use crate::vmobjects::{MethodBody, SArray, SBlock, SMethod, SSymbol, Value};
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+use std::result;
+
+#[derive(Debug)]
+pub enum InterpreterError {
+    LoadError(LoadError),
+    RuntimeError(String),
+    /// A `^` inside a block, unwinding the frames above its home method.
+    NonLocalReturn {
+        home: Rc<Frame>,
+        value: Value,
+    },
+    /// `System>>exit:` was sent, unwinding every frame.
+    Exit(i32),
+}
+
+impl From<LoadError> for InterpreterError {
+    fn from(source: LoadError) -> Self {
+        InterpreterError::LoadError(source)
+    }
+}
+
+pub type Result<T> = result::Result<T, InterpreterError>;
+
+fn runtime_error<T>(description: String) -> Result<T> {
+    Err(InterpreterError::RuntimeError(description))
+}
+
+/// The number of arguments a message with `selector` takes.
+pub fn arity(selector: &str) -> usize {
+    match selector.chars().next() {
+        Some(c) if c.is_alphabetic() => selector.matches(':').count(),
+        Some(_) => 1,
+        None => 0,
+    }
+}
+
+/// Fails unless `count` arguments are right for `selector`.
+pub fn check_arity(selector: &str, count: usize) -> Result<()> {
+    let expected = arity(selector);
+    if count == expected {
+        Ok(())
+    } else {
+        runtime_error(format!(
+            "#{} takes {} arguments, got {}",
+            selector, expected, count
+        ))
+    }
+}
+
+pub fn send(
+    universe: &mut Universe,
+    receiver: Value,
+    selector: &Rc<SSymbol>,
+    mut arguments: Vec<Value>,
+) -> Result<Value> {
+    let class = universe.class_of(&receiver);
+    let method = universe.lookup_method(&class, selector);
+    arguments.insert(0, receiver);
+    dispatch(universe, method, selector, arguments)
+}
+
+/// Invokes `method`, or tells the receiver it does not understand
+/// `selector` when the lookup found nothing.
+fn dispatch(
+    universe: &mut Universe,
+    method: Option<Rc<SMethod>>,
+    selector: &Rc<SSymbol>,
+    mut arguments: Vec<Value>,
+) -> Result<Value> {
+    if let Some(method) = method {
+        return invoke(universe, &method, arguments);
+    }
+
+    let receiver = arguments.remove(0);
+    let class = universe.class_of(&receiver);
+    let does_not_understand = universe.load_symbol("doesNotUnderstand:arguments:");
+    match universe.lookup_method(&class, &does_not_understand) {
+        Some(method) => {
+            let arguments = vec![
+                receiver,
+                Value::Symbol(selector.clone()),
+                Value::Array(Rc::new(SArray::new(arguments))),
+            ];
+            invoke(universe, &method, arguments)
+        }
+        None => runtime_error(not_understood(universe, &receiver, selector)),
+    }
+}
+
+/// The error reported when `receiver` has no method for `selector`.
+pub fn not_understood(universe: &Universe, receiver: &Value, selector: &SSymbol) -> String {
+    format!(
+        "{} does not understand #{}",
+        universe.class_of(receiver).name(),
+        selector.as_str()
+    )
+}
+
+pub fn invoke(
+    universe: &mut Universe,
+    method: &Rc<SMethod>,
+    arguments: Vec<Value>,
+) -> Result<Value> {
+    match method.body() {
+        MethodBody::Bytecode(code) if arguments.len() != code.num_parameters() + 1 => {
+            runtime_error(format!(
+                "{:?} takes {} arguments, got {}",
+                method,
+                code.num_parameters(),
+                arguments.len().saturating_sub(1)
+            ))
+        }
+        MethodBody::Bytecode(code) => {
+            let frame = Rc::new(Frame::new(method.clone(), code.clone(), arguments, None));
+            match activate(universe, &frame) {
+                Err(InterpreterError::NonLocalReturn { home, value }) => {
+                    if Rc::ptr_eq(&home, &frame) {
+                        Ok(value)
+                    } else {
+                        Err(InterpreterError::NonLocalReturn { home, value })
+                    }
+                }
+                result => result,
+            }
+        }
+        MethodBody::Primitive(primitive) => {
+            let primitive = *primitive;
+            let depth = universe.frames().len();
+            let result = panic::catch_unwind(AssertUnwindSafe(|| primitive(universe, arguments)));
+            result.unwrap_or_else(|payload| {
+                universe.record_error_trace();
+                universe.truncate_frames(depth);
+                runtime_error(format!(
+                    "Primitive {:?} panicked: {}",
+                    method,
+                    panic_message(&payload)
+                ))
+            })
+        }
+        MethodBody::MissingPrimitive => {
+            runtime_error(format!("Primitive {:?} is not implemented", method))
+        }
+    }
+}
+
+fn panic_message(payload: &Box<dyn Any + Send>) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
+
+/// Runs `block` with `arguments`, which exclude the block itself.
+pub fn evaluate_block(
+    universe: &mut Universe,
+    block: &Rc<SBlock>,
+    mut arguments: Vec<Value>,
+) -> Result<Value> {
+    arguments.insert(0, Value::Block(block.clone()));
+    let frame = Rc::new(Frame::new(
+        block.method().clone(),
+        block.code().clone(),
+        arguments,
+        Some(block.context().clone()),
+    ));
+    activate(universe, &frame)
+}
+
+/// Executes `frame` on top of the universe's frame stack. The frame is
+/// inactive afterwards, however it was left. The innermost frame to see an
+/// error records the stack trace.
+fn activate(universe: &mut Universe, frame: &Rc<Frame>) -> Result<Value> {
+    universe.push_frame(frame.clone());
+    let result = execute(universe, frame);
+    if let Err(InterpreterError::RuntimeError(_)) = result {
+        universe.record_error_trace();
+    }
+    universe.pop_frame();
+    frame.deactivate();
+    result
+}
+
+/// Handles a non-local return from a block whose home method has already
+/// returned by sending `escapedBlock:` to the block's sender. Its answer
+/// becomes the value of the block.
+fn escaped_block(universe: &mut Universe, frame: &Frame) -> Result<Value> {
+    let frames = universe.frames();
+    let sender = match frames.len() {
+        n if n >= 2 => frames[n - 2].receiver(),
+        _ => frame.receiver(),
+    };
+
+    let selector = universe.load_symbol("escapedBlock:");
+    send(universe, sender, &selector, vec![frame.argument(0)])
+}
+
+fn field(receiver: &Value, index: u8) -> Result<Value> {
+    let value = match receiver {
+        Value::Object(object) => object.field(index as usize),
+        Value::Class(class) => class.class_field(index as usize),
+        _ => None,
+    };
+
+    match value {
+        Some(value) => Ok(value),
+        None => runtime_error(format!("Field {} out of range in {:?}", index, receiver)),
+    }
+}
+
+fn set_field(receiver: &Value, index: u8, value: Value) -> Result<()> {
+    let stored = match receiver {
+        Value::Object(object) => object.set_field(index as usize, value),
+        Value::Class(class) => class.set_class_field(index as usize, value),
+        _ => false,
+    };
+
+    if stored {
+        Ok(())
+    } else {
+        runtime_error(format!("Field {} out of range in {:?}", index, receiver))
+    }
+}
+
+/// Reads the global `name`, loading a class of that name if there is none.
+/// Failing both, `receiver` is sent `unknownGlobal:`.
+fn global(universe: &mut Universe, receiver: Value, name: &Rc<SSymbol>) -> Result<Value> {
+    if let Some(value) = universe.global(name.as_str()) {
+        return Ok(value);
+    }
+
+    match universe.load_class(name.as_str()) {
+        Ok(class) => return Ok(Value::Class(class)),
+        Err(LoadError::ClassNotFound(_)) => {}
+        Err(e) => return Err(e.into()),
+    }
+
+    let class = universe.class_of(&receiver);
+    let unknown_global = universe.load_symbol("unknownGlobal:");
+    match universe.lookup_method(&class, &unknown_global) {
+        Some(method) => invoke(
+            universe,
+            &method,
+            vec![receiver, Value::Symbol(name.clone())],
+        ),
+        None => runtime_error(format!("Unknown global {}", name.as_str())),
+    }
+}
+
+/// Picks the quickened form of a `Send` from the operands it is about to
+/// be sent with.
+fn quicken_send(
+    universe: &Universe,
+    stack: &[Value],
+    selector: &SSymbol,
+    index: u8,
+) -> Instruction {
+    let op = match IntegerOp::from_selector(selector.as_str()) {
+        Some(op) => op,
+        None => return Instruction::SendCached { index },
+    };
+
+    let operands = &stack[stack.len() - 2..];
+    let integers = matches!(operands, [Value::Integer(_), Value::Integer(_)]);
+    let primitive = universe
+        .core_classes()
+        .integer
+        .lookup(selector)
+        .is_some_and(|method| matches!(method.body(), MethodBody::Primitive(_)));
+
+    if integers && primitive {
+        Instruction::SendInteger { index, op }
+    } else {
+        Instruction::SendCached { index }
+    }
+}
+
+fn send_cached(
+    universe: &mut Universe,
+    code: &Code,
+    pc: usize,
+    stack: &mut Vec<Value>,
+    selector: &Rc<SSymbol>,
+) -> Result<()> {
+    let receiver_index = stack.len() - 1 - arity(selector.as_str());
+    let class = universe.class_of(&stack[receiver_index]);
+    let cache = code.inline_cache(pc);
+    let method = match cache.lookup(&class, universe.method_epoch()) {
+        Some(method) => Some(method),
+        None => {
+            let method = universe.lookup_method(&class, selector);
+            if let Some(method) = &method {
+                cache.insert(class, method.clone());
+            }
+            method
+        }
+    };
+
+    let arguments = stack.split_off(receiver_index);
+    let result = dispatch(universe, method, selector, arguments)?;
+    stack.push(result);
+    Ok(())
+}
+
+fn super_send(
+    universe: &mut Universe,
+    frame: &Frame,
+    stack: &mut Vec<Value>,
+    selector: &Rc<SSymbol>,
+) -> Result<()> {
+    let method = frame.method();
+    let superclass = method.holder().and_then(|holder| holder.superclass());
+    let found = match superclass {
+        Some(superclass) => universe.lookup_method(&superclass, selector),
+        None => return runtime_error(format!("{:?} has no superclass", method)),
+    };
+
+    let arguments = stack.split_off(stack.len() - 1 - arity(selector.as_str()));
+    let result = dispatch(universe, found, selector, arguments)?;
+    stack.push(result);
+    Ok(())
+}
+
+/// Runs `frame` until it returns. With quickening enabled, each instruction
+/// is rewritten in place the first time it executes.
+pub fn execute(universe: &mut Universe, frame: &Rc<Frame>) -> Result<Value> {
+    let code = frame.code().clone();
+    let mut stack = Vec::new();
+    let mut pc = 0;
+
+    loop {
+        let mut instruction = match code.instruction(pc) {
+            Some(instruction) => instruction,
+            None => return runtime_error(format!("{:?} ran past its end", frame.method())),
+        };
+
+        if let (true, Instruction::Bytecode(bytecode)) =
+            (universe.quickening_enabled(), instruction)
+        {
+            let fused = code
+                .bytecode(pc + 1)
+                .and_then(|next| quicken::fuse(bytecode, next));
+            if let Some(fused) = fused {
+                code.rewrite(pc, fused);
+                instruction = fused;
+            }
+        }
+
+        let current = pc;
+        pc += instruction.width();
+        frame.set_pc(current);
+
+        match instruction {
+            Instruction::Bytecode(Bytecode::Halt) => return Ok(stack.pop().unwrap_or(Value::Nil)),
+            Instruction::Bytecode(Bytecode::Dup) => {
+                let value = stack.last().expect("value on stack").clone();
+                stack.push(value);
+            }
+            Instruction::Bytecode(Bytecode::PushLocal { index, context }) => {
+                stack.push(frame.context(context).local(index))
+            }
+            Instruction::Bytecode(Bytecode::PushArgument { index, context }) => {
+                stack.push(frame.context(context).argument(index))
+            }
+            Instruction::Bytecode(Bytecode::PushField { index }) => {
+                stack.push(field(&frame.receiver(), index)?)
+            }
+            Instruction::Bytecode(Bytecode::PushBlock { index }) => {
+                let block = SBlock::new(code.block(index).clone(), frame.clone());
+                stack.push(Value::Block(Rc::new(block)))
+            }
+            Instruction::Bytecode(Bytecode::PushConstant { index }) => {
+                stack.push(code.constant(index, universe)?)
+            }
+            Instruction::Bytecode(Bytecode::PushGlobal { index }) => {
+                stack.push(global(universe, frame.receiver(), code.symbol(index))?)
+            }
+            Instruction::Bytecode(Bytecode::Pop) => {
+                stack.pop();
+            }
+            Instruction::Bytecode(Bytecode::PopLocal { index, context }) => {
+                let value = stack.pop().expect("value on stack");
+                frame.context(context).set_local(index, value);
+            }
+            Instruction::Bytecode(Bytecode::PopArgument { index, context }) => {
+                let value = stack.pop().expect("value on stack");
+                frame.context(context).set_argument(index, value);
+            }
+            Instruction::Bytecode(Bytecode::PopField { index }) => {
+                let value = stack.pop().expect("value on stack");
+                set_field(&frame.receiver(), index, value)?;
+            }
+            Instruction::Bytecode(Bytecode::Send { index }) => {
+                let selector = code.symbol(index);
+                if universe.quickening_enabled() {
+                    code.rewrite(current, quicken_send(universe, &stack, selector, index));
+                }
+
+                send_cached(universe, &code, current, &mut stack, selector)?;
+            }
+            Instruction::Bytecode(Bytecode::SuperSend { index }) => {
+                super_send(universe, frame, &mut stack, code.symbol(index))?
+            }
+            Instruction::Bytecode(Bytecode::ReturnLocal) => {
+                return Ok(stack.pop().expect("value on stack"))
+            }
+            Instruction::Bytecode(Bytecode::ReturnNonLocal) => {
+                let value = stack.pop().expect("value on stack");
+                let home = frame.home().expect("home of a block frame");
+                if !home.is_active() {
+                    return escaped_block(universe, frame);
+                }
+
+                return Err(InterpreterError::NonLocalReturn {
+                    home: home.clone(),
+                    value,
+                });
+            }
+            Instruction::Bytecode(Bytecode::PushSelf) => stack.push(frame.receiver()),
+            Instruction::Bytecode(Bytecode::PushNil) => stack.push(Value::Nil),
+            Instruction::Bytecode(Bytecode::PushZero) => stack.push(Value::Integer(0)),
+            Instruction::Bytecode(Bytecode::PushOne) => stack.push(Value::Integer(1)),
+            Instruction::Bytecode(Bytecode::ReturnSelf) => return Ok(frame.receiver()),
+            Instruction::SendInteger { index, op } => {
+                let operands = &stack[stack.len() - 2..];
+                let result = match operands {
+                    [Value::Integer(a), Value::Integer(b)] => op.apply(*a, *b),
+                    _ => {
+                        // the call site turned out not to be integer only
+                        code.rewrite(current, Instruction::SendCached { index });
+                        None
+                    }
+                };
+
+                match result {
+                    Some(value) => {
+                        stack.truncate(stack.len() - 2);
+                        stack.push(value);
+                    }
+                    None => send_cached(universe, &code, current, &mut stack, code.symbol(index))?,
+                }
+            }
+            Instruction::SendCached { index } => {
+                send_cached(universe, &code, current, &mut stack, code.symbol(index))?
+            }
+            Instruction::ReturnField { index } => return field(&frame.receiver(), index),
+            Instruction::ReturnArgument { index, context } => {
+                return Ok(frame.context(context).argument(index))
+            }
+            Instruction::StoreLocal { index, context } => {
+                let value = stack.last().expect("value on stack").clone();
+                frame.context(context).set_local(index, value);
+            }
+            Instruction::StoreField { index } => {
+                let value = stack.last().expect("value on stack").clone();
+                set_field(&frame.receiver(), index, value)?;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::{compile_source, CompileOptions};
+    use crate::vmobjects::SClass;
+
+    fn define(universe: &mut Universe, source: &str) -> Rc<SClass> {
+        let class = compile_source(source.as_bytes(), "test", &CompileOptions::default()).unwrap();
+        universe.define_class(class).unwrap()
+    }
+
+    fn run(universe: &mut Universe, source: &str) -> Result<Value> {
+        let class = define(universe, source);
+        let instance = universe.send(Value::Class(class), "new", vec![])?;
+        universe.send(instance, "run", vec![])
+    }
+
+    fn code(universe: &mut Universe, class: &SClass, selector: &str) -> Rc<Code> {
+        let selector = universe.load_symbol(selector);
+        match class.lookup(&selector).unwrap().body() {
+            MethodBody::Bytecode(code) => code.clone(),
+            _ => panic!("bytecode method expected"),
+        }
+    }
+
+    #[test]
+    fn test_arity() {
+        assert_eq!(0, arity("run"));
+        assert_eq!(1, arity("+"));
+        assert_eq!(1, arity("at:"));
+        assert_eq!(2, arity("at:put:"));
+    }
+
+    #[test]
+    fn test_invoke_checks_argument_count() {
+        let mut universe = Universe::new();
+        let class = define(&mut universe, "Test = ( twice: n = ( ^ n * 2 ) )");
+        let selector = universe.load_symbol("twice:");
+        let method = class.lookup(&selector).unwrap();
+
+        for arguments in [vec![Value::Nil], vec![Value::Nil; 3]] {
+            let count = arguments.len() - 1;
+            match invoke(&mut universe, &method, arguments) {
+                Err(InterpreterError::RuntimeError(e)) => {
+                    assert_eq!(format!("Test>>twice: takes 1 arguments, got {}", count), e)
+                }
+                r => panic!("unexpected result {:?}", r),
+            }
+        }
+    }
+
+    #[test]
+    fn test_run_arithmetic() {
+        let mut universe = Universe::new();
+        let result = run(&mut universe, "Test = ( run = ( ^ 3 + 4 * 2 - 1 ) )").unwrap();
+        assert_eq!(Value::Integer(13), result);
+    }
+
+    #[test]
+    fn test_run_large_integers() {
+        let mut universe = Universe::new();
+        let result = run(
+            &mut universe,
+            "Test = (
+                run = ( ^ (9223372036854775807 + 1) * 2 - 18446744073709551615 )
+            )",
+        )
+        .unwrap();
+        assert_eq!(Value::Integer(1), result);
+
+        let result = run(
+            &mut universe,
+            "Other = ( run = ( ^ 99999999999999999999 > 9223372036854775807 ) )",
+        )
+        .unwrap();
+        assert_eq!(Value::Boolean(true), result);
+    }
+
+    #[test]
+    fn test_run_locals_arguments_and_fields() {
+        let mut universe = Universe::new();
+        let result = run(
+            &mut universe,
+            "Test = (
+                | total |
+                add: a to: b = ( | sum | sum := a + b. ^ sum )
+                run = ( total := self add: 2 to: 3. total := total * 10. ^ total )
+            )",
+        )
+        .unwrap();
+        assert_eq!(Value::Integer(50), result);
+    }
+
+    #[test]
+    fn test_run_super_send() {
+        let mut universe = Universe::new();
+        define(&mut universe, "Base = ( value = ( ^ 1 ) )");
+        let result = run(
+            &mut universe,
+            "Test = Base ( value = ( ^ super value + 10 ) run = ( ^ self value ) )",
+        )
+        .unwrap();
+        assert_eq!(Value::Integer(11), result);
+    }
+
+    #[test]
+    fn test_run_class_side_methods_and_fields() {
+        let mut universe = Universe::new();
+        let class = define(
+            &mut universe,
+            "Counter = ( ---- | count | increment = ( count := count + 1. ^ count ) reset = ( count := 0 ) )",
+        );
+        let class = Value::Class(class);
+        universe.send(class.clone(), "reset", vec![]).unwrap();
+        universe.send(class.clone(), "increment", vec![]).unwrap();
+        assert_eq!(
+            Value::Integer(2),
+            universe.send(class, "increment", vec![]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_run_metaclass_hierarchy() {
+        let mut universe = Universe::new();
+        let class = define(
+            &mut universe,
+            "Test = (
+                ----
+                run = ( ^ self class )
+                metaclassOfMetaclass = ( ^ Metaclass class class == Metaclass )
+            )",
+        );
+        let test = Value::Class(class.clone());
+        let metaclass = universe.send(test.clone(), "run", vec![]).unwrap();
+        assert_eq!(Value::Class(class.metaclass().unwrap()), metaclass);
+        assert_eq!(
+            Value::Boolean(true),
+            universe
+                .send(test.clone(), "metaclassOfMetaclass", vec![])
+                .unwrap()
+        );
+
+        let core = universe.core_classes();
+        let (object, class_class, metaclass_class) = (
+            core.object.clone(),
+            core.class.clone(),
+            core.metaclass.clone(),
+        );
+        assert_eq!(
+            Value::Class(metaclass_class),
+            universe.send(metaclass.clone(), "class", vec![]).unwrap()
+        );
+
+        let mut superclass = universe.send(metaclass, "superclass", vec![]).unwrap();
+        assert_eq!(Value::Class(object.metaclass().unwrap()), superclass);
+        superclass = universe.send(superclass, "superclass", vec![]).unwrap();
+        assert_eq!(Value::Class(class_class), superclass);
+    }
+
+    #[test]
+    fn test_run_does_not_understand() {
+        let mut universe = Universe::new();
+        match run(&mut universe, "Test = ( run = ( ^ 3 foo ) )") {
+            Err(InterpreterError::RuntimeError(e)) => {
+                assert_eq!("Integer does not understand #foo", e)
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_run_does_not_understand_handler() {
+        let mut universe = Universe::new();
+        let result = run(
+            &mut universe,
+            "Test = (
+                doesNotUnderstand: selector arguments: args = ( ^ selector )
+                run = ( ^ self foo: 1 bar: 2 )
+            )",
+        )
+        .unwrap();
+        assert_eq!(Value::Symbol(universe.load_symbol("foo:bar:")), result);
+
+        match run(
+            &mut universe,
+            "Other = (
+                doesNotUnderstand: selector arguments: args = ( ^ args )
+                run = ( ^ self foo: 1 bar: 2 )
+            )",
+        )
+        .unwrap()
+        {
+            Value::Array(args) => {
+                assert_eq!(vec![Value::Integer(1), Value::Integer(2)], args.to_vec())
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_run_super_send_does_not_understand() {
+        let mut universe = Universe::new();
+        let result = run(
+            &mut universe,
+            "Test = (
+                doesNotUnderstand: selector arguments: args = ( ^ 7 )
+                run = ( ^ super missing )
+            )",
+        )
+        .unwrap();
+        assert_eq!(Value::Integer(7), result);
+    }
+
+    #[test]
+    fn test_run_unknown_global() {
+        let mut universe = Universe::new();
+        let result = run(
+            &mut universe,
+            "Test = (
+                unknownGlobal: name = ( ^ name )
+                run = ( ^ Missing )
+            )",
+        )
+        .unwrap();
+        assert_eq!(Value::Symbol(universe.load_symbol("Missing")), result);
+
+        match run(&mut universe, "Other = ( run = ( ^ Missing ) )") {
+            Err(InterpreterError::RuntimeError(e)) => assert_eq!("Unknown global Missing", e),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_error_stack_trace() {
+        let mut universe = Universe::new();
+        let result = run(
+            &mut universe,
+            "Test = (
+                fail: x = (
+                    ^ [ x frob ] value
+                )
+                run = ( ^ self fail: 3 )
+            )",
+        );
+        assert!(result.is_err());
+        assert!(universe.frames().is_empty());
+
+        let trace = universe.error_stack_trace().unwrap();
+        let lines = trace
+            .iter()
+            .map(|frame| frame.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                "Test>>fail: (block depth 1) at 3:27",
+                "Test>>fail: at 3:34",
+                "Test>>run at 5:32",
+            ],
+            lines
+        );
+
+        universe
+            .send(Value::Integer(1), "+", vec![Value::Integer(2)])
+            .unwrap();
+        assert!(universe.error_stack_trace().is_none());
+    }
+
+    #[test]
+    fn test_run_cascade() {
+        let mut universe = Universe::new();
+        let options = CompileOptions {
+            cascades: true,
+            ..CompileOptions::default()
+        };
+        let source = "Test = (
+            | count |
+            add: x = ( count := count + x )
+            run = ( count := 0. ^ (self add: 1; add: 2; add: 3; yourself) count )
+            chained = ( count := 0. ^ self add: 1; yourself add: 10; count )
+            count = ( ^ count )
+            yourself = ( ^ self )
+        )";
+        let class = compile_source(source.as_bytes(), "test", &options).unwrap();
+        let class = universe.define_class(class).unwrap();
+        let instance = universe.send(Value::Class(class), "new", vec![]).unwrap();
+        assert_eq!(
+            Value::Integer(6),
+            universe.send(instance.clone(), "run", vec![]).unwrap()
+        );
+        assert_eq!(
+            Value::Integer(11),
+            universe.send(instance, "chained", vec![]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_run_blocks() {
+        let mut universe = Universe::new();
+        let result = run(
+            &mut universe,
+            "Test = ( run = ( | a | a := 1. ^ [ :b | a := a + b. [ a * 2 ] value ] value: 4 ) )",
+        )
+        .unwrap();
+        assert_eq!(Value::Integer(10), result);
+    }
+
+    #[test]
+    fn test_non_local_return_unwinds_frames() {
+        let mut universe = Universe::new();
+        let result = run(
+            &mut universe,
+            "Test = (
+                each: block = ( block value: 3. ^ 99 )
+                find = ( self each: [ :x | [ ^ x ] value ]. ^ 0 )
+                run = ( ^ self find + 1 )
+            )",
+        )
+        .unwrap();
+        assert_eq!(Value::Integer(4), result);
+        assert!(universe.frames().is_empty());
+    }
+
+    #[test]
+    fn test_non_local_return_from_escaped_block() {
+        let mut universe = Universe::new();
+        let result = run(
+            &mut universe,
+            "Test = (
+                | block |
+                remember = ( block := [ ^ 42 ] )
+                escapedBlock: aBlock = ( ^ aBlock == block )
+                run = ( self remember. ^ block value )
+            )",
+        )
+        .unwrap();
+        assert_eq!(Value::Boolean(true), result);
+
+        match run(
+            &mut universe,
+            "Other = ( | block | remember = ( block := [ ^ 1 ] ) run = ( self remember. ^ block value ) )",
+        ) {
+            Err(InterpreterError::RuntimeError(e)) => assert_eq!(
+                "Block in Other>>remember has escaped and cannot be executed",
+                e
+            ),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_non_local_return_through_ensure() {
+        let mut universe = Universe::new();
+        let result = run(
+            &mut universe,
+            "Test = (
+                | count |
+                inner = ( count := 1. [ ^ 10 ] ensure: [ count := count + 1 ]. ^ 20 )
+                run = ( ^ self inner + count )
+            )",
+        )
+        .unwrap();
+        assert_eq!(Value::Integer(12), result);
+    }
+
+    #[test]
+    fn test_quickening_rewrites_instructions() {
+        let mut universe = Universe::new();
+        let class = define(
+            &mut universe,
+            "Test = (
+                | value |
+                value = ( ^ value )
+                run = ( value := 3. ^ self value + 4 )
+            )",
+        );
+        let instance = universe
+            .send(Value::Class(class.clone()), "new", vec![])
+            .unwrap();
+        let result = universe.send(instance, "run", vec![]).unwrap();
+        assert_eq!(Value::Integer(7), result);
+
+        let getter = code(&mut universe, &class, "value");
+        assert_eq!(
+            Some(Instruction::ReturnField { index: 0 }),
+            getter.instruction(0)
+        );
+
+        let run = code(&mut universe, &class, "run");
+        let instructions = (0..run.bytecode_count())
+            .filter_map(|i| run.instruction(i))
+            .collect::<Vec<_>>();
+        assert!(instructions.contains(&Instruction::SendCached { index: 1 }));
+        assert!(instructions.contains(&Instruction::SendInteger {
+            index: 3,
+            op: IntegerOp::Add
+        }));
+    }
+
+    #[test]
+    fn test_quickening_falls_back_when_guard_fails() {
+        let mut universe = Universe::new();
+        let class = define(
+            &mut universe,
+            "Test = ( ---- compare: a with: b = ( ^ a = b ) )",
+        );
+        let class = Value::Class(class);
+        let compare = |universe: &mut Universe, a: Value, b: Value| {
+            universe
+                .send(class.clone(), "compare:with:", vec![a, b])
+                .unwrap()
+        };
+
+        assert_eq!(
+            Value::Boolean(true),
+            compare(&mut universe, Value::Integer(1), Value::Integer(1))
+        );
+        assert_eq!(
+            Value::Boolean(false),
+            compare(&mut universe, Value::Integer(1), Value::Nil)
+        );
+        assert_eq!(
+            Value::Boolean(false),
+            compare(&mut universe, Value::Integer(1), Value::Integer(2))
+        );
+    }
+
+    #[test]
+    fn test_quickening_disabled() {
+        let mut universe = Universe::new();
+        universe.set_quickening_enabled(false);
+        let class = define(&mut universe, "Test = ( | value | run = ( ^ value ) )");
+        let instance = universe
+            .send(Value::Class(class.clone()), "new", vec![])
+            .unwrap();
+        assert_eq!(Value::Nil, universe.send(instance, "run", vec![]).unwrap());
+
+        let run = code(&mut universe, &class, "run");
+        assert_eq!(
+            Some(Instruction::Bytecode(Bytecode::PushField { index: 0 })),
+            run.instruction(0)
+        );
+    }
+
+    #[test]
+    fn test_redefinition_resets_quickening() {
+        let mut universe = Universe::new();
+        let class = define(&mut universe, "Test = ( | value | run = ( ^ value ) )");
+        let instance = universe
+            .send(Value::Class(class.clone()), "new", vec![])
+            .unwrap();
+        universe.send(instance, "run", vec![]).unwrap();
+
+        let run = code(&mut universe, &class, "run");
+        assert_eq!(
+            Some(Instruction::ReturnField { index: 0 }),
+            run.instruction(0)
+        );
+
+        define(&mut universe, "Test = ( | value | other = ( ^ 1 ) )");
+        assert_eq!(
+            Some(Instruction::Bytecode(Bytecode::PushField { index: 0 })),
+            run.instruction(0)
+        );
+    }
+
+    fn call_site<'a>(stats: &'a [CallSiteStats], selector: &str) -> &'a CallSiteStats {
+        stats.iter().find(|site| site.selector == selector).unwrap()
+    }
+
+    #[test]
+    fn test_inline_cache_goes_polymorphic() {
+        let mut universe = Universe::new();
+        define(&mut universe, "A = ( name = ( ^ 1 ) )");
+        define(&mut universe, "B = ( name = ( ^ 2 ) )");
+        let class = define(&mut universe, "Test = ( nameOf: x = ( ^ x name ) )");
+        let test = universe.send(Value::Class(class), "new", vec![]).unwrap();
+
+        for name in &["A", "B", "A", "A"] {
+            let class = universe.global(name).unwrap();
+            let instance = universe.send(class, "new", vec![]).unwrap();
+            universe
+                .send(test.clone(), "nameOf:", vec![instance])
+                .unwrap();
+        }
+
+        let stats = universe.inline_cache_stats();
+        let site = call_site(&stats, "name");
+        assert_eq!("Test>>nameOf:", site.method);
+        assert_eq!(CacheState::Polymorphic(2), site.state);
+        assert_eq!((2, 2), (site.hits, site.misses));
+    }
+
+    #[test]
+    fn test_inline_cache_goes_megamorphic() {
+        let mut universe = Universe::new();
+        let class = define(&mut universe, "Test = ( classOf: x = ( ^ x class ) )");
+        let test = universe.send(Value::Class(class), "new", vec![]).unwrap();
+
+        let receivers = vec![
+            Value::Nil,
+            Value::Boolean(true),
+            Value::Boolean(false),
+            Value::Integer(1),
+            Value::Class(universe.core_classes().object.clone()),
+        ];
+        assert!(receivers.len() > POLYMORPHIC_LIMIT);
+        for receiver in receivers {
+            universe
+                .send(test.clone(), "classOf:", vec![receiver])
+                .unwrap();
+        }
+
+        let stats = universe.inline_cache_stats();
+        assert_eq!(CacheState::Megamorphic, call_site(&stats, "class").state);
+    }
+
+    #[test]
+    fn test_inline_cache_invalidated_by_redefinition() {
+        let mut universe = Universe::new();
+        define(&mut universe, "Answer = ( value = ( ^ 1 ) )");
+        let class = define(&mut universe, "Test = ( run = ( ^ Answer new value ) )");
+        let test = universe.send(Value::Class(class), "new", vec![]).unwrap();
+        assert_eq!(
+            Value::Integer(1),
+            universe.send(test.clone(), "run", vec![]).unwrap()
+        );
+
+        define(&mut universe, "Answer = ( value = ( ^ 2 ) )");
+        assert_eq!(
+            Value::Integer(2),
+            universe.send(test, "run", vec![]).unwrap()
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use crate::interpreter::Bytecode;
+use crate::vmobjects::Value;
+
+/// What the interpreter dispatches on. Every slot starts out as the compiled
+/// bytecode and may be rewritten in place the first time it runs, either
+/// into a specialised form of itself or into a superinstruction covering it
+/// and its successor.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Instruction {
+    Bytecode(Bytecode),
+    /// A `Send` of an arithmetic or comparison selector whose receiver and
+    /// argument were integers, evaluated inline while they stay integers.
+    SendInteger {
+        index: u8,
+        op: IntegerOp,
+    },
+    /// A `Send` that is known not to be worth specialising. It still goes
+    /// through the call site's inline cache, like an unquickened `Send`.
+    SendCached {
+        index: u8,
+    },
+    /// `PushField` followed by `ReturnLocal`, the body of every getter.
+    ReturnField {
+        index: u8,
+    },
+    /// `PushArgument` followed by `ReturnLocal`.
+    ReturnArgument {
+        index: u8,
+        context: u8,
+    },
+    /// `Dup` followed by `PopLocal`, an assignment whose value is used.
+    StoreLocal {
+        index: u8,
+        context: u8,
+    },
+    /// `Dup` followed by `PopField`.
+    StoreField {
+        index: u8,
+    },
+}
+
+impl Instruction {
+    /// The number of bytecode slots the instruction covers. A fused
+    /// instruction leaves its second bytecode in place, so bytecode indices
+    /// and the line table stay valid.
+    pub fn width(self) -> usize {
+        match self {
+            Instruction::ReturnField { .. }
+            | Instruction::ReturnArgument { .. }
+            | Instruction::StoreLocal { .. }
+            | Instruction::StoreField { .. } => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// The superinstruction for a pair of bytecodes, if there is one. These are
+/// the pairs that dominate the dynamic instruction counts of the benchmark
+/// suite once the peephole optimizer has run.
+pub fn fuse(first: Bytecode, second: Bytecode) -> Option<Instruction> {
+    match (first, second) {
+        (Bytecode::PushField { index }, Bytecode::ReturnLocal) => {
+            Some(Instruction::ReturnField { index })
+        }
+        (Bytecode::PushArgument { index, context }, Bytecode::ReturnLocal) => {
+            Some(Instruction::ReturnArgument { index, context })
+        }
+        (Bytecode::Dup, Bytecode::PopLocal { index, context }) => {
+            Some(Instruction::StoreLocal { index, context })
+        }
+        (Bytecode::Dup, Bytecode::PopField { index }) => Some(Instruction::StoreField { index }),
+        _ => None,
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum IntegerOp {
+    Add,
+    Subtract,
+    Multiply,
+    LessThan,
+    GreaterThan,
+    LessThanOrEqual,
+    GreaterThanOrEqual,
+    Equal,
+}
+
+impl IntegerOp {
+    pub fn from_selector(selector: &str) -> Option<IntegerOp> {
+        let op = match selector {
+            "+" => IntegerOp::Add,
+            "-" => IntegerOp::Subtract,
+            "*" => IntegerOp::Multiply,
+            "<" => IntegerOp::LessThan,
+            ">" => IntegerOp::GreaterThan,
+            "<=" => IntegerOp::LessThanOrEqual,
+            ">=" => IntegerOp::GreaterThanOrEqual,
+            "=" => IntegerOp::Equal,
+            _ => return None,
+        };
+
+        Some(op)
+    }
+
+    /// Evaluates the operation, or `None` when the result does not fit and
+    /// the full primitive has to handle it.
+    pub fn apply(self, a: i64, b: i64) -> Option<Value> {
+        let value = match self {
+            IntegerOp::Add => Value::Integer(a.checked_add(b)?),
+            IntegerOp::Subtract => Value::Integer(a.checked_sub(b)?),
+            IntegerOp::Multiply => Value::Integer(a.checked_mul(b)?),
+            IntegerOp::LessThan => Value::Boolean(a < b),
+            IntegerOp::GreaterThan => Value::Boolean(a > b),
+            IntegerOp::LessThanOrEqual => Value::Boolean(a <= b),
+            IntegerOp::GreaterThanOrEqual => Value::Boolean(a >= b),
+            IntegerOp::Equal => Value::Boolean(a == b),
+        };
+
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuse_pairs() {
+        assert_eq!(
+            Some(Instruction::ReturnField { index: 2 }),
+            fuse(Bytecode::PushField { index: 2 }, Bytecode::ReturnLocal)
+        );
+        assert_eq!(
+            Some(Instruction::StoreLocal {
+                index: 1,
+                context: 0
+            }),
+            fuse(
+                Bytecode::Dup,
+                Bytecode::PopLocal {
+                    index: 1,
+                    context: 0
+                }
+            )
+        );
+        assert_eq!(None, fuse(Bytecode::Dup, Bytecode::Pop));
+    }
+
+    #[test]
+    fn test_integer_op_overflow() {
+        assert_eq!(Some(Value::Integer(5)), IntegerOp::Add.apply(2, 3));
+        assert_eq!(None, IntegerOp::Multiply.apply(i64::MAX, 2));
+        assert_eq!(
+            Some(Value::Boolean(true)),
+            IntegerOp::LessThanOrEqual.apply(3, 3)
+        );
+    }
+}
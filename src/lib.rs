@@ -1,6 +1,11 @@
 #![allow(dead_code)]
 
+extern crate num_bigint;
+extern crate num_integer;
+extern crate num_traits;
+
 pub mod compiler;
 pub mod interpreter;
+pub mod primitives;
 pub mod vm;
 pub mod vmobjects;
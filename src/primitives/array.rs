@@ -0,0 +1,226 @@
+use crate::interpreter::{self, InterpreterError, Result};
+use crate::primitives::PrimitiveTable;
+use crate::vm::Universe;
+use crate::vmobjects::{SArray, Value};
+use std::convert::TryFrom;
+use std::rc::Rc;
+
+pub const PRIMITIVES: PrimitiveTable = &[
+    ("at:", at),
+    ("at:put:", at_put),
+    ("copy", copy),
+    ("do:", for_each),
+    ("indexOutOfBounds:", index_out_of_bounds),
+    ("length", length),
+];
+
+pub const CLASS_PRIMITIVES: PrimitiveTable = &[("new:", new)];
+
+fn receiver(arguments: &[Value], selector: &str) -> Result<Rc<SArray>> {
+    match &arguments[0] {
+        Value::Array(array) => Ok(array.clone()),
+        receiver => Err(InterpreterError::RuntimeError(format!(
+            "Array>>{} sent to {:?}",
+            selector, receiver
+        ))),
+    }
+}
+
+/// The 0-based index for the 1-based SOM index `value`, if it lies within
+/// `array`.
+fn index(array: &SArray, value: &Value) -> Option<usize> {
+    match value {
+        Value::Integer(index) if *index >= 1 && *index as u64 <= array.len() as u64 => {
+            Some(*index as usize - 1)
+        }
+        _ => None,
+    }
+}
+
+/// Reports a bad index by sending `indexOutOfBounds:` to the array, so SOM
+/// code can handle it.
+fn out_of_bounds(universe: &mut Universe, array: Value, index: Value) -> Result<Value> {
+    let selector = universe.load_symbol("indexOutOfBounds:");
+    interpreter::send(universe, array, &selector, vec![index])
+}
+
+fn at(universe: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let array = receiver(&arguments, "at:")?;
+    match index(&array, &arguments[1]) {
+        Some(index) => Ok(array.get(index).unwrap()),
+        None => out_of_bounds(universe, arguments[0].clone(), arguments[1].clone()),
+    }
+}
+
+fn at_put(universe: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let array = receiver(&arguments, "at:put:")?;
+    match index(&array, &arguments[1]) {
+        Some(index) => {
+            array.set(index, arguments[2].clone());
+            Ok(arguments[2].clone())
+        }
+        None => out_of_bounds(universe, arguments[0].clone(), arguments[1].clone()),
+    }
+}
+
+fn copy(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let array = receiver(&arguments, "copy")?;
+    Ok(Value::Array(Rc::new(SArray::new(array.to_vec()))))
+}
+
+/// Evaluates the block with each element in order.
+fn for_each(universe: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let array = receiver(&arguments, "do:")?;
+    let block = arguments[1].clone();
+    let value = universe.load_symbol("value:");
+    for i in 0..array.len() {
+        let element = array.get(i).unwrap_or(Value::Nil);
+        interpreter::send(universe, block.clone(), &value, vec![element])?;
+    }
+
+    Ok(arguments[0].clone())
+}
+
+/// The default answer to a bad index: fail naming the index and the length.
+fn index_out_of_bounds(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let array = receiver(&arguments, "indexOutOfBounds:")?;
+    Err(InterpreterError::RuntimeError(format!(
+        "Index {:?} out of bounds for Array of length {}",
+        arguments[1],
+        array.len()
+    )))
+}
+
+fn length(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let array = receiver(&arguments, "length")?;
+    Ok(Value::Integer(array.len() as i64))
+}
+
+/// Allocates a nil-filled array. Lengths the sandbox forbids or memory
+/// cannot hold fail instead of aborting the process.
+fn new(universe: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let length = match arguments[1] {
+        Value::Integer(length) if length >= 0 => length,
+        ref length => {
+            return Err(InterpreterError::RuntimeError(format!(
+                "Array class>>new: expects a non-negative length, got {:?}",
+                length
+            )))
+        }
+    };
+
+    let too_large =
+        || InterpreterError::RuntimeError(format!("Array class>>new: {} is too large", length));
+    let length = usize::try_from(length).map_err(|_| too_large())?;
+    if !universe.sandbox().allows_array_length(length) {
+        return Err(too_large());
+    }
+
+    let mut values = Vec::new();
+    values.try_reserve_exact(length).map_err(|_| too_large())?;
+    values.resize(length, Value::Nil);
+    Ok(Value::Array(Rc::new(SArray::new(values))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::{compile_source, CompileOptions};
+    use crate::vm::Sandbox;
+
+    fn run(universe: &mut Universe, source: &str) -> Result<Value> {
+        let class = compile_source(source.as_bytes(), "test", &CompileOptions::default()).unwrap();
+        let class = universe.define_class(class).unwrap();
+        let instance = universe.send(Value::Class(class), "new", vec![])?;
+        universe.send(instance, "run", vec![])
+    }
+
+    #[test]
+    fn test_literal_arrays() {
+        let mut universe = Universe::new();
+        let result = run(
+            &mut universe,
+            "Test = ( run = ( ^ (#(1 #foo #(3 4)) at: 3) at: 2 ) )",
+        );
+        assert_eq!(Value::Integer(4), result.unwrap());
+
+        let result = run(
+            &mut universe,
+            "Test = ( run = ( | sum | sum := 0. #(1 2 3) do: [ :x | sum := sum + x ]. ^ sum ) )",
+        );
+        assert_eq!(Value::Integer(6), result.unwrap());
+    }
+
+    #[test]
+    fn test_literals_are_created_once() {
+        let mut universe = Universe::new();
+        let source = "Test = ( literal = ( ^ #(1 2) ) string = ( ^ 'abc' )
+            run = ( ^ self literal == self literal ) same = ( ^ self string == self string ) )";
+        let class = compile_source(source.as_bytes(), "test", &CompileOptions::default()).unwrap();
+        let class = universe.define_class(class).unwrap();
+        let instance = universe.send(Value::Class(class), "new", vec![]).unwrap();
+        for _ in 0..2 {
+            let result = universe.send(instance.clone(), "run", vec![]).unwrap();
+            assert_eq!(Value::Boolean(true), result);
+            let result = universe.send(instance.clone(), "same", vec![]).unwrap();
+            assert_eq!(Value::Boolean(true), result);
+        }
+    }
+
+    #[test]
+    fn test_new_at_put_and_copy() {
+        let mut universe = Universe::new();
+        let result = run(
+            &mut universe,
+            "Test = ( run = ( | a b sum |
+                a := Array new: 3.
+                a at: 1 put: 10. a at: 2 put: 20. a at: 3 put: 30.
+                b := a copy.
+                a at: 2 put: 0.
+                sum := 0.
+                b do: [ :x | sum := sum + x ].
+                ^ sum + (a at: 2) + a length ) )",
+        );
+        assert_eq!(Value::Integer(63), result.unwrap());
+    }
+
+    #[test]
+    fn test_new_rejects_huge_lengths() {
+        let mut universe = Universe::new();
+        let source = "Test = ( run = ( ^ Array new: 9223372036854775807 ) )";
+        match run(&mut universe, source) {
+            Err(InterpreterError::RuntimeError(e)) => {
+                assert_eq!("Array class>>new: 9223372036854775807 is too large", e)
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        universe.set_sandbox(Sandbox::restricted(vec![], 0, 10));
+        let source = "Test = ( run = ( ^ (Array new: 10) length ) )";
+        assert_eq!(Value::Integer(10), run(&mut universe, source).unwrap());
+        let source = "Test = ( run = ( ^ Array new: 11 ) )";
+        match run(&mut universe, source) {
+            Err(InterpreterError::RuntimeError(e)) => {
+                assert_eq!("Array class>>new: 11 is too large", e)
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_index_out_of_bounds() {
+        let mut universe = Universe::new();
+        let source = "Test = ( run = ( ^ #(1 2) at: 3 ) )";
+        match run(&mut universe, source) {
+            Err(InterpreterError::RuntimeError(e)) => {
+                assert_eq!("Index Integer(3) out of bounds for Array of length 2", e)
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        let handler = "Array = ( indexOutOfBounds: index = ( ^ index * 2 ) )";
+        let handler = compile_source(handler.as_bytes(), "test", &CompileOptions::default());
+        universe.define_class(handler.unwrap()).unwrap();
+        assert_eq!(Value::Integer(6), run(&mut universe, source).unwrap());
+    }
+}
@@ -0,0 +1,146 @@
+use crate::interpreter::{self, InterpreterError, Result};
+use crate::primitives::PrimitiveTable;
+use crate::vm::Universe;
+use crate::vmobjects::{SBlock, Value};
+use std::rc::Rc;
+
+pub const PRIMITIVES: PrimitiveTable = &[
+    ("ensure:", ensure),
+    ("numArgs", num_args),
+    ("value", value),
+    ("value:", value),
+    ("value:with:", value),
+    ("whileTrue:", while_true),
+];
+
+pub const BLOCK1_PRIMITIVES: PrimitiveTable = &[("value", value)];
+
+pub const BLOCK2_PRIMITIVES: PrimitiveTable = &[("value:", value)];
+
+pub const BLOCK3_PRIMITIVES: PrimitiveTable = &[("value:with:", value)];
+
+fn block(value: &Value, selector: &str) -> Result<Rc<SBlock>> {
+    match value {
+        Value::Block(block) => Ok(block.clone()),
+        value => Err(InterpreterError::RuntimeError(format!(
+            "Block>>{} sent to {:?}",
+            selector, value
+        ))),
+    }
+}
+
+fn value(universe: &mut Universe, mut arguments: Vec<Value>) -> Result<Value> {
+    let receiver = block(&arguments.remove(0), "value")?;
+    if arguments.len() != receiver.num_arguments() {
+        return Err(InterpreterError::RuntimeError(format!(
+            "{:?} takes {} arguments, got {}",
+            receiver,
+            receiver.num_arguments(),
+            arguments.len()
+        )));
+    }
+
+    interpreter::evaluate_block(universe, &receiver, arguments)
+}
+
+fn num_args(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let receiver = block(&arguments[0], "numArgs")?;
+    Ok(Value::Integer(receiver.num_arguments() as i64))
+}
+
+/// Evaluates the argument block as long as the receiver evaluates to true.
+fn while_true(universe: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let condition = block(&arguments[0], "whileTrue:")?;
+    let body = block(&arguments[1], "whileTrue:")?;
+
+    loop {
+        match value(universe, vec![Value::Block(condition.clone())])? {
+            Value::Boolean(true) => {}
+            Value::Boolean(false) => return Ok(Value::Nil),
+            result => {
+                return Err(InterpreterError::RuntimeError(format!(
+                    "Block>>whileTrue: expects the receiver to answer a boolean, got {:?}",
+                    result
+                )))
+            }
+        }
+
+        value(universe, vec![Value::Block(body.clone())])?;
+    }
+}
+
+/// Evaluates the receiver, then the argument block, even when the receiver
+/// fails or returns non-locally.
+fn ensure(universe: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let receiver = block(&arguments[0], "ensure:")?;
+    let ensured = block(&arguments[1], "ensure:")?;
+
+    let result = interpreter::evaluate_block(universe, &receiver, vec![]);
+    interpreter::evaluate_block(universe, &ensured, vec![])?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::{compile_source, CompileOptions};
+
+    fn run(universe: &mut Universe, body: &str) -> Result<Value> {
+        let source = format!("Test = ( run = ( {} ) )", body);
+        let class = compile_source(source.as_bytes(), "test", &CompileOptions::default()).unwrap();
+        let class = universe.define_class(class).unwrap();
+        let instance = universe.send(Value::Class(class), "new", vec![])?;
+        universe.send(instance, "run", vec![])
+    }
+
+    #[test]
+    fn test_block_classes() {
+        let mut universe = Universe::new();
+        let blocks = universe.core_classes().blocks.clone();
+        for (i, source) in ["^ [ 1 ]", "^ [ :a | a ]", "^ [ :a :b | a ]"]
+            .iter()
+            .enumerate()
+        {
+            let block = run(&mut universe, source).unwrap();
+            assert_eq!(
+                Value::Class(blocks[i].clone()),
+                universe.send(block.clone(), "class", vec![]).unwrap()
+            );
+            assert_eq!(
+                Value::Integer(i as i64),
+                universe.send(block, "numArgs", vec![]).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_while_true() {
+        let mut universe = Universe::new();
+        let result = run(
+            &mut universe,
+            "| i sum | i := 0. sum := 0.
+             [ i < 5 ] whileTrue: [ i := i + 1. sum := sum + i ].
+             ^ sum",
+        );
+        assert_eq!(Value::Integer(15), result.unwrap());
+
+        match run(&mut universe, "[ 1 ] whileTrue: [ 2 ]") {
+            Err(InterpreterError::RuntimeError(e)) => assert_eq!(
+                "Block>>whileTrue: expects the receiver to answer a boolean, got Integer(1)",
+                e
+            ),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_wrong_number_of_arguments() {
+        let mut universe = Universe::new();
+        match run(&mut universe, "^ [ :a | a ] value") {
+            Err(InterpreterError::RuntimeError(e)) => {
+                assert_eq!("Block in Test>>run takes 1 arguments, got 0", e)
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use crate::interpreter::{InterpreterError, Result};
+use crate::primitives::PrimitiveTable;
+use crate::vm::Universe;
+use crate::vmobjects::{SArray, SClass, SMethod, SObject, Value};
+use std::rc::Rc;
+
+pub const PRIMITIVES: PrimitiveTable = &[
+    ("fields", fields),
+    ("methods", methods),
+    ("name", name),
+    ("new", new),
+    ("selectors", selectors),
+    ("superclass", superclass),
+];
+
+fn receiver(arguments: &[Value], selector: &str) -> Result<Rc<SClass>> {
+    match &arguments[0] {
+        Value::Class(class) => Ok(class.clone()),
+        receiver => Err(InterpreterError::RuntimeError(format!(
+            "Class>>{} sent to {:?}",
+            selector, receiver
+        ))),
+    }
+}
+
+fn array(values: Vec<Value>) -> Value {
+    Value::Array(Rc::new(SArray::new(values)))
+}
+
+/// The methods defined in the class itself, ordered by selector.
+fn sorted_methods(class: &SClass) -> Vec<Rc<SMethod>> {
+    let mut methods = class.methods();
+    methods.sort_by(|a, b| a.signature().as_str().cmp(b.signature().as_str()));
+    methods
+}
+
+/// The names of all instance fields, inherited ones first.
+fn fields(universe: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let class = receiver(&arguments, "fields")?;
+    let fields = class
+        .instance_fields()
+        .iter()
+        .map(|field| Value::Symbol(universe.load_symbol(field)))
+        .collect();
+    Ok(array(fields))
+}
+
+fn methods(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let class = receiver(&arguments, "methods")?;
+    Ok(array(
+        sorted_methods(&class)
+            .into_iter()
+            .map(Value::Method)
+            .collect(),
+    ))
+}
+
+fn selectors(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let class = receiver(&arguments, "selectors")?;
+    Ok(array(
+        sorted_methods(&class)
+            .iter()
+            .map(|method| Value::Symbol(method.signature().clone()))
+            .collect(),
+    ))
+}
+
+fn name(universe: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let class = receiver(&arguments, "name")?;
+    Ok(Value::Symbol(universe.load_symbol(class.name())))
+}
+
+fn new(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let class = receiver(&arguments, "new")?;
+    let fields = class.num_instance_fields();
+    Ok(Value::Object(Rc::new(SObject::new(class, fields))))
+}
+
+fn superclass(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let class = receiver(&arguments, "superclass")?;
+    Ok(class.superclass().map_or(Value::Nil, Value::Class))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_allocates_fields() {
+        let mut universe = Universe::new();
+        let class = Rc::new(SClass::new("Point", None));
+        class.set_instance_fields(vec!["x".into(), "y".into()]);
+
+        match universe.send(Value::Class(class), "new", vec![]).unwrap() {
+            Value::Object(object) => {
+                assert_eq!(Some(Value::Nil), object.field(1));
+                assert_eq!(None, object.field(2));
+            }
+            v => panic!("unexpected value {:?}", v),
+        }
+    }
+
+    #[test]
+    fn test_name_and_superclass() {
+        let mut universe = Universe::new();
+        let integer = Value::Class(universe.core_classes().integer.clone());
+
+        match universe.send(integer.clone(), "name", vec![]).unwrap() {
+            Value::Symbol(symbol) => assert_eq!("Integer", symbol.as_str()),
+            v => panic!("unexpected value {:?}", v),
+        }
+
+        let object = universe.send(integer, "superclass", vec![]).unwrap();
+        let object = universe.send(object, "superclass", vec![]).unwrap();
+        assert_eq!(Value::Nil, object);
+    }
+
+    #[test]
+    fn test_fields_methods_and_selectors() {
+        let mut universe = Universe::new();
+        let class = Rc::new(SClass::new("Point", None));
+        class.set_instance_fields(vec!["x".into(), "y".into()]);
+        let class = Value::Class(class);
+        let names = |universe: &mut Universe, selector: &str| match universe
+            .send(class.clone(), selector, vec![])
+            .unwrap()
+        {
+            Value::Array(array) => array
+                .to_vec()
+                .into_iter()
+                .map(|value| match value {
+                    Value::Symbol(symbol) => symbol.as_str().to_string(),
+                    v => panic!("unexpected value {:?}", v),
+                })
+                .collect::<Vec<_>>(),
+            v => panic!("unexpected value {:?}", v),
+        };
+        assert_eq!(vec!["x", "y"], names(&mut universe, "fields"));
+        assert!(names(&mut universe, "selectors").is_empty());
+
+        let class = Value::Class(universe.core_classes().class.clone());
+        let selectors = match universe.send(class.clone(), "selectors", vec![]).unwrap() {
+            Value::Array(array) => array.len(),
+            v => panic!("unexpected value {:?}", v),
+        };
+        assert_eq!(PRIMITIVES.len(), selectors);
+        match universe.send(class, "methods", vec![]).unwrap() {
+            Value::Array(array) => match array.get(0) {
+                Some(Value::Method(method)) => assert_eq!("Class>>fields", format!("{:?}", method)),
+                v => panic!("unexpected value {:?}", v),
+            },
+            v => panic!("unexpected value {:?}", v),
+        }
+    }
+}
@@ -0,0 +1,276 @@
+use crate::interpreter::{InterpreterError, Result};
+use crate::primitives::integer;
+use crate::primitives::PrimitiveTable;
+use crate::vm::Universe;
+use crate::vmobjects::{SString, Value};
+use num_bigint::BigInt;
+use num_traits::{FromPrimitive, ToPrimitive};
+use std::rc::Rc;
+
+pub const PRIMITIVES: PrimitiveTable = &[
+    ("%", modulo),
+    ("*", multiply),
+    ("+", add),
+    ("-", subtract),
+    ("//", divide),
+    ("<", less_than),
+    ("=", equal),
+    ("asInteger", as_integer),
+    ("asString", as_string),
+    ("cos", cos),
+    ("round", round),
+    ("sin", sin),
+    ("sqrt", sqrt),
+];
+
+fn error<T>(description: String) -> Result<T> {
+    Err(InterpreterError::RuntimeError(description))
+}
+
+/// The value of a number as a double. Integers too large for a double
+/// become infinite.
+pub fn to_f64(value: &Value) -> Option<f64> {
+    match value {
+        Value::Double(value) => Some(*value),
+        Value::Integer(value) => Some(*value as f64),
+        Value::LargeInteger(value) => value.to_f64(),
+        _ => None,
+    }
+}
+
+/// Both operands of a binary primitive, the argument coerced to a double.
+fn operands(arguments: &[Value], selector: &str) -> Result<(f64, f64)> {
+    match (to_f64(&arguments[0]), to_f64(&arguments[1])) {
+        (Some(a), Some(b)) => Ok((a, b)),
+        _ => error(format!(
+            "Double>>{} expects a number argument, got {:?}",
+            selector, arguments[1]
+        )),
+    }
+}
+
+fn receiver(arguments: &[Value], selector: &str) -> Result<f64> {
+    match arguments[0] {
+        Value::Double(value) => Ok(value),
+        ref receiver => error(format!("Double>>{} sent to {:?}", selector, receiver)),
+    }
+}
+
+/// Formats like `Double.toString` in Java, which the reference SOM uses:
+/// the shortest digits that read back as the same double, in scientific
+/// notation outside `1e-3 <= |value| < 1e7`.
+pub fn format(value: f64) -> String {
+    if value.is_nan() {
+        return "NaN".into();
+    } else if value.is_infinite() {
+        return if value > 0.0 { "Infinity" } else { "-Infinity" }.into();
+    } else if value == 0.0 {
+        return if value.is_sign_negative() {
+            "-0.0"
+        } else {
+            "0.0"
+        }
+        .into();
+    }
+
+    let with_fraction = |digits: &str| {
+        if digits.contains('.') {
+            digits.to_string()
+        } else {
+            format!("{}.0", digits)
+        }
+    };
+
+    if (1e-3..1e7).contains(&value.abs()) {
+        with_fraction(&value.to_string())
+    } else {
+        let scientific = format!("{:e}", value);
+        let (mantissa, exponent) = scientific.split_at(scientific.find('e').unwrap());
+        format!("{}E{}", with_fraction(mantissa), &exponent[1..])
+    }
+}
+
+/// Converts a double with no fractional part into an integer.
+fn integer_value(value: f64, selector: &str) -> Result<Value> {
+    match BigInt::from_f64(value) {
+        Some(value) => Ok(integer::integer(value)),
+        None => error(format!(
+            "Double>>{} cannot convert {}",
+            selector,
+            format(value)
+        )),
+    }
+}
+
+pub fn add(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let (a, b) = operands(&arguments, "+")?;
+    Ok(Value::Double(a + b))
+}
+
+pub fn subtract(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let (a, b) = operands(&arguments, "-")?;
+    Ok(Value::Double(a - b))
+}
+
+pub fn multiply(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let (a, b) = operands(&arguments, "*")?;
+    Ok(Value::Double(a * b))
+}
+
+pub fn divide(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let (a, b) = operands(&arguments, "//")?;
+    Ok(Value::Double(a / b))
+}
+
+/// The remainder with the sign of the receiver, like Java's `%`.
+pub fn modulo(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let (a, b) = operands(&arguments, "%")?;
+    Ok(Value::Double(a % b))
+}
+
+pub fn less_than(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let (a, b) = operands(&arguments, "<")?;
+    Ok(Value::Boolean(a < b))
+}
+
+/// Whether both values are numbers of equal value, compared as doubles.
+pub fn equal_values(a: &Value, b: &Value) -> bool {
+    match (to_f64(a), to_f64(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Equality never fails: anything that is not a number of equal value is
+/// unequal.
+fn equal(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    Ok(Value::Boolean(equal_values(&arguments[0], &arguments[1])))
+}
+
+fn as_integer(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    integer_value(receiver(&arguments, "asInteger")?.trunc(), "asInteger")
+}
+
+fn as_string(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let value = receiver(&arguments, "asString")?;
+    Ok(Value::String(Rc::new(SString::new(format(value)))))
+}
+
+fn cos(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    Ok(Value::Double(receiver(&arguments, "cos")?.cos()))
+}
+
+/// Rounds half up like Java's `Math.round`, so `-2.5 round` is -2. This is
+/// `(x + 0.5) floor` without the rounding error of the addition.
+fn round(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let value = receiver(&arguments, "round")?;
+    let floor = value.floor();
+    let rounded = if value - floor >= 0.5 {
+        floor + 1.0
+    } else {
+        floor
+    };
+    integer_value(rounded, "round")
+}
+
+fn sin(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    Ok(Value::Double(receiver(&arguments, "sin")?.sin()))
+}
+
+fn sqrt(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    Ok(Value::Double(receiver(&arguments, "sqrt")?.sqrt()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::{compile_source, CompileOptions};
+
+    fn send(receiver: Value, selector: &str, arguments: Vec<Value>) -> Result<Value> {
+        Universe::new().send(receiver, selector, arguments)
+    }
+
+    #[test]
+    fn test_format_matches_java() {
+        let cases = [
+            (1.0, "1.0"),
+            (-2.5, "-2.5"),
+            (0.1, "0.1"),
+            (0.001, "0.001"),
+            (0.0001, "1.0E-4"),
+            (1234567.0, "1234567.0"),
+            (1e7, "1.0E7"),
+            (1.5e300, "1.5E300"),
+            (-0.0, "-0.0"),
+            (1.0 / 3.0, "0.3333333333333333"),
+            (f64::NAN, "NaN"),
+            (f64::NEG_INFINITY, "-Infinity"),
+        ];
+        for &(value, text) in cases.iter() {
+            assert_eq!(text, format(value));
+        }
+    }
+
+    #[test]
+    fn test_literal_doubles() {
+        let mut universe = Universe::new();
+        let source = "Test = ( run = ( ^ (2.5 * 2) + 0.25 ) )";
+        let class = compile_source(source.as_bytes(), "test", &CompileOptions::default()).unwrap();
+        let class = universe.define_class(class).unwrap();
+        let instance = universe.send(Value::Class(class), "new", vec![]).unwrap();
+        let result = universe.send(instance, "run", vec![]).unwrap();
+        assert_eq!(Value::Double(5.25), result);
+    }
+
+    #[test]
+    fn test_mixed_arithmetic() {
+        let sum = send(Value::Double(1.5), "+", vec![Value::Integer(2)]).unwrap();
+        assert_eq!(Value::Double(3.5), sum);
+        let sum = send(Value::Integer(2), "+", vec![Value::Double(1.5)]).unwrap();
+        assert_eq!(Value::Double(3.5), sum);
+        let quotient = send(Value::Integer(1), "//", vec![Value::Integer(4)]).unwrap();
+        assert_eq!(Value::Double(0.25), quotient);
+        let less = send(Value::Integer(1), "<", vec![Value::Double(1.5)]).unwrap();
+        assert_eq!(Value::Boolean(true), less);
+        let equal = send(Value::Integer(2), "=", vec![Value::Double(2.0)]).unwrap();
+        assert_eq!(Value::Boolean(true), equal);
+        let remainder = send(Value::Double(-7.5), "%", vec![Value::Integer(2)]).unwrap();
+        assert_eq!(Value::Double(-1.5), remainder);
+    }
+
+    #[test]
+    fn test_math() {
+        let root = send(Value::Double(2.25), "sqrt", vec![]).unwrap();
+        assert_eq!(Value::Double(1.5), root);
+        for &(value, rounded) in &[
+            (-2.5, -2),
+            (-0.5, 0),
+            (-3.5, -3),
+            (-2.6, -3),
+            (2.5, 3),
+            (0.49999999999999994, 0),
+            (4503599627370497.0, 4503599627370497),
+        ] {
+            let result = send(Value::Double(value), "round", vec![]).unwrap();
+            assert_eq!(Value::Integer(rounded), result, "{}", value);
+        }
+        let truncated = send(Value::Double(-2.7), "asInteger", vec![]).unwrap();
+        assert_eq!(Value::Integer(-2), truncated);
+        let large = send(Value::Double(1e20), "asInteger", vec![]).unwrap();
+        assert_eq!(integer::integer(BigInt::from(10).pow(20)), large);
+        assert_eq!(
+            Value::Double(0.0),
+            send(Value::Double(0.0), "sin", vec![]).unwrap()
+        );
+        assert_eq!(
+            Value::Double(1.0),
+            send(Value::Double(0.0), "cos", vec![]).unwrap()
+        );
+        assert!(send(Value::Double(f64::NAN), "round", vec![]).is_err());
+
+        match send(Value::Double(0.5), "asString", vec![]).unwrap() {
+            Value::String(string) => assert_eq!("0.5", string.as_str()),
+            v => panic!("unexpected value {:?}", v),
+        }
+    }
+}
@@ -0,0 +1,396 @@
+use crate::interpreter::{InterpreterError, Result};
+use crate::primitives::{double, PrimitiveTable};
+use crate::vm::Universe;
+use crate::vmobjects::Value;
+use num_bigint::BigInt;
+use num_integer::Integer;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::rc::Rc;
+
+pub const PRIMITIVES: PrimitiveTable = &[
+    ("%", modulo),
+    ("&", bit_and),
+    ("*", multiply),
+    ("+", add),
+    ("-", subtract),
+    ("/", divide),
+    ("//", double::divide),
+    ("<", less_than),
+    ("<<", shift_left),
+    ("<=", less_than_or_equal),
+    ("=", equal),
+    (">", greater_than),
+    (">=", greater_than_or_equal),
+    (">>", shift_right),
+    ("bitXor:", bit_xor),
+    ("rem:", remainder),
+    ("~=", not_equal),
+];
+
+fn error<T>(description: String) -> Result<T> {
+    Err(InterpreterError::RuntimeError(description))
+}
+
+/// The operands of a binary primitive. Both stay machine integers unless
+/// one of them is already large, and become doubles if the argument is one.
+enum Operands {
+    Small(i64, i64),
+    Large(BigInt, BigInt),
+    Double(f64, f64),
+}
+
+fn large(value: &Value) -> Option<BigInt> {
+    match value {
+        Value::Integer(value) => Some(BigInt::from(*value)),
+        Value::LargeInteger(value) => Some(value.as_ref().clone()),
+        _ => None,
+    }
+}
+
+fn operands(arguments: &[Value], selector: &str) -> Result<Operands> {
+    if let [Value::Integer(a), Value::Integer(b)] = arguments {
+        return Ok(Operands::Small(*a, *b));
+    }
+
+    match arguments {
+        [a, Value::Double(b)] => match double::to_f64(a) {
+            Some(a) => Ok(Operands::Double(a, *b)),
+            None => error(format!("Integer>>{} sent to {:?}", selector, a)),
+        },
+        [a, b] => match (large(a), large(b)) {
+            (Some(a), Some(b)) => Ok(Operands::Large(a, b)),
+            _ => error(format!(
+                "Integer>>{} expects an Integer argument, got {:?}",
+                selector, b
+            )),
+        },
+        _ => error(format!("Integer>>{} sent with wrong arguments", selector)),
+    }
+}
+
+/// Applies `small` to machine integers and `large` to big ones, or when
+/// `small` overflows. The result is demoted again if it fits.
+fn arithmetic(
+    arguments: &[Value],
+    selector: &str,
+    small: fn(i64, i64) -> Option<i64>,
+    large: fn(BigInt, BigInt) -> BigInt,
+) -> Result<Value> {
+    let result = match operands(arguments, selector)? {
+        Operands::Small(a, b) => match small(a, b) {
+            Some(value) => return Ok(Value::Integer(value)),
+            None => large(a.into(), b.into()),
+        },
+        Operands::Large(a, b) => large(a, b),
+        Operands::Double(..) => {
+            return error(format!(
+                "Integer>>{} expects an Integer argument, got {:?}",
+                selector, arguments[1]
+            ))
+        }
+    };
+
+    Ok(integer(result))
+}
+
+fn comparison(
+    arguments: &[Value],
+    selector: &str,
+    ordering: fn(Ordering) -> bool,
+) -> Result<Value> {
+    let result = match operands(arguments, selector)? {
+        Operands::Small(a, b) => ordering(a.cmp(&b)),
+        Operands::Large(a, b) => ordering(a.cmp(&b)),
+        Operands::Double(a, b) => a.partial_cmp(&b).is_some_and(ordering),
+    };
+
+    Ok(Value::Boolean(result))
+}
+
+/// Turns a big integer into the smallest value representing it.
+pub fn integer(value: BigInt) -> Value {
+    match i64::try_from(&value) {
+        Ok(value) => Value::Integer(value),
+        Err(_) => Value::LargeInteger(Rc::new(value)),
+    }
+}
+
+/// Whether the argument is a Double, making the receiver one too.
+fn coerces(arguments: &[Value]) -> bool {
+    matches!(arguments.get(1), Some(Value::Double(_)))
+}
+
+fn check_divisor(arguments: &[Value], selector: &str) -> Result<()> {
+    match arguments.get(1) {
+        Some(Value::Integer(0)) => error(format!("Division by zero in Integer>>{}", selector)),
+        _ => Ok(()),
+    }
+}
+
+fn add(universe: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    if coerces(&arguments) {
+        return double::add(universe, arguments);
+    }
+    arithmetic(&arguments, "+", i64::checked_add, |a, b| a + b)
+}
+
+fn subtract(universe: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    if coerces(&arguments) {
+        return double::subtract(universe, arguments);
+    }
+    arithmetic(&arguments, "-", i64::checked_sub, |a, b| a - b)
+}
+
+fn multiply(universe: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    if coerces(&arguments) {
+        return double::multiply(universe, arguments);
+    }
+    arithmetic(&arguments, "*", i64::checked_mul, |a, b| a * b)
+}
+
+/// Integer division rounding towards negative infinity, like Smalltalk's `//`.
+fn divide(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    check_divisor(&arguments, "/")?;
+    arithmetic(
+        &arguments,
+        "/",
+        |a, b| {
+            let quotient = a.checked_div(b)?;
+            if (a % b != 0) && ((a < 0) != (b < 0)) {
+                Some(quotient - 1)
+            } else {
+                Some(quotient)
+            }
+        },
+        |a, b| a.div_floor(&b),
+    )
+}
+
+/// Modulo with the sign of the divisor.
+fn modulo(universe: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    if coerces(&arguments) {
+        return double::modulo(universe, arguments);
+    }
+    check_divisor(&arguments, "%")?;
+    arithmetic(
+        &arguments,
+        "%",
+        |a, b| {
+            a.checked_rem_euclid(b)
+                .map(|r| if b < 0 && r != 0 { r + b } else { r })
+        },
+        |a, b| a.mod_floor(&b),
+    )
+}
+
+/// Remainder with the sign of the receiver.
+fn remainder(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    check_divisor(&arguments, "rem:")?;
+    arithmetic(&arguments, "rem:", i64::checked_rem, |a, b| a % b)
+}
+
+fn bit_and(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    arithmetic(&arguments, "&", |a, b| Some(a & b), |a, b| a & b)
+}
+
+fn bit_xor(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    arithmetic(&arguments, "bitXor:", |a, b| Some(a ^ b), |a, b| a ^ b)
+}
+
+/// The shift distance, which must be a non-negative machine integer.
+fn shift(arguments: &[Value], selector: &str) -> Result<usize> {
+    match arguments.get(1) {
+        Some(Value::Integer(b)) => match usize::try_from(*b) {
+            Ok(b) => Ok(b),
+            Err(_) => error(format!("Negative shift {} in Integer>>{}", b, selector)),
+        },
+        Some(b) => error(format!(
+            "Integer>>{} expects an Integer argument, got {:?}",
+            selector, b
+        )),
+        None => error(format!("Integer>>{} sent with wrong arguments", selector)),
+    }
+}
+
+fn shift_left(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let b = shift(&arguments, "<<")?;
+    let shifted = match &arguments[0] {
+        Value::Integer(a) => u32::try_from(b)
+            .ok()
+            .and_then(|b| a.checked_shl(b))
+            .filter(|shifted| shifted >> b == *a),
+        _ => None,
+    };
+
+    match shifted {
+        Some(shifted) => Ok(Value::Integer(shifted)),
+        None => match large(&arguments[0]) {
+            Some(a) => Ok(integer(a << b)),
+            None => error(format!("Integer>><< sent to {:?}", arguments[0])),
+        },
+    }
+}
+
+fn shift_right(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let b = shift(&arguments, ">>")?;
+    match &arguments[0] {
+        Value::Integer(a) => Ok(Value::Integer(
+            u32::try_from(b)
+                .ok()
+                .and_then(|b| a.checked_shr(b))
+                .unwrap_or(if *a < 0 { -1 } else { 0 }),
+        )),
+        receiver => match large(receiver) {
+            Some(a) => Ok(integer(a >> b)),
+            None => error(format!("Integer>>>> sent to {:?}", receiver)),
+        },
+    }
+}
+
+fn less_than(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    comparison(&arguments, "<", Ordering::is_lt)
+}
+
+fn less_than_or_equal(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    comparison(&arguments, "<=", Ordering::is_le)
+}
+
+fn greater_than(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    comparison(&arguments, ">", Ordering::is_gt)
+}
+
+fn greater_than_or_equal(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    comparison(&arguments, ">=", Ordering::is_ge)
+}
+
+/// Equality never fails: anything that is not an equal number is unequal.
+/// Large integers are always demoted when they fit, so comparing
+/// representations is enough.
+fn equal_values(arguments: &[Value]) -> bool {
+    if coerces(arguments) {
+        double::equal_values(&arguments[0], &arguments[1])
+    } else {
+        arguments[0].is_identical(&arguments[1])
+    }
+}
+
+fn equal(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    Ok(Value::Boolean(equal_values(&arguments)))
+}
+
+fn not_equal(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    Ok(Value::Boolean(!equal_values(&arguments)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn send(selector: &str, a: i64, b: i64) -> Result<Value> {
+        Universe::new().send(Value::Integer(a), selector, vec![Value::Integer(b)])
+    }
+
+    #[test]
+    fn test_division_rounds_down() {
+        assert_eq!(Value::Integer(3), send("/", 7, 2).unwrap());
+        assert_eq!(Value::Integer(-4), send("/", -7, 2).unwrap());
+        assert_eq!(Value::Integer(-4), send("/", 7, -2).unwrap());
+    }
+
+    #[test]
+    fn test_modulo_and_remainder() {
+        assert_eq!(Value::Integer(1), send("%", -7, 2).unwrap());
+        assert_eq!(Value::Integer(-1), send("%", 7, -2).unwrap());
+        assert_eq!(Value::Integer(-1), send("rem:", -7, 2).unwrap());
+    }
+
+    #[test]
+    fn test_shifts() {
+        assert_eq!(Value::Integer(40), send("<<", 5, 3).unwrap());
+        assert_eq!(Value::Integer(-3), send(">>", -5, 1).unwrap());
+    }
+
+    fn big(text: &str) -> Value {
+        integer(text.parse().unwrap())
+    }
+
+    fn send_values(selector: &str, a: Value, b: Value) -> Value {
+        Universe::new().send(a, selector, vec![b]).unwrap()
+    }
+
+    #[test]
+    fn test_overflow_promotes() {
+        assert_eq!(big("9223372036854775808"), send("+", i64::MAX, 1).unwrap());
+        assert_eq!(big("-9223372036854775809"), send("-", i64::MIN, 1).unwrap());
+        assert_eq!(
+            big("85070591730234615847396907784232501249"),
+            send("*", i64::MAX, i64::MAX).unwrap()
+        );
+        assert_eq!(big("9223372036854775808"), send("/", i64::MIN, -1).unwrap());
+        assert_eq!(big("18446744073709551616"), send("<<", 1, 64).unwrap());
+    }
+
+    #[test]
+    fn test_large_results_demote() {
+        let large = big("9223372036854775808");
+        assert_eq!(
+            Value::Integer(i64::MAX),
+            send_values("-", large.clone(), Value::Integer(1))
+        );
+        assert_eq!(
+            Value::Integer(1),
+            send_values("/", large.clone(), large.clone())
+        );
+        assert_eq!(
+            Value::Integer(1),
+            send_values(">>", large.clone(), Value::Integer(63))
+        );
+        assert_eq!(
+            Value::Integer(0),
+            send_values("%", large.clone(), Value::Integer(2))
+        );
+        assert_eq!(
+            Value::Integer(0),
+            send_values("&", large, Value::Integer(1))
+        );
+    }
+
+    #[test]
+    fn test_large_comparisons() {
+        let large = big("100000000000000000000");
+        assert_eq!(
+            Value::Boolean(true),
+            send_values("<", Value::Integer(i64::MAX), large.clone())
+        );
+        assert_eq!(
+            Value::Boolean(true),
+            send_values(">", large.clone(), big("-100000000000000000000"))
+        );
+        assert_eq!(
+            Value::Boolean(true),
+            send_values("=", large.clone(), big("100000000000000000000"))
+        );
+        assert_eq!(
+            Value::Boolean(false),
+            send_values("=", large, Value::Integer(1))
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(send("/", 1, 0).is_err());
+        assert!(send("<<", 1, -1).is_err());
+        assert!(Universe::new()
+            .send(Value::Integer(1), "<", vec![Value::Nil])
+            .is_err());
+    }
+
+    #[test]
+    fn test_equality_with_other_values() {
+        let result = Universe::new()
+            .send(Value::Integer(1), "=", vec![Value::Nil])
+            .unwrap();
+        assert_eq!(Value::Boolean(false), result);
+    }
+}
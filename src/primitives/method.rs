@@ -0,0 +1,97 @@
+use crate::interpreter::{self, InterpreterError, Result};
+use crate::primitives::PrimitiveTable;
+use crate::vm::Universe;
+use crate::vmobjects::{SMethod, Value};
+use std::rc::Rc;
+
+pub const PRIMITIVES: PrimitiveTable = &[
+    ("holder", holder),
+    ("invokeOn:with:", invoke_on_with),
+    ("signature", signature),
+];
+
+fn receiver(arguments: &[Value], selector: &str) -> Result<Rc<SMethod>> {
+    match &arguments[0] {
+        Value::Method(method) => Ok(method.clone()),
+        receiver => Err(InterpreterError::RuntimeError(format!(
+            "Method>>{} sent to {:?}",
+            selector, receiver
+        ))),
+    }
+}
+
+fn holder(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let method = receiver(&arguments, "holder")?;
+    Ok(method.holder().map_or(Value::Nil, Value::Class))
+}
+
+fn signature(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let method = receiver(&arguments, "signature")?;
+    Ok(Value::Symbol(method.signature().clone()))
+}
+
+/// Runs the method on the first argument without a lookup, passing the
+/// elements of the array as arguments.
+fn invoke_on_with(universe: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let method = receiver(&arguments, "invokeOn:with:")?;
+    let mut values = match &arguments[2] {
+        Value::Array(array) => array.to_vec(),
+        value => {
+            return Err(InterpreterError::RuntimeError(format!(
+                "Method>>invokeOn:with: expects an array, got {:?}",
+                value
+            )))
+        }
+    };
+
+    interpreter::check_arity(method.signature().as_str(), values.len())?;
+    values.insert(0, arguments[1].clone());
+    interpreter::invoke(universe, &method, values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::{compile_source, CompileOptions};
+    use crate::vmobjects::SArray;
+
+    #[test]
+    fn test_reflect_on_method() {
+        let mut universe = Universe::new();
+        let source = "Test = ( add: a to: b = ( ^ a + b ) )";
+        let class = compile_source(source.as_bytes(), "test", &CompileOptions::default()).unwrap();
+        let class = universe.define_class(class).unwrap();
+        let selector = universe.load_symbol("add:to:");
+        let method = Value::Method(class.lookup(&selector).unwrap());
+
+        assert_eq!(
+            Value::Symbol(selector),
+            universe.send(method.clone(), "signature", vec![]).unwrap()
+        );
+        assert_eq!(
+            Value::Class(class),
+            universe.send(method.clone(), "holder", vec![]).unwrap()
+        );
+
+        let arguments = vec![Value::Integer(2), Value::Integer(3)];
+        let arguments = Value::Array(Rc::new(SArray::new(arguments)));
+        assert_eq!(
+            Value::Integer(5),
+            universe
+                .send(
+                    method.clone(),
+                    "invokeOn:with:",
+                    vec![Value::Nil, arguments]
+                )
+                .unwrap()
+        );
+
+        let arguments = Value::Array(Rc::new(SArray::new(vec![])));
+        match universe.send(method, "invokeOn:with:", vec![Value::Nil, arguments]) {
+            Err(InterpreterError::RuntimeError(e)) => {
+                assert_eq!("#add:to: takes 2 arguments, got 0", e)
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,42 @@
+mod array;
+mod block;
+mod class;
+mod double;
+mod integer;
+mod method;
+mod object;
+mod string;
+mod system;
+
+use crate::vmobjects::PrimitiveFn;
+
+pub type PrimitiveTable = &'static [(&'static str, PrimitiveFn)];
+
+/// The primitives the VM implements for `class`. Class-side primitives are
+/// listed under `"Foo class"`.
+pub fn primitives(class: &str) -> PrimitiveTable {
+    match class {
+        "Array" => array::PRIMITIVES,
+        "Array class" => array::CLASS_PRIMITIVES,
+        "Block" => block::PRIMITIVES,
+        "Block1" => block::BLOCK1_PRIMITIVES,
+        "Block2" => block::BLOCK2_PRIMITIVES,
+        "Block3" => block::BLOCK3_PRIMITIVES,
+        "Class" => class::PRIMITIVES,
+        "Double" => double::PRIMITIVES,
+        "Integer" => integer::PRIMITIVES,
+        "Method" => method::PRIMITIVES,
+        "Object" => object::PRIMITIVES,
+        "String" => string::PRIMITIVES,
+        "Symbol" => string::SYMBOL_PRIMITIVES,
+        "System" => system::PRIMITIVES,
+        _ => &[],
+    }
+}
+
+pub fn lookup(class: &str, signature: &str) -> Option<PrimitiveFn> {
+    primitives(class)
+        .iter()
+        .find(|(name, _)| *name == signature)
+        .map(|&(_, primitive)| primitive)
+}
@@ -0,0 +1,285 @@
+use crate::interpreter::{self, InterpreterError, Result};
+use crate::primitives::PrimitiveTable;
+use crate::vm::Universe;
+use crate::vmobjects::{SSymbol, Value};
+use std::rc::Rc;
+
+pub const PRIMITIVES: PrimitiveTable = &[
+    ("==", identical),
+    ("class", class),
+    ("doesNotUnderstand:arguments:", does_not_understand),
+    ("escapedBlock:", escaped_block),
+    ("instVarAt:", inst_var_at),
+    ("instVarAt:put:", inst_var_at_put),
+    ("instVarNamed:", inst_var_named),
+    ("perform:", perform),
+    ("perform:with:", perform),
+    ("perform:withArguments:", perform_with_arguments),
+    ("respondsTo:", responds_to),
+    ("unknownGlobal:", unknown_global),
+];
+
+fn symbol(value: &Value, selector: &str) -> Result<Rc<SSymbol>> {
+    match value {
+        Value::Symbol(symbol) => Ok(symbol.clone()),
+        value => Err(InterpreterError::RuntimeError(format!(
+            "Object>>{} expects a symbol, got {:?}",
+            selector, value
+        ))),
+    }
+}
+
+/// Converts the 1-based SOM index `value` to a field index of `receiver`.
+fn field_index(universe: &Universe, receiver: &Value, value: &Value) -> Result<usize> {
+    let count = universe.class_of(receiver).num_instance_fields();
+    match value {
+        Value::Integer(index) if *index >= 1 && *index as u64 <= count as u64 => {
+            Ok(*index as usize - 1)
+        }
+        index => Err(InterpreterError::RuntimeError(format!(
+            "Field index {:?} out of range 1..{} in {:?}",
+            index, count, receiver
+        ))),
+    }
+}
+
+fn identical(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    Ok(Value::Boolean(arguments[0].is_identical(&arguments[1])))
+}
+
+fn class(universe: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    Ok(Value::Class(universe.class_of(&arguments[0])))
+}
+
+fn inst_var_at(universe: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let index = field_index(universe, &arguments[0], &arguments[1])?;
+    let value = match &arguments[0] {
+        Value::Object(object) => object.field(index),
+        Value::Class(class) => class.class_field(index),
+        _ => None,
+    };
+
+    Ok(value.unwrap_or(Value::Nil))
+}
+
+fn inst_var_at_put(universe: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let index = field_index(universe, &arguments[0], &arguments[1])?;
+    let value = arguments[2].clone();
+    match &arguments[0] {
+        Value::Object(object) => object.set_field(index, value.clone()),
+        Value::Class(class) => class.set_class_field(index, value.clone()),
+        _ => false,
+    };
+
+    Ok(value)
+}
+
+fn inst_var_named(universe: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let name = symbol(&arguments[1], "instVarNamed:")?;
+    let fields = universe.class_of(&arguments[0]).instance_fields();
+    match fields.iter().position(|field| field == name.as_str()) {
+        Some(index) => {
+            let index = Value::Integer(index as i64 + 1);
+            inst_var_at(universe, vec![arguments[0].clone(), index])
+        }
+        None => Err(InterpreterError::RuntimeError(format!(
+            "{:?} has no field named {}",
+            arguments[0],
+            name.as_str()
+        ))),
+    }
+}
+
+fn responds_to(universe: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let selector = symbol(&arguments[1], "respondsTo:")?;
+    let class = universe.class_of(&arguments[0]);
+    Ok(Value::Boolean(
+        universe.lookup_method(&class, &selector).is_some(),
+    ))
+}
+
+/// `perform:` and `perform:with:`, the arguments follow the selector.
+fn perform(universe: &mut Universe, mut arguments: Vec<Value>) -> Result<Value> {
+    let receiver = arguments.remove(0);
+    let selector = symbol(&arguments.remove(0), "perform:")?;
+    interpreter::check_arity(selector.as_str(), arguments.len())?;
+    interpreter::send(universe, receiver, &selector, arguments)
+}
+
+fn perform_with_arguments(universe: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let selector = symbol(&arguments[1], "perform:withArguments:")?;
+    let values = match &arguments[2] {
+        Value::Array(array) => array.to_vec(),
+        value => {
+            return Err(InterpreterError::RuntimeError(format!(
+                "Object>>perform:withArguments: expects an array, got {:?}",
+                value
+            )))
+        }
+    };
+
+    interpreter::check_arity(selector.as_str(), values.len())?;
+    interpreter::send(universe, arguments[0].clone(), &selector, values)
+}
+
+/// The default answer to a message without a method: fail with a readable
+/// error, naming the receiver's class and the selector.
+fn does_not_understand(universe: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    match &arguments[1] {
+        Value::Symbol(selector) => Err(InterpreterError::RuntimeError(
+            interpreter::not_understood(universe, &arguments[0], selector),
+        )),
+        selector => Err(InterpreterError::RuntimeError(format!(
+            "Object>>doesNotUnderstand:arguments: expects a symbol, got {:?}",
+            selector
+        ))),
+    }
+}
+
+fn unknown_global(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    match &arguments[1] {
+        Value::Symbol(name) => Err(InterpreterError::RuntimeError(format!(
+            "Unknown global {}",
+            name.as_str()
+        ))),
+        name => Err(InterpreterError::RuntimeError(format!(
+            "Object>>unknownGlobal: expects a symbol, got {:?}",
+            name
+        ))),
+    }
+}
+
+/// Sent to the sender of a block that tried to return from a method that
+/// has already returned.
+fn escaped_block(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let description = match &arguments[1] {
+        Value::Block(block) => format!("{:?}", block),
+        _ => "Block".into(),
+    };
+
+    Err(InterpreterError::RuntimeError(format!(
+        "{} has escaped and cannot be executed",
+        description
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::{compile_source, CompileOptions};
+    use crate::vmobjects::SArray;
+
+    fn point(universe: &mut Universe) -> Value {
+        let source = "Point = ( | x y | x = ( ^ x ) x: value = ( x := value ) )";
+        let class = compile_source(source.as_bytes(), "test", &CompileOptions::default()).unwrap();
+        let class = universe.define_class(class).unwrap();
+        universe.send(Value::Class(class), "new", vec![]).unwrap()
+    }
+
+    #[test]
+    fn test_identical() {
+        let mut universe = Universe::new();
+        let result = universe
+            .send(Value::Integer(3), "==", vec![Value::Integer(3)])
+            .unwrap();
+        assert_eq!(Value::Boolean(true), result);
+
+        let result = universe
+            .send(Value::Nil, "==", vec![Value::Boolean(false)])
+            .unwrap();
+        assert_eq!(Value::Boolean(false), result);
+    }
+
+    #[test]
+    fn test_inst_var_access() {
+        let mut universe = Universe::new();
+        let point = point(&mut universe);
+        let y = Value::Symbol(universe.load_symbol("y"));
+
+        universe
+            .send(
+                point.clone(),
+                "instVarAt:put:",
+                vec![Value::Integer(2), Value::Integer(7)],
+            )
+            .unwrap();
+        assert_eq!(
+            Value::Integer(7),
+            universe
+                .send(point.clone(), "instVarNamed:", vec![y])
+                .unwrap()
+        );
+        assert_eq!(
+            Value::Nil,
+            universe
+                .send(point.clone(), "instVarAt:", vec![Value::Integer(1)])
+                .unwrap()
+        );
+
+        match universe.send(point, "instVarAt:", vec![Value::Integer(3)]) {
+            Err(InterpreterError::RuntimeError(e)) => {
+                assert!(e.starts_with("Field index Integer(3) out of range 1..2"))
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_responds_to_and_perform() {
+        let mut universe = Universe::new();
+        let point = point(&mut universe);
+        let x = Value::Symbol(universe.load_symbol("x"));
+        let set_x = Value::Symbol(universe.load_symbol("x:"));
+        let missing = Value::Symbol(universe.load_symbol("z"));
+
+        let responds = |universe: &mut Universe, selector: &Value| {
+            universe
+                .send(point.clone(), "respondsTo:", vec![selector.clone()])
+                .unwrap()
+        };
+        assert_eq!(Value::Boolean(true), responds(&mut universe, &x));
+        assert_eq!(Value::Boolean(true), responds(&mut universe, &set_x));
+        assert_eq!(Value::Boolean(false), responds(&mut universe, &missing));
+
+        universe
+            .send(
+                point.clone(),
+                "perform:with:",
+                vec![set_x.clone(), Value::Integer(4)],
+            )
+            .unwrap();
+        assert_eq!(
+            Value::Integer(4),
+            universe.send(point.clone(), "perform:", vec![x]).unwrap()
+        );
+
+        let arguments = Value::Array(Rc::new(SArray::new(vec![Value::Integer(5)])));
+        universe
+            .send(
+                point.clone(),
+                "perform:withArguments:",
+                vec![set_x.clone(), arguments],
+            )
+            .unwrap();
+        assert_eq!(
+            Value::Integer(5),
+            universe
+                .send(point.clone(), "instVarAt:", vec![Value::Integer(1)])
+                .unwrap()
+        );
+
+        match universe.send(point, "perform:", vec![set_x]) {
+            Err(InterpreterError::RuntimeError(e)) => {
+                assert_eq!("#x: takes 1 arguments, got 0", e)
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_class() {
+        let mut universe = Universe::new();
+        let result = universe.send(Value::Nil, "class", vec![]).unwrap();
+        assert_eq!(Value::Class(universe.core_classes().nil.clone()), result);
+    }
+}
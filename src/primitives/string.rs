@@ -0,0 +1,256 @@
+use crate::interpreter::{InterpreterError, Result};
+use crate::primitives::PrimitiveTable;
+use crate::vm::Universe;
+use crate::vmobjects::{SString, Value};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+/// Shared by `String` and its subclass `Symbol`, so every primitive accepts
+/// either as the receiver.
+pub const PRIMITIVES: PrimitiveTable = &[
+    ("=", equals),
+    ("asString", as_string),
+    ("asSymbol", as_symbol),
+    ("charAt:", char_at),
+    ("concatenate:", concatenate),
+    ("hashcode", hashcode),
+    ("isDigits", is_digits),
+    ("isLetters", is_letters),
+    ("isWhiteSpace", is_white_space),
+    ("length", length),
+    ("primSubstringFrom:to:", substring),
+];
+
+/// The primitives `Symbol.som` redeclares.
+pub const SYMBOL_PRIMITIVES: PrimitiveTable = &[("asString", as_string)];
+
+/// The text of a `String` or `Symbol`.
+pub fn text(value: &Value) -> Option<&str> {
+    match value {
+        Value::String(string) => Some(string.as_str()),
+        Value::Symbol(symbol) => Some(symbol.as_str()),
+        _ => None,
+    }
+}
+
+fn receiver<'a>(arguments: &'a [Value], selector: &str) -> Result<&'a str> {
+    text(&arguments[0]).ok_or_else(|| {
+        InterpreterError::RuntimeError(format!("String>>{} sent to {:?}", selector, arguments[0]))
+    })
+}
+
+fn string(text: &str) -> Value {
+    Value::String(Rc::new(SString::new(text)))
+}
+
+/// Converts the 1-based SOM index `value` into a character index below
+/// `len`.
+fn index(value: &Value, len: usize, selector: &str) -> Result<usize> {
+    match value {
+        Value::Integer(index) if *index >= 1 && *index as u64 <= len as u64 => {
+            Ok(*index as usize - 1)
+        }
+        index => Err(InterpreterError::RuntimeError(format!(
+            "String>>{} index {:?} out of range 1..{}",
+            selector, index, len
+        ))),
+    }
+}
+
+fn equals(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let receiver = receiver(&arguments, "=")?;
+    Ok(Value::Boolean(text(&arguments[1]) == Some(receiver)))
+}
+
+fn as_string(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    match &arguments[0] {
+        Value::String(_) => Ok(arguments[0].clone()),
+        _ => Ok(string(receiver(&arguments, "asString")?)),
+    }
+}
+
+fn as_symbol(universe: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    match &arguments[0] {
+        Value::Symbol(_) => Ok(arguments[0].clone()),
+        _ => {
+            let text = receiver(&arguments, "asSymbol")?;
+            Ok(Value::Symbol(universe.load_symbol(text)))
+        }
+    }
+}
+
+fn char_at(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let receiver = receiver(&arguments, "charAt:")?;
+    let index = index(&arguments[1], receiver.chars().count(), "charAt:")?;
+    let char = receiver.chars().nth(index).unwrap();
+    Ok(string(char.encode_utf8(&mut [0; 4])))
+}
+
+fn concatenate(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let receiver = receiver(&arguments, "concatenate:")?;
+    match text(&arguments[1]) {
+        Some(other) => Ok(string(&format!("{}{}", receiver, other))),
+        None => Err(InterpreterError::RuntimeError(format!(
+            "String>>concatenate: expects a string, got {:?}",
+            arguments[1]
+        ))),
+    }
+}
+
+fn hashcode(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let mut hasher = DefaultHasher::new();
+    receiver(&arguments, "hashcode")?.hash(&mut hasher);
+    Ok(Value::Integer(hasher.finish() as i64))
+}
+
+/// Whether the receiver is non-empty and every character satisfies
+/// `predicate`.
+fn all_chars(arguments: &[Value], selector: &str, predicate: fn(char) -> bool) -> Result<Value> {
+    let receiver = receiver(arguments, selector)?;
+    Ok(Value::Boolean(
+        !receiver.is_empty() && receiver.chars().all(predicate),
+    ))
+}
+
+fn is_digits(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    all_chars(&arguments, "isDigits", char::is_numeric)
+}
+
+fn is_letters(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    all_chars(&arguments, "isLetters", char::is_alphabetic)
+}
+
+fn is_white_space(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    all_chars(&arguments, "isWhiteSpace", char::is_whitespace)
+}
+
+fn length(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let receiver = receiver(&arguments, "length")?;
+    Ok(Value::Integer(receiver.chars().count() as i64))
+}
+
+/// The characters from index `start` to `end`, both 1-based and inclusive.
+fn substring(_: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let receiver = SString::new(receiver(&arguments, "primSubstringFrom:to:")?);
+    let range = match (&arguments[1], &arguments[2]) {
+        (Value::Integer(start), Value::Integer(end)) if *start >= 1 && *end >= 0 => {
+            receiver.substring(*start as usize - 1, *end as usize)
+        }
+        _ => None,
+    };
+
+    match range {
+        Some(text) => Ok(string(text)),
+        None => Err(InterpreterError::RuntimeError(format!(
+            "String>>primSubstringFrom:to: range {:?} to {:?} out of bounds for {:?}",
+            arguments[1], arguments[2], receiver
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::{compile_source, CompileOptions};
+
+    fn run(universe: &mut Universe, body: &str) -> Result<Value> {
+        let source = format!("Test = ( run = ( {} ) )", body);
+        let class = compile_source(source.as_bytes(), "test", &CompileOptions::default()).unwrap();
+        let class = universe.define_class(class).unwrap();
+        let instance = universe.send(Value::Class(class), "new", vec![])?;
+        universe.send(instance, "run", vec![])
+    }
+
+    fn text_of(value: Value) -> String {
+        match value {
+            Value::String(string) => string.as_str().to_string(),
+            v => panic!("unexpected value {:?}", v),
+        }
+    }
+
+    #[test]
+    fn test_symbol_identity() {
+        let mut universe = Universe::new();
+        let tests = [
+            "^ #foo == #foo",
+            "^ 'foo' asSymbol == #foo",
+            "^ #foo asString asSymbol == #foo",
+            "^ #foo = 'foo'",
+        ];
+        for source in tests.iter() {
+            assert_eq!(Value::Boolean(true), run(&mut universe, source).unwrap());
+        }
+
+        assert_eq!(
+            Value::Boolean(false),
+            run(&mut universe, "^ 'foo' == ('fo' concatenate: 'o')").unwrap()
+        );
+        assert_eq!(
+            Value::Class(universe.core_classes().string.clone()),
+            run(&mut universe, "^ #foo class superclass").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_non_ascii_source_spanning_lines() {
+        let mut universe = Universe::new();
+        let class = universe
+            .load_source("T = (\n a = ( ^ 'é' )\n b = ( ^ 2 )\n)")
+            .unwrap();
+        let instance = universe.send(Value::Class(class), "new", vec![]).unwrap();
+        assert_eq!(
+            "é",
+            text_of(universe.send(instance.clone(), "a", vec![]).unwrap())
+        );
+        assert_eq!(
+            Value::Integer(2),
+            universe.send(instance, "b", vec![]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_characters_not_bytes() {
+        let mut universe = Universe::new();
+        assert_eq!(
+            Value::Integer(5),
+            run(&mut universe, "^ 'héllo' length").unwrap()
+        );
+        assert_eq!(
+            "é",
+            text_of(run(&mut universe, "^ 'héllo' charAt: 2").unwrap())
+        );
+        assert_eq!(
+            "éll",
+            text_of(run(&mut universe, "^ 'héllo' primSubstringFrom: 2 to: 4").unwrap())
+        );
+        assert_eq!(
+            "héllo wörld",
+            text_of(run(&mut universe, "^ 'héllo' concatenate: ' wörld'").unwrap())
+        );
+
+        match run(&mut universe, "^ 'héllo' charAt: 6") {
+            Err(InterpreterError::RuntimeError(e)) => {
+                assert_eq!("String>>charAt: index Integer(6) out of range 1..5", e)
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_character_classes() {
+        let mut universe = Universe::new();
+        assert_eq!(
+            Value::Boolean(true),
+            run(&mut universe, "^ 'äb' isLetters").unwrap()
+        );
+        assert_eq!(
+            Value::Boolean(false),
+            run(&mut universe, "^ '12a' isDigits").unwrap()
+        );
+        assert_eq!(
+            Value::Boolean(false),
+            run(&mut universe, "^ '' isWhiteSpace").unwrap()
+        );
+    }
+}
@@ -0,0 +1,291 @@
+use crate::interpreter::{InterpreterError, Result};
+use crate::primitives::{string, PrimitiveTable};
+use crate::vm::{LoadError, Universe};
+use crate::vmobjects::Value;
+use std::convert::TryFrom;
+
+pub const PRIMITIVES: PrimitiveTable = &[
+    ("errorPrintln:", error_println),
+    ("exit:", exit),
+    ("fullGC", full_gc),
+    ("global:", global),
+    ("global:put:", global_put),
+    ("hasGlobal:", has_global),
+    ("load:", load),
+    ("printNewline", print_newline),
+    ("printStackTrace", print_stack_trace),
+    ("printString:", print_string),
+    ("ticks", ticks),
+    ("time", time),
+];
+
+fn error<T>(description: String) -> Result<T> {
+    Err(InterpreterError::RuntimeError(description))
+}
+
+/// The text of a `String` or `Symbol` argument.
+fn text<'a>(arguments: &'a [Value], selector: &str) -> Result<&'a str> {
+    match string::text(&arguments[1]) {
+        Some(text) => Ok(text),
+        None => error(format!(
+            "System>>{} expects a String argument, got {:?}",
+            selector, arguments[1]
+        )),
+    }
+}
+
+fn print_string(universe: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let text = text(&arguments, "printString:")?;
+    universe.write_output(text)?;
+    Ok(arguments[0].clone())
+}
+
+fn print_newline(universe: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    universe.write_output("\n")?;
+    Ok(arguments[0].clone())
+}
+
+fn error_println(universe: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let text = text(&arguments, "errorPrintln:")?;
+    universe.write_error_output(&format!("{}\n", text))?;
+    Ok(arguments[0].clone())
+}
+
+fn print_stack_trace(universe: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    for frame in universe.stack_trace() {
+        universe.write_output(&format!("{}\n", frame))?;
+    }
+
+    Ok(arguments[0].clone())
+}
+
+/// Loads the class named by the argument, answering nil if there is no
+/// such class on the classpath.
+fn load(universe: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let name = text(&arguments, "load:")?;
+    match universe.load_class(name) {
+        Ok(class) => Ok(Value::Class(class)),
+        Err(LoadError::ClassNotFound(_)) => Ok(Value::Nil),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Stops the program. The status unwinds to whoever started the send, so
+/// the embedder decides whether to end the process.
+fn exit(universe: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    match arguments[1] {
+        Value::Integer(status) if universe.sandbox().deny_exit => {
+            error(format!("System>>exit: {} is not allowed", status))
+        }
+        Value::Integer(status) => match i32::try_from(status) {
+            Ok(status) => Err(InterpreterError::Exit(status)),
+            Err(_) => error(format!("Exit status {} out of range", status)),
+        },
+        ref status => error(format!(
+            "System>>exit: expects an Integer argument, got {:?}",
+            status
+        )),
+    }
+}
+
+fn global(universe: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let name = text(&arguments, "global:")?;
+    Ok(universe.global(name).unwrap_or(Value::Nil))
+}
+
+fn global_put(universe: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let name = text(&arguments, "global:put:")?;
+    universe.set_global(name, arguments[2].clone());
+    Ok(arguments[2].clone())
+}
+
+fn has_global(universe: &mut Universe, arguments: Vec<Value>) -> Result<Value> {
+    let name = text(&arguments, "hasGlobal:")?;
+    Ok(Value::Boolean(universe.has_global(name)))
+}
+
+/// Microseconds since the universe started.
+fn ticks(universe: &mut Universe, _: Vec<Value>) -> Result<Value> {
+    Ok(Value::Integer(universe.uptime().as_micros() as i64))
+}
+
+/// Milliseconds since the universe started.
+fn time(universe: &mut Universe, _: Vec<Value>) -> Result<Value> {
+    Ok(Value::Integer(universe.uptime().as_millis() as i64))
+}
+
+/// Values are reference counted and freed as soon as they are unreachable,
+/// so there is never anything left to collect.
+fn full_gc(_: &mut Universe, _: Vec<Value>) -> Result<Value> {
+    Ok(Value::Boolean(true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::{compile_source, CompileOptions};
+    use crate::vm::{OutputBuffer, Sandbox};
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    fn run(universe: &mut Universe, body: &str) -> Result<Value> {
+        let source = format!("Test = ( run = ( {} ) )", body);
+        let class = compile_source(source.as_bytes(), "test", &CompileOptions::default()).unwrap();
+        let class = universe.define_class(class).unwrap();
+        let instance = universe.send(Value::Class(class), "new", vec![])?;
+        universe.send(instance, "run", vec![])
+    }
+
+    #[test]
+    fn test_print_stack_trace() {
+        let mut universe = Universe::new();
+        let output = OutputBuffer::new();
+        universe.set_output(Box::new(output.clone()));
+        run(&mut universe, "system printStackTrace").unwrap();
+        assert_eq!("Test>>run at 1:25\n", output.contents());
+    }
+
+    #[test]
+    fn test_output_is_captured() {
+        let mut universe = Universe::new();
+        let output = OutputBuffer::new();
+        let error_output = OutputBuffer::new();
+        universe.set_output(Box::new(output.clone()));
+        universe.set_error_output(Box::new(error_output.clone()));
+
+        let result = run(
+            &mut universe,
+            "system printString: 'Hello'. system printString: #World. system printNewline.
+             system errorPrintln: 'oops'",
+        );
+        assert!(result.is_ok());
+        assert_eq!("HelloWorld\n", output.contents());
+        assert_eq!("oops\n", error_output.contents());
+    }
+
+    #[test]
+    fn test_globals() {
+        let mut universe = Universe::new();
+        let result = run(
+            &mut universe,
+            "system global: #answer put: 42. ^ system global: #answer",
+        );
+        assert_eq!(Value::Integer(42), result.unwrap());
+        assert_eq!(
+            Value::Boolean(true),
+            run(&mut universe, "^ system hasGlobal: #answer").unwrap()
+        );
+        assert_eq!(
+            Value::Boolean(false),
+            run(&mut universe, "^ system hasGlobal: #question").unwrap()
+        );
+        assert_eq!(
+            Value::Nil,
+            run(&mut universe, "^ system global: #question").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_load_missing_class_answers_nil() {
+        let mut universe = Universe::new();
+        assert_eq!(
+            Value::Nil,
+            run(&mut universe, "^ system load: #Missing").unwrap()
+        );
+        match run(&mut universe, "^ system load: #Integer").unwrap() {
+            Value::Class(class) => assert_eq!("Integer", class.name()),
+            v => panic!("unexpected value {:?}", v),
+        }
+    }
+
+    #[test]
+    fn test_exit_unwinds() {
+        let mut universe = Universe::new();
+        match run(&mut universe, "[ system exit: 3 ] value. ^ 1") {
+            Err(InterpreterError::Exit(3)) => (),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_time() {
+        let mut universe = Universe::new();
+        let result = run(&mut universe, "^ (system time * 1000) <= system ticks");
+        assert_eq!(Value::Boolean(true), result.unwrap());
+        assert_eq!(
+            Value::Boolean(true),
+            run(&mut universe, "^ system fullGC").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_sandbox_denies_exit_and_caps_output() {
+        let mut universe = Universe::new();
+        let output = OutputBuffer::new();
+        universe.set_output(Box::new(output.clone()));
+        universe.set_sandbox(Sandbox::restricted(vec![], 8, 0));
+
+        match run(&mut universe, "system exit: 0") {
+            Err(InterpreterError::RuntimeError(e)) => {
+                assert_eq!("System>>exit: 0 is not allowed", e)
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+        match run(
+            &mut universe,
+            "system printString: 'Hello'. system printString: 'World'",
+        ) {
+            Err(InterpreterError::RuntimeError(e)) => {
+                assert_eq!("Output limit of 8 bytes exceeded", e)
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+        assert_eq!("Hello", output.contents());
+    }
+
+    #[test]
+    fn test_sandbox_restricts_class_loading() {
+        let base = env::temp_dir().join(format!("som-rs-system-sandbox-{}", process::id()));
+        let root = base.join("classes");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("Allowed.som"), "Allowed = ( )").unwrap();
+        fs::write(base.join("Secret.som"), "Secret = ( )").unwrap();
+
+        let mut sandbox = Sandbox::restricted(vec![root.clone()], 1024, 0);
+        sandbox
+            .virtual_classes
+            .insert("Virtual".into(), "Virtual = ( answer = ( ^ 42 ) )".into());
+        let mut universe = Universe::with_classpath(vec![root]);
+        universe.set_sandbox(sandbox);
+
+        assert!(run(&mut universe, "^ system load: #Allowed").is_ok());
+        match run(&mut universe, "^ system load: #'../Secret'") {
+            Err(InterpreterError::LoadError(LoadError::AccessDenied(path))) => {
+                assert!(path.ends_with("../Secret.som"))
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+        assert_eq!(
+            Value::Integer(42),
+            run(&mut universe, "^ Virtual new answer").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_sandbox_rejects_misnamed_virtual_classes() {
+        let mut sandbox = Sandbox::restricted(vec![], 1024, 0);
+        sandbox
+            .virtual_classes
+            .insert("Foo".into(), "System = ( exit: code = ( ^ 0 ) )".into());
+        let mut universe = Universe::new();
+        universe.set_sandbox(sandbox);
+
+        match run(&mut universe, "^ Foo") {
+            Err(InterpreterError::LoadError(LoadError::ClassNameMismatch { expected, found })) => {
+                assert_eq!(("Foo", "System"), (expected.as_str(), found.as_str()))
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,449 @@
+use crate::compiler::{
+    compile_path, compile_source_with_fields, ClassFile, CompileError, CompileOptions,
+    CompiledClass, InheritedFields, KnownPrimitives, Parser, SourceStamp,
+};
+use crate::vm::Sandbox;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+const SOURCE_EXTENSION: &str = "som";
+const CACHE_EXTENSION: &str = "somc";
+const NUMERIC_CLASSES: &[&str] = &["Integer", "Double"];
+
+#[derive(Debug)]
+pub enum LoadError {
+    ClassNotFound(String),
+    CompileError(CompileError),
+    /// The class was compiled against other superclass fields than its
+    /// superclass has now.
+    InheritedFieldsMismatch(String),
+    /// The sandbox does not allow reading the class source at this path.
+    AccessDenied(PathBuf),
+    /// A source registered for the class `expected` defines `found`.
+    ClassNameMismatch {
+        expected: String,
+        found: String,
+    },
+}
+
+impl From<CompileError> for LoadError {
+    fn from(source: CompileError) -> Self {
+        LoadError::CompileError(source)
+    }
+}
+
+/// Finds class sources on the classpath and compiles them, keeping a `.somc`
+/// file next to each source so unchanged classes skip parsing.
+pub struct ClassLoader {
+    classpath: Vec<PathBuf>,
+    options: CompileOptions,
+    use_cache: bool,
+    sandbox: Sandbox,
+}
+
+impl ClassLoader {
+    pub fn new(classpath: Vec<PathBuf>) -> ClassLoader {
+        ClassLoader {
+            classpath,
+            options: CompileOptions::default(),
+            use_cache: true,
+            sandbox: Sandbox::default(),
+        }
+    }
+
+    pub fn classpath(&self) -> &[PathBuf] {
+        &self.classpath
+    }
+
+    pub fn options(&self) -> &CompileOptions {
+        &self.options
+    }
+
+    /// Replaces the compile options. When constant folding is requested
+    /// without any known primitives, they are read from the `Integer` and
+    /// `Double` classes on the classpath.
+    pub fn set_options(&mut self, mut options: CompileOptions) {
+        if options.fold_constants && options.known_primitives == KnownPrimitives::default() {
+            options.known_primitives = self.discover_primitives();
+        }
+
+        self.options = options;
+    }
+
+    pub fn set_cache_enabled(&mut self, enabled: bool) {
+        self.use_cache = enabled;
+    }
+
+    /// Restricts which sources primitive discovery reads and whether caches
+    /// are written. Set it before the options, which may discover
+    /// primitives.
+    pub fn set_sandbox(&mut self, sandbox: Sandbox) {
+        self.sandbox = sandbox;
+    }
+
+    pub fn find_source(&self, name: &str) -> Option<PathBuf> {
+        self.classpath
+            .iter()
+            .map(|directory| directory.join(name).with_extension(SOURCE_EXTENSION))
+            .find(|path| path.is_file())
+    }
+
+    fn discover_primitives(&self) -> KnownPrimitives {
+        let options = CompileOptions::default();
+        let mut primitives = KnownPrimitives::default();
+        for name in NUMERIC_CLASSES {
+            let class = self
+                .find_source(name)
+                .filter(|path| self.sandbox.allows_path(path))
+                .and_then(|path| compile_path(path, &options).ok());
+            if let Some(class) = class {
+                primitives.add_class(&class);
+            }
+        }
+
+        primitives
+    }
+
+    /// The superclass the source of class `name` declares, read without
+    /// compiling the class.
+    pub fn superclass_name(&self, name: &str) -> Result<Option<String>, LoadError> {
+        let path = self
+            .find_source(name)
+            .ok_or_else(|| LoadError::ClassNotFound(name.into()))?;
+        let (_, superclass) = self.read_header(&path)?;
+        Ok(superclass)
+    }
+
+    /// The name and superclass the source at `path` declares.
+    pub fn read_header(&self, path: &Path) -> Result<(String, Option<String>), LoadError> {
+        let file = File::open(path).map_err(CompileError::from)?;
+        let mut parser = Parser::new(BufReader::new(file), path);
+        Ok(parser.parse_header().map_err(CompileError::from)?)
+    }
+
+    /// Loads the class `name` as a subclass of a class without fields.
+    pub fn load(&self, name: &str) -> Result<CompiledClass, LoadError> {
+        self.load_with_fields(name, &InheritedFields::default())
+    }
+
+    /// Loads the class `name` compiled against the `inherited` fields.
+    pub fn load_with_fields(
+        &self,
+        name: &str,
+        inherited: &InheritedFields,
+    ) -> Result<CompiledClass, LoadError> {
+        match self.find_source(name) {
+            Some(path) => Ok(self.load_path_with_fields(&path, inherited)?),
+            None => Err(LoadError::ClassNotFound(name.into())),
+        }
+    }
+
+    pub fn load_path(&self, path: &Path) -> Result<CompiledClass, CompileError> {
+        self.load_path_with_fields(path, &InheritedFields::default())
+    }
+
+    pub fn load_path_with_fields(
+        &self,
+        path: &Path,
+        inherited: &InheritedFields,
+    ) -> Result<CompiledClass, CompileError> {
+        let metadata = fs::metadata(path)?;
+        let stamp = SourceStamp::unhashed(metadata.len(), metadata.modified().ok());
+        let cache_path = path.with_extension(CACHE_EXTENSION);
+
+        let cached = if self.use_cache {
+            read_cache(&cache_path).filter(|cached| {
+                cached.options == self.options && cached.class.inherited == *inherited
+            })
+        } else {
+            None
+        };
+
+        if let Some(ref cached) = cached {
+            if cached.stamp.matches_time(&stamp) {
+                return Ok(cached.class.clone());
+            }
+        }
+
+        let source = fs::read(path)?;
+        let stamp = SourceStamp::new(&source, metadata.modified().ok());
+
+        let class = match cached {
+            Some(cached) if cached.stamp.matches_content(&stamp) => cached.class,
+            _ => compile_source_with_fields(source.as_slice(), path, &self.options, inherited)?,
+        };
+
+        if self.use_cache && !self.sandbox.deny_cache_writes {
+            // the cache is only an optimization, a read-only classpath is fine
+            let _ = write_cache(&cache_path, stamp, &self.options, &class);
+        }
+
+        Ok(class)
+    }
+}
+
+fn read_cache(path: &Path) -> Option<ClassFile> {
+    let file = File::open(path).ok()?;
+    ClassFile::read_from(BufReader::new(file)).ok()
+}
+
+fn write_cache(
+    path: &Path,
+    stamp: SourceStamp,
+    options: &CompileOptions,
+    class: &CompiledClass,
+) -> io::Result<()> {
+    let class_file = ClassFile {
+        stamp,
+        options: options.clone(),
+        class: class.clone(),
+    };
+
+    // write to a temporary file first so a concurrent reader never sees a
+    // partially written class
+    let temporary = path.with_extension(format!("{}.tmp", CACHE_EXTENSION));
+    class_file.write_to(BufWriter::new(File::create(&temporary)?))?;
+    fs::rename(&temporary, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::{CompiledMethod, Literal, OptimizationLevel};
+    use crate::interpreter::Bytecode;
+    use std::env;
+    use std::process;
+
+    fn classpath(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("som-rs-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_load_class_not_found() {
+        let loader = ClassLoader::new(vec![classpath("not-found")]);
+        match loader.load("Missing") {
+            Err(LoadError::ClassNotFound(name)) => assert_eq!("Missing", name),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_load_writes_cache() {
+        let directory = classpath("writes-cache");
+        fs::write(directory.join("Hello.som"), "Hello = ( run = ( ^ 1 ) )").unwrap();
+
+        let loader = ClassLoader::new(vec![directory.clone()]);
+        let class = loader.load("Hello").unwrap();
+        assert_eq!("Hello", class.name);
+
+        let cached = read_cache(&directory.join("Hello.somc")).unwrap();
+        assert_eq!(class, cached.class);
+        assert_eq!(class, loader.load("Hello").unwrap());
+    }
+
+    #[test]
+    fn test_load_uses_cache_when_source_unchanged() {
+        let directory = classpath("uses-cache");
+        let source = "Hello = ( run = ( ^ 1 ) )";
+        fs::write(directory.join("Hello.som"), source).unwrap();
+
+        let loader = ClassLoader::new(vec![directory.clone()]);
+        let mut class = loader.load("Hello").unwrap();
+
+        // rewrite the cache with a different class under the same stamp to
+        // prove the loader reads it instead of recompiling
+        class.name = "FromCache".into();
+        let cached = read_cache(&directory.join("Hello.somc")).unwrap();
+        write_cache(
+            &directory.join("Hello.somc"),
+            cached.stamp,
+            &cached.options,
+            &class,
+        )
+        .unwrap();
+
+        assert_eq!("FromCache", loader.load("Hello").unwrap().name);
+    }
+
+    #[test]
+    fn test_load_recompiles_when_inherited_fields_change() {
+        let directory = classpath("inherited-fields");
+        fs::write(
+            directory.join("Hello.som"),
+            "Hello = Base ( run = ( ^ x ) )",
+        )
+        .unwrap();
+
+        let loader = ClassLoader::new(vec![directory.clone()]);
+        assert_eq!(
+            Some("Base".into()),
+            loader.superclass_name("Hello").unwrap()
+        );
+        let class = loader.load("Hello").unwrap();
+        assert!(class.inherited.instance_fields.is_empty());
+
+        let inherited = InheritedFields {
+            instance_fields: vec!["x".into()],
+            class_fields: vec![],
+        };
+        let class = loader.load_with_fields("Hello", &inherited).unwrap();
+        assert_eq!(inherited, class.inherited);
+        let cached = read_cache(&directory.join("Hello.somc")).unwrap();
+        assert_eq!(inherited, cached.class.inherited);
+    }
+
+    #[test]
+    fn test_load_invalidates_cache_when_source_changes() {
+        let directory = classpath("invalidates-cache");
+        fs::write(directory.join("Hello.som"), "Hello = ( run = ( ^ 1 ) )").unwrap();
+
+        let loader = ClassLoader::new(vec![directory.clone()]);
+        loader.load("Hello").unwrap();
+
+        fs::write(
+            directory.join("Hello.som"),
+            "Hello = ( | a b | run = ( ^ 2 ) )",
+        )
+        .unwrap();
+        let class = loader.load("Hello").unwrap();
+        assert_eq!(vec!["a", "b"], class.instance_fields);
+
+        let cached = read_cache(&directory.join("Hello.somc")).unwrap();
+        assert_eq!(class, cached.class);
+    }
+
+    #[test]
+    fn test_load_recompiles_with_different_options() {
+        let directory = classpath("different-options");
+        fs::write(directory.join("Hello.som"), "Hello = ( run = ( ^ self ) )").unwrap();
+
+        let mut loader = ClassLoader::new(vec![directory.clone()]);
+        loader.load("Hello").unwrap();
+
+        loader.set_options(CompileOptions {
+            optimization_level: OptimizationLevel::None,
+            ..CompileOptions::default()
+        });
+        let class = loader.load("Hello").unwrap();
+        match &class.instance_methods[0] {
+            CompiledMethod::Bytecode { code, .. } => assert_eq!(2, code.bytecodes.len()),
+            m => panic!("unexpected method {:?}", m),
+        }
+
+        let cached = read_cache(&directory.join("Hello.somc")).unwrap();
+        assert_eq!(loader.options(), &cached.options);
+    }
+
+    #[test]
+    fn test_load_ignores_corrupt_cache() {
+        let directory = classpath("corrupt-cache");
+        fs::write(directory.join("Hello.som"), "Hello = ( run = ( ^ 1 ) )").unwrap();
+        fs::write(directory.join("Hello.somc"), "garbage").unwrap();
+
+        let loader = ClassLoader::new(vec![directory]);
+        assert_eq!("Hello", loader.load("Hello").unwrap().name);
+    }
+
+    #[test]
+    fn test_load_recompiles_cache_with_bad_operands() {
+        let directory = classpath("bad-operands");
+        fs::write(directory.join("Hello.som"), "Hello = ( run = ( ^ 1 ) )").unwrap();
+
+        let loader = ClassLoader::new(vec![directory.clone()]);
+        let class = loader.load("Hello").unwrap();
+
+        let cached = read_cache(&directory.join("Hello.somc")).unwrap();
+        let mut corrupted = class.clone();
+        match &mut corrupted.instance_methods[0] {
+            CompiledMethod::Bytecode { code, .. } => {
+                code.bytecodes[0] = Bytecode::PushConstant { index: 42 }
+            }
+            m => panic!("unexpected method {:?}", m),
+        }
+        write_cache(
+            &directory.join("Hello.somc"),
+            cached.stamp,
+            &cached.options,
+            &corrupted,
+        )
+        .unwrap();
+
+        assert!(read_cache(&directory.join("Hello.somc")).is_none());
+        assert_eq!(class, loader.load("Hello").unwrap());
+    }
+
+    #[test]
+    fn test_load_without_cache() {
+        let directory = classpath("without-cache");
+        fs::write(directory.join("Hello.som"), "Hello = ()").unwrap();
+
+        let mut loader = ClassLoader::new(vec![directory.clone()]);
+        loader.set_cache_enabled(false);
+        loader.load("Hello").unwrap();
+        assert!(!directory.join("Hello.somc").exists());
+    }
+
+    #[test]
+    fn test_load_without_cache_writes_in_sandbox() {
+        let directory = classpath("sandbox-cache");
+        fs::write(directory.join("Hello.som"), "Hello = ()").unwrap();
+
+        let mut loader = ClassLoader::new(vec![directory.clone()]);
+        loader.set_sandbox(Sandbox::restricted(vec![directory.clone()], 0, 0));
+        loader.load("Hello").unwrap();
+        assert!(!directory.join("Hello.somc").exists());
+    }
+
+    #[test]
+    fn test_discover_primitives_outside_sandbox() {
+        let directory = classpath("sandbox-primitives");
+        fs::write(
+            directory.join("Integer.som"),
+            "Integer = ( + other = primitive )",
+        )
+        .unwrap();
+
+        let mut loader = ClassLoader::new(vec![directory]);
+        loader.set_sandbox(Sandbox::restricted(vec![], 0, 0));
+        loader.set_options(CompileOptions {
+            fold_constants: true,
+            ..CompileOptions::default()
+        });
+        assert!(!loader.options().known_primitives.contains("Integer", "+"));
+    }
+
+    #[test]
+    fn test_load_discovers_known_primitives() {
+        let directory = classpath("known-primitives");
+        fs::write(
+            directory.join("Integer.som"),
+            "Integer = ( + other = primitive - other = ( ^ 0 ) )",
+        )
+        .unwrap();
+        fs::write(
+            directory.join("Hello.som"),
+            "Hello = ( run = ( ^ (1 + 2) - 3 ) )",
+        )
+        .unwrap();
+
+        let mut loader = ClassLoader::new(vec![directory]);
+        loader.set_options(CompileOptions {
+            fold_constants: true,
+            ..CompileOptions::default()
+        });
+        assert!(loader.options().known_primitives.contains("Integer", "+"));
+        assert!(!loader.options().known_primitives.contains("Integer", "-"));
+
+        let class = loader.load("Hello").unwrap();
+        match &class.instance_methods[0] {
+            CompiledMethod::Bytecode { code, .. } => {
+                assert_eq!(Some(&Literal::Integer(3)), code.literals.first())
+            }
+            m => panic!("unexpected method {:?}", m),
+        }
+    }
+}
@@ -1 +1,9 @@
+mod class_loader;
+mod output;
+mod sandbox;
 mod universe;
+
+pub use self::class_loader::{ClassLoader, LoadError};
+pub use self::output::OutputBuffer;
+pub use self::sandbox::Sandbox;
+pub use self::universe::{CoreClasses, Universe, UniverseOptions};
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Restricts what SOM code may do outside the VM. The default allows
+/// everything.
+#[derive(Clone, Debug, Default)]
+pub struct Sandbox {
+    /// The directories class sources may be read from. Sources anywhere
+    /// else, including ones a class name reaches through `..`, are denied.
+    pub class_roots: Option<Vec<PathBuf>>,
+    /// Class sources served from memory by class name, taking precedence
+    /// over the classpath.
+    pub virtual_classes: HashMap<String, String>,
+    /// Make `System>>exit:` fail instead of ending the program.
+    pub deny_exit: bool,
+    /// The most bytes `System` may print, on output and error output
+    /// together.
+    pub output_limit: Option<usize>,
+    /// The most elements `Array class>>new:` may allocate at once.
+    pub max_array_length: Option<usize>,
+    /// Never write `.somc` caches next to class sources.
+    pub deny_cache_writes: bool,
+}
+
+impl Sandbox {
+    /// A sandbox denying everything it can: no class sources outside
+    /// `class_roots`, no cache writes, no exiting, at most `output_limit`
+    /// bytes of output and no arrays longer than `max_array_length`.
+    pub fn restricted(
+        class_roots: Vec<PathBuf>,
+        output_limit: usize,
+        max_array_length: usize,
+    ) -> Sandbox {
+        Sandbox {
+            class_roots: Some(class_roots),
+            virtual_classes: HashMap::new(),
+            deny_exit: true,
+            output_limit: Some(output_limit),
+            max_array_length: Some(max_array_length),
+            deny_cache_writes: true,
+        }
+    }
+
+    /// Whether a class source may be read from `path`. Paths are compared
+    /// after resolving symbolic links and `..`.
+    pub fn allows_path(&self, path: &Path) -> bool {
+        let roots = match &self.class_roots {
+            Some(roots) => roots,
+            None => return true,
+        };
+
+        match path.canonicalize() {
+            Ok(path) => roots
+                .iter()
+                .filter_map(|root| root.canonicalize().ok())
+                .any(|root| path.starts_with(root)),
+            Err(_) => false,
+        }
+    }
+
+    /// Whether an array of `length` elements may be allocated.
+    pub fn allows_array_length(&self, length: usize) -> bool {
+        match self.max_array_length {
+            Some(max) => length <= max,
+            None => true,
+        }
+    }
+
+    /// Whether `written` more bytes may be printed after `total` already
+    /// were.
+    pub fn allows_output(&self, total: usize, written: usize) -> bool {
+        match self.output_limit {
+            Some(limit) => total.saturating_add(written) <= limit,
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    #[test]
+    fn test_allows_paths_below_roots() {
+        let base = env::temp_dir().join(format!("som-rs-sandbox-{}", process::id()));
+        let root = base.join("root");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("Inside.som"), "").unwrap();
+        fs::write(base.join("Outside.som"), "").unwrap();
+
+        let sandbox = Sandbox::restricted(vec![root.clone()], 0, 0);
+        assert!(sandbox.allows_path(&root.join("Inside.som")));
+        assert!(!sandbox.allows_path(&root.join("../Outside.som")));
+        assert!(!sandbox.allows_path(&root.join("Missing.som")));
+        assert!(Sandbox::default().allows_path(&base.join("Outside.som")));
+    }
+
+    #[test]
+    fn test_output_limit() {
+        let sandbox = Sandbox::restricted(vec![], 10, 3);
+        assert!(sandbox.allows_output(4, 6));
+        assert!(!sandbox.allows_output(4, 7));
+        assert!(Sandbox::default().allows_output(usize::MAX, 1));
+        assert!(sandbox.allows_array_length(3));
+        assert!(!sandbox.allows_array_length(4));
+        assert!(Sandbox::default().allows_array_length(usize::MAX));
+    }
+}
@@ -1,25 +1,803 @@
-use crate::vmobjects::SSymbol;
+use crate::compiler::{
+    compile_source_with_fields, CompileError, CompileOptions, CompiledClass, CompiledMethod,
+    InheritedFields, Literal, Parser,
+};
+use crate::interpreter::{self, CallSiteStats, Code, Frame, FrameInfo, InterpreterError};
+use crate::primitives;
+use crate::vm::{ClassLoader, LoadError, Sandbox};
+use crate::vmobjects::{
+    MethodBody, PrimitiveFn, SArray, SClass, SMethod, SObject, SString, SSymbol, SymbolId,
+    SymbolTable, Value,
+};
 use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// The classes the VM itself depends on. They exist before any source is
+/// loaded so the interpreter can run without a class library.
+pub struct CoreClasses {
+    pub object: Rc<SClass>,
+    pub class: Rc<SClass>,
+    pub metaclass: Rc<SClass>,
+    pub nil: Rc<SClass>,
+    pub boolean: Rc<SClass>,
+    pub true_class: Rc<SClass>,
+    pub false_class: Rc<SClass>,
+    pub integer: Rc<SClass>,
+    pub double: Rc<SClass>,
+    pub string: Rc<SClass>,
+    pub symbol: Rc<SClass>,
+    pub block: Rc<SClass>,
+    /// The classes of blocks taking no, one and two arguments.
+    pub blocks: [Rc<SClass>; 3],
+    pub array: Rc<SClass>,
+    pub method: Rc<SClass>,
+    pub system: Rc<SClass>,
+}
+
+impl CoreClasses {
+    fn all(&self) -> Vec<&Rc<SClass>> {
+        vec![
+            &self.object,
+            &self.class,
+            &self.metaclass,
+            &self.nil,
+            &self.boolean,
+            &self.true_class,
+            &self.false_class,
+            &self.integer,
+            &self.double,
+            &self.string,
+            &self.symbol,
+            &self.block,
+            &self.blocks[0],
+            &self.blocks[1],
+            &self.blocks[2],
+            &self.array,
+            &self.method,
+            &self.system,
+        ]
+    }
+}
+
+/// How to set up a universe for embedding.
+#[derive(Clone, Debug)]
+pub struct UniverseOptions {
+    pub classpath: Vec<PathBuf>,
+    pub compile_options: CompileOptions,
+    pub quickening: bool,
+    /// Load the sources of the core classes found on the classpath.
+    pub load_system_classes: bool,
+    pub sandbox: Sandbox,
+}
+
+impl Default for UniverseOptions {
+    fn default() -> Self {
+        UniverseOptions {
+            classpath: vec![],
+            compile_options: CompileOptions::default(),
+            quickening: true,
+            load_system_classes: true,
+            sandbox: Sandbox::default(),
+        }
+    }
+}
+
+/// Keyed by the identity of the receiver class and the selector.
+type MethodCacheKey = (*const SClass, SymbolId);
 
 pub struct Universe {
-    symbols: HashMap<String, Rc<SSymbol>>,
+    symbols: SymbolTable,
+    method_cache: HashMap<MethodCacheKey, (Rc<SClass>, Rc<SMethod>)>,
+    globals: HashMap<String, Value>,
+    class_loader: ClassLoader,
+    core: CoreClasses,
+    quickening: bool,
+    method_epoch: u64,
+    frames: Vec<Rc<Frame>>,
+    error_trace: Option<Vec<FrameInfo>>,
+    output: Box<dyn Write>,
+    error_output: Box<dyn Write>,
+    start_time: Instant,
+    sandbox: Sandbox,
+    /// Bytes printed so far, counted against the output limit.
+    output_written: usize,
+    /// Primitives registered by the embedder, by holder name.
+    registered_primitives: HashMap<String, Vec<(String, PrimitiveFn)>>,
 }
 
 impl Universe {
     pub fn new() -> Universe {
+        Universe::with_classpath(vec![])
+    }
+
+    pub fn with_classpath(classpath: Vec<PathBuf>) -> Universe {
+        let mut globals = HashMap::new();
+        let mut symbols = SymbolTable::new();
+        let mut bootstrap = |name: &str, superclass: Option<&Rc<SClass>>| {
+            let class = Rc::new(SClass::new(name, superclass.cloned()));
+            globals.insert(name.to_string(), Value::Class(class.clone()));
+            class
+        };
+
+        let object = bootstrap("Object", None);
+        let class = bootstrap("Class", Some(&object));
+        let metaclass = bootstrap("Metaclass", Some(&class));
+        let nil = bootstrap("Nil", Some(&object));
+        let boolean = bootstrap("Boolean", Some(&object));
+        let true_class = bootstrap("True", Some(&boolean));
+        let false_class = bootstrap("False", Some(&boolean));
+        let integer = bootstrap("Integer", Some(&object));
+        let double = bootstrap("Double", Some(&object));
+        let string = bootstrap("String", Some(&object));
+        let symbol = bootstrap("Symbol", Some(&string));
+        let block = bootstrap("Block", Some(&object));
+        let blocks = [
+            bootstrap("Block1", Some(&block)),
+            bootstrap("Block2", Some(&block)),
+            bootstrap("Block3", Some(&block)),
+        ];
+        let array = bootstrap("Array", Some(&object));
+        let method = bootstrap("Method", Some(&object));
+        let system = bootstrap("System", Some(&object));
+
+        globals.insert("nil".into(), Value::Nil);
+        globals.insert("true".into(), Value::Boolean(true));
+        globals.insert("false".into(), Value::Boolean(false));
+        globals.insert(
+            "system".into(),
+            Value::Object(Rc::new(SObject::new(system.clone(), 0))),
+        );
+
+        let core = CoreClasses {
+            object,
+            class,
+            metaclass,
+            nil,
+            boolean,
+            true_class,
+            false_class,
+            integer,
+            double,
+            string,
+            symbol,
+            block,
+            blocks,
+            array,
+            method,
+            system,
+        };
+
+        // Superclasses come first, so their metaclasses exist by the time
+        // a subclass needs them.
+        for class in core.all() {
+            class.set_metaclass(Rc::new(SClass::new_metaclass(class, &core.class)));
+            install_primitives(class, &mut symbols);
+        }
+
         Universe {
-            symbols: HashMap::new(),
+            symbols,
+            method_cache: HashMap::new(),
+            globals,
+            class_loader: ClassLoader::new(classpath),
+            core,
+            quickening: true,
+            method_epoch: 0,
+            frames: vec![],
+            error_trace: None,
+            output: Box::new(io::stdout()),
+            error_output: Box::new(io::stderr()),
+            start_time: Instant::now(),
+            sandbox: Sandbox::default(),
+            output_written: 0,
+            registered_primitives: HashMap::new(),
+        }
+    }
+
+    pub fn with_options(options: UniverseOptions) -> Result<Universe, LoadError> {
+        let mut universe = Universe::with_classpath(options.classpath);
+        universe.set_quickening_enabled(options.quickening);
+        universe.set_sandbox(options.sandbox);
+        universe
+            .class_loader_mut()
+            .set_options(options.compile_options);
+        if options.load_system_classes {
+            universe.load_system_classes()?;
+        }
+
+        Ok(universe)
+    }
+
+    pub fn class_loader(&self) -> &ClassLoader {
+        &self.class_loader
+    }
+
+    pub fn class_loader_mut(&mut self) -> &mut ClassLoader {
+        &mut self.class_loader
+    }
+
+    pub fn core_classes(&self) -> &CoreClasses {
+        &self.core
+    }
+
+    pub fn quickening_enabled(&self) -> bool {
+        self.quickening
+    }
+
+    /// Turns in-place rewriting of executed instructions on or off. Turning
+    /// it off also undoes everything quickened so far, which makes traces
+    /// show the bytecodes exactly as compiled.
+    pub fn set_quickening_enabled(&mut self, enabled: bool) {
+        self.quickening = enabled;
+        if !enabled {
+            self.flush_method_caches();
+        }
+    }
+
+    /// Where `System` prints; standard output unless replaced.
+    pub fn output(&mut self) -> &mut dyn Write {
+        self.output.as_mut()
+    }
+
+    /// Prints `text` to the output, within the limit of the sandbox.
+    pub fn write_output(&mut self, text: &str) -> interpreter::Result<()> {
+        self.count_output(text)?;
+        let result = self.output.write_all(text.as_bytes());
+        result.map_err(|e| InterpreterError::RuntimeError(format!("Cannot write output: {}", e)))
+    }
+
+    /// Prints `text` to the error output, within the limit of the sandbox.
+    pub fn write_error_output(&mut self, text: &str) -> interpreter::Result<()> {
+        self.count_output(text)?;
+        let result = self.error_output.write_all(text.as_bytes());
+        result.map_err(|e| InterpreterError::RuntimeError(format!("Cannot write output: {}", e)))
+    }
+
+    fn count_output(&mut self, text: &str) -> interpreter::Result<()> {
+        if !self.sandbox.allows_output(self.output_written, text.len()) {
+            return Err(InterpreterError::RuntimeError(format!(
+                "Output limit of {} bytes exceeded",
+                self.sandbox.output_limit.unwrap_or_default()
+            )));
+        }
+
+        self.output_written += text.len();
+        Ok(())
+    }
+
+    pub fn set_output(&mut self, output: Box<dyn Write>) {
+        self.output = output;
+    }
+
+    /// Where `System` prints errors; standard error unless replaced.
+    pub fn error_output(&mut self) -> &mut dyn Write {
+        self.error_output.as_mut()
+    }
+
+    pub fn set_error_output(&mut self, error_output: Box<dyn Write>) {
+        self.error_output = error_output;
+    }
+
+    pub fn sandbox(&self) -> &Sandbox {
+        &self.sandbox
+    }
+
+    /// Replaces the policy for what SOM code may do outside the VM. Classes
+    /// already loaded stay loaded.
+    pub fn set_sandbox(&mut self, sandbox: Sandbox) {
+        self.class_loader.set_sandbox(sandbox.clone());
+        self.sandbox = sandbox;
+    }
+
+    /// The time since the universe was created.
+    pub fn uptime(&self) -> Duration {
+        self.start_time.elapsed()
+    }
+
+    pub fn has_global(&self, name: &str) -> bool {
+        self.globals.contains_key(name)
+    }
+
+    pub fn global(&self, name: &str) -> Option<Value> {
+        self.globals.get(name).cloned()
+    }
+
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        self.globals.insert(name.into(), value);
+    }
+
+    pub fn class_of(&self, value: &Value) -> Rc<SClass> {
+        match value {
+            Value::Nil => self.core.nil.clone(),
+            Value::Boolean(true) => self.core.true_class.clone(),
+            Value::Boolean(false) => self.core.false_class.clone(),
+            Value::Integer(_) | Value::LargeInteger(_) => self.core.integer.clone(),
+            Value::Double(_) => self.core.double.clone(),
+            Value::String(_) => self.core.string.clone(),
+            Value::Symbol(_) => self.core.symbol.clone(),
+            Value::Object(object) => object.class().clone(),
+            Value::Class(class) => match class.metaclass() {
+                Some(metaclass) => metaclass,
+                None if class.is_metaclass() => self.core.metaclass.clone(),
+                None => self.core.class.clone(),
+            },
+            Value::Block(block) => match self.core.blocks.get(block.num_arguments()) {
+                Some(class) => class.clone(),
+                None => self.core.block.clone(),
+            },
+            Value::Array(_) => self.core.array.clone(),
+            Value::Method(_) => self.core.method.clone(),
+        }
+    }
+
+    /// The frames currently executing, innermost last.
+    pub fn frames(&self) -> &[Rc<Frame>] {
+        &self.frames
+    }
+
+    pub fn push_frame(&mut self, frame: Rc<Frame>) {
+        self.frames.push(frame);
+    }
+
+    pub fn pop_frame(&mut self) -> Option<Rc<Frame>> {
+        self.frames.pop()
+    }
+
+    pub fn truncate_frames(&mut self, depth: usize) {
+        self.frames.truncate(depth);
+    }
+
+    /// The active frames, innermost first.
+    pub fn stack_trace(&self) -> Vec<FrameInfo> {
+        self.frames.iter().rev().map(|frame| frame.info()).collect()
+    }
+
+    /// The stack trace taken where the error of the last failed send was
+    /// raised.
+    pub fn error_stack_trace(&self) -> Option<&[FrameInfo]> {
+        self.error_trace.as_deref()
+    }
+
+    /// Remembers the current stack trace for the error being raised, unless
+    /// a deeper frame has already done so.
+    pub fn record_error_trace(&mut self) {
+        if self.error_trace.is_none() {
+            self.error_trace = Some(self.stack_trace());
+        }
+    }
+
+    pub fn load_class(&mut self, name: &str) -> Result<Rc<SClass>, LoadError> {
+        if let Some(Value::Class(class)) = self.globals.get(name) {
+            return Ok(class.clone());
+        }
+
+        let class = self.compile_class(name)?;
+        self.define_class(class)
+    }
+
+    /// Compiles and defines the class in `source`, loading its superclass
+    /// from the classpath if needed.
+    pub fn load_source(&mut self, source: &str) -> Result<Rc<SClass>, LoadError> {
+        let class = self.compile_source(source, None)?;
+        self.define_class(class)
+    }
+
+    /// Compiles and defines the class in the source file at `path`, which
+    /// need not be on the classpath but must be allowed by the sandbox.
+    pub fn load_path(&mut self, path: &Path) -> Result<Rc<SClass>, LoadError> {
+        let class = self.compile_path(path)?;
+        self.define_class(class)
+    }
+
+    /// Compiles the class `name` against the fields of its superclass,
+    /// loading the superclass first. Virtual classes of the sandbox shadow
+    /// the classpath.
+    fn compile_class(&mut self, name: &str) -> Result<CompiledClass, LoadError> {
+        if let Some(source) = self.sandbox.virtual_classes.get(name).cloned() {
+            return self.compile_source(&source, Some(name));
+        }
+
+        match self.class_loader.find_source(name) {
+            Some(path) => self.compile_path(&path),
+            None => Err(LoadError::ClassNotFound(name.into())),
+        }
+    }
+
+    /// Compiles the class in `source`, which must be named `expected` if
+    /// given.
+    fn compile_source(
+        &mut self,
+        source: &str,
+        expected: Option<&str>,
+    ) -> Result<CompiledClass, LoadError> {
+        let filename = "<source>";
+        let mut parser = Parser::new(source.as_bytes(), filename);
+        let (name, superclass) = parser.parse_header().map_err(CompileError::from)?;
+        if let Some(expected) = expected.filter(|&expected| expected != name) {
+            return Err(LoadError::ClassNameMismatch {
+                expected: expected.into(),
+                found: name,
+            });
+        }
+
+        let superclass = self.resolve_superclass(&name, superclass.as_deref())?;
+        let inherited = self.inherited_fields(superclass.as_ref());
+        let options = self.class_loader.options().clone();
+        Ok(compile_source_with_fields(
+            source.as_bytes(),
+            filename,
+            &options,
+            &inherited,
+        )?)
+    }
+
+    fn compile_path(&mut self, path: &Path) -> Result<CompiledClass, LoadError> {
+        if !self.sandbox.allows_path(path) {
+            return Err(LoadError::AccessDenied(path.into()));
+        }
+
+        let (name, superclass) = self.class_loader.read_header(path)?;
+        let superclass = self.resolve_superclass(&name, superclass.as_deref())?;
+        let inherited = self.inherited_fields(superclass.as_ref());
+        Ok(self.class_loader.load_path_with_fields(path, &inherited)?)
+    }
+
+    fn resolve_superclass(
+        &mut self,
+        name: &str,
+        superclass: Option<&str>,
+    ) -> Result<Option<Rc<SClass>>, LoadError> {
+        match superclass {
+            Some("nil") => Ok(None),
+            Some(superclass) => self.load_class(superclass).map(Some),
+            None if name == "Object" => Ok(None),
+            None => Ok(Some(self.core.object.clone())),
+        }
+    }
+
+    /// The fields a subclass of `superclass` inherits. The class side
+    /// inherits from the metaclass of `superclass`, or from `Class`.
+    pub fn inherited_fields(&self, superclass: Option<&Rc<SClass>>) -> InheritedFields {
+        let class_side = superclass
+            .and_then(|superclass| superclass.metaclass())
+            .unwrap_or_else(|| self.core.class.clone());
+
+        InheritedFields {
+            instance_fields: superclass.map_or(vec![], |superclass| superclass.instance_fields()),
+            class_fields: class_side.instance_fields(),
+        }
+    }
+
+    /// Loads the sources of the core classes from the classpath, adding
+    /// their SOM methods to the built-in classes.
+    pub fn load_system_classes(&mut self) -> Result<(), LoadError> {
+        let names = self
+            .core
+            .all()
+            .iter()
+            .map(|class| class.name().to_string())
+            .collect::<Vec<_>>();
+
+        for name in names {
+            let virtual_class = self.sandbox.virtual_classes.contains_key(&name);
+            if virtual_class || self.class_loader.find_source(&name).is_some() {
+                let class = self.compile_class(&name)?;
+                self.define_class(class)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Creates the runtime class for `compiled` and makes it a global. An
+    /// existing class of the same name is updated in place, so references to
+    /// it stay valid. `compiled` must have been compiled against the fields
+    /// its superclass has.
+    pub fn define_class(&mut self, compiled: CompiledClass) -> Result<Rc<SClass>, LoadError> {
+        let superclass = self.resolve_superclass(&compiled.name, compiled.superclass.as_deref())?;
+        let inherited = self.inherited_fields(superclass.as_ref());
+        if compiled.inherited != inherited {
+            return Err(LoadError::InheritedFieldsMismatch(compiled.name));
+        }
+
+        let (class, existed) = match self.globals.get(&compiled.name) {
+            Some(Value::Class(class)) => (class.clone(), true),
+            _ => (Rc::new(SClass::new(&compiled.name, None)), false),
+        };
+
+        class.set_superclass(superclass);
+        let metaclass = match class.metaclass() {
+            Some(metaclass) => {
+                let superclass = SClass::metaclass_superclass(&class, &self.core.class);
+                metaclass.set_superclass(Some(superclass));
+                metaclass
+            }
+            None => {
+                let metaclass = Rc::new(SClass::new_metaclass(&class, &self.core.class));
+                class.set_metaclass(metaclass.clone());
+                metaclass
+            }
+        };
+
+        let layout = |inherited: Vec<String>, declared: Vec<String>| {
+            inherited.into_iter().chain(declared).collect::<Vec<_>>()
+        };
+        class.set_instance_fields(layout(inherited.instance_fields, compiled.instance_fields));
+        metaclass.set_instance_fields(layout(inherited.class_fields, compiled.class_fields));
+        class.set_num_class_fields(metaclass.num_instance_fields());
+
+        for method in compiled.instance_methods {
+            let method = runtime_method(&class, method, &mut self.symbols);
+            class.install_method(Rc::new(method));
+        }
+        for method in compiled.class_methods {
+            let method = runtime_method(&metaclass, method, &mut self.symbols);
+            metaclass.install_method(Rc::new(method));
+        }
+        self.install_registered_primitives(&class);
+        self.install_registered_primitives(&metaclass);
+
+        self.globals
+            .insert(compiled.name, Value::Class(class.clone()));
+        if existed {
+            self.invalidate_methods();
+        }
+
+        Ok(class)
+    }
+
+    /// Makes `primitive` the method for `signature` in the class named
+    /// `class`, or its metaclass for `"Foo class"`. It replaces any method
+    /// the class defines itself, now if the class is loaded and otherwise
+    /// whenever it gets defined.
+    pub fn register_primitive(&mut self, class: &str, signature: &str, primitive: PrimitiveFn) {
+        self.registered_primitives
+            .entry(class.into())
+            .or_default()
+            .push((signature.into(), primitive));
+
+        if let Some(holder) = self.holder_named(class) {
+            self.install_registered_primitives(&holder);
+            self.invalidate_methods();
+        }
+    }
+
+    /// The loaded class or metaclass with `name`.
+    fn holder_named(&self, name: &str) -> Option<Rc<SClass>> {
+        let (class, metaclass) = match name.strip_suffix(" class") {
+            Some(class) => (class, true),
+            None => (name, false),
+        };
+
+        match self.globals.get(class) {
+            Some(Value::Class(class)) if metaclass => class.metaclass(),
+            Some(Value::Class(class)) => Some(class.clone()),
+            _ => None,
+        }
+    }
+
+    fn install_registered_primitives(&mut self, holder: &Rc<SClass>) {
+        let primitives = match self.registered_primitives.get(holder.name()) {
+            Some(primitives) => primitives,
+            None => return,
+        };
+
+        for (signature, primitive) in primitives {
+            let body = MethodBody::Primitive(*primitive);
+            let signature = self.symbols.intern(signature);
+            holder.install_method(Rc::new(SMethod::new(signature, holder, body)));
+        }
+    }
+
+    /// Drops everything cached about method dictionaries after one changed.
+    fn invalidate_methods(&mut self) {
+        self.method_epoch += 1;
+        self.method_cache.clear();
+        self.flush_method_caches();
+    }
+
+    /// Finds the method `class` runs for `selector`, consulting the global
+    /// lookup cache before walking the superclass chain.
+    pub fn lookup_method(&mut self, class: &Rc<SClass>, selector: &SSymbol) -> Option<Rc<SMethod>> {
+        let key = (Rc::as_ptr(class), selector.id());
+        if let Some((_, method)) = self.method_cache.get(&key) {
+            return Some(method.clone());
+        }
+
+        let method = class.lookup(selector)?;
+        self.method_cache
+            .insert(key, (class.clone(), method.clone()));
+        Some(method)
+    }
+
+    /// The number of lookups currently held by the global method cache.
+    pub fn method_cache_size(&self) -> usize {
+        self.method_cache.len()
+    }
+
+    /// Changes whenever a method dictionary of a loaded class changes. Inline
+    /// caches filled in an older epoch are discarded on their next use.
+    pub fn method_epoch(&self) -> u64 {
+        self.method_epoch
+    }
+
+    /// The inline caches of every call site that has been executed, ordered
+    /// by class and method.
+    pub fn inline_cache_stats(&self) -> Vec<CallSiteStats> {
+        let mut classes = self
+            .globals
+            .values()
+            .filter_map(|value| match value {
+                Value::Class(class) => Some(class.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        classes.sort_by(|a, b| a.name().cmp(b.name()));
+
+        let mut stats = vec![];
+        for class in classes {
+            let mut methods = methods_of(&class);
+            methods.sort_by_key(|method| {
+                (
+                    method.is_class_side(),
+                    method.signature().as_str().to_string(),
+                )
+            });
+
+            for method in methods {
+                if let MethodBody::Bytecode(code) = method.body() {
+                    collect_call_sites(&format!("{:?}", method), code, &mut stats);
+                }
+            }
+        }
+
+        stats
+    }
+
+    /// Undoes all quickening and drops cached lookups. Needed whenever a
+    /// method dictionary changes, since a rewritten send may have captured
+    /// the method it replaced.
+    pub fn flush_method_caches(&self) {
+        for value in self.globals.values() {
+            if let Value::Class(class) = value {
+                for method in methods_of(class) {
+                    if let MethodBody::Bytecode(code) = method.body() {
+                        code.reset();
+                    }
+                }
+            }
         }
     }
 
     pub fn load_symbol(&mut self, text: &str) -> Rc<SSymbol> {
-        if self.symbols.contains_key(text) {
-            self.symbols[text].clone()
-        } else {
-            let symbol = Rc::new(SSymbol(text.into()));
-            self.symbols.insert(text.into(), symbol.clone());
-            symbol
+        self.symbols.intern(text)
+    }
+
+    pub fn literal_value(&mut self, literal: &Literal) -> interpreter::Result<Value> {
+        match literal {
+            Literal::Array(values) => {
+                let values = values
+                    .iter()
+                    .map(|value| self.literal_value(value))
+                    .collect::<interpreter::Result<Vec<_>>>()?;
+                Ok(Value::Array(Rc::new(SArray::new(values))))
+            }
+            Literal::Boolean(value) => Ok(Value::Boolean(*value)),
+            Literal::Double(value) => Ok(Value::Double(*value)),
+            Literal::Integer(value) => Ok(Value::Integer(*value)),
+            Literal::LargeInteger(value) => Ok(Value::LargeInteger(Rc::new(value.clone()))),
+            Literal::Nil => Ok(Value::Nil),
+            Literal::String(value) => Ok(Value::String(Rc::new(SString::new(value.as_str())))),
+            Literal::Symbol(value) => Ok(Value::Symbol(self.load_symbol(value))),
+        }
+    }
+
+    pub fn send(
+        &mut self,
+        receiver: Value,
+        selector: &str,
+        arguments: Vec<Value>,
+    ) -> interpreter::Result<Value> {
+        if self.frames.is_empty() {
+            self.error_trace = None;
+        }
+
+        interpreter::check_arity(selector, arguments.len())?;
+        let selector = self.load_symbol(selector);
+        interpreter::send(self, receiver, &selector, arguments)
+    }
+
+    /// Sends a message and converts the result, e.g.
+    /// `universe.call::<i64>(receiver, "+", vec![3.into()])`.
+    pub fn call<T>(
+        &mut self,
+        receiver: Value,
+        selector: &str,
+        arguments: Vec<Value>,
+    ) -> interpreter::Result<T>
+    where
+        T: TryFrom<Value, Error = InterpreterError>,
+    {
+        T::try_from(self.send(receiver, selector, arguments)?)
+    }
+
+    /// Runs a program: instantiates the class `name` and sends it `run`.
+    pub fn run(&mut self, name: &str) -> interpreter::Result<Value> {
+        let class = self.load_class(name)?;
+        let instance = self.send(Value::Class(class), "new", vec![])?;
+        self.send(instance, "run", vec![])
+    }
+}
+
+impl Default for Universe {
+    fn default() -> Self {
+        Universe::new()
+    }
+}
+
+fn collect_call_sites(name: &str, code: &Code, stats: &mut Vec<CallSiteStats>) {
+    for (index, selector, cache) in code.call_sites() {
+        if cache.hits() + cache.misses() == 0 {
+            continue;
+        }
+
+        stats.push(CallSiteStats {
+            method: name.into(),
+            bytecode_index: index,
+            selector: selector.into(),
+            state: cache.state(),
+            hits: cache.hits(),
+            misses: cache.misses(),
+        });
+    }
+
+    for (index, block) in code.blocks().iter().enumerate() {
+        collect_call_sites(&format!("{} block {}", name, index), block, stats);
+    }
+}
+
+/// The methods of `class` followed by those of its metaclass.
+fn methods_of(class: &SClass) -> Vec<Rc<SMethod>> {
+    let mut methods = class.methods();
+    if let Some(metaclass) = class.metaclass() {
+        methods.extend(metaclass.methods());
+    }
+
+    methods
+}
+
+/// Installs the primitives of `class` and of its metaclass.
+fn install_primitives(class: &Rc<SClass>, symbols: &mut SymbolTable) {
+    let classes = class.metaclass().into_iter().chain(Some(class.clone()));
+    for class in classes {
+        for &(signature, primitive) in primitives::primitives(class.name()).iter() {
+            let body = MethodBody::Primitive(primitive);
+            let signature = symbols.intern(signature);
+            class.install_method(Rc::new(SMethod::new(signature, &class, body)));
+        }
+    }
+}
+
+fn runtime_method(
+    holder: &Rc<SClass>,
+    method: CompiledMethod,
+    symbols: &mut SymbolTable,
+) -> SMethod {
+    match method {
+        CompiledMethod::Primitive { signature, .. } => {
+            let body = match primitives::lookup(holder.name(), &signature) {
+                Some(primitive) => MethodBody::Primitive(primitive),
+                None => MethodBody::MissingPrimitive,
+            };
+
+            SMethod::new(symbols.intern(&signature), holder, body)
+        }
+        CompiledMethod::Bytecode { signature, code } => {
+            let body = MethodBody::Bytecode(Rc::new(Code::new(code, symbols)));
+            SMethod::new(symbols.intern(&signature), holder, body)
         }
     }
 }
@@ -27,12 +805,22 @@ impl Universe {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    fn classpath(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("som-rs-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
 
     #[test]
     fn test_load_symbol_creates_symbol() {
         let mut universe = Universe::new();
         let symbol = universe.load_symbol("test");
-        assert_eq!(&SSymbol("test".into()), symbol.as_ref());
+        assert_eq!("test", symbol.as_str());
     }
 
     #[test]
@@ -42,4 +830,248 @@ mod tests {
         let symbol2 = universe.load_symbol("test");
         assert!(Rc::ptr_eq(&symbol1, &symbol2));
     }
+
+    #[test]
+    fn test_load_class_with_superclass() {
+        let directory = classpath("universe-superclass");
+        fs::write(directory.join("Shape.som"), "Shape = ( sides = ( ^ 0 ) )").unwrap();
+        fs::write(
+            directory.join("Square.som"),
+            "Square = Shape ( sides = ( ^ super sides + 4 ) )",
+        )
+        .unwrap();
+
+        let mut universe = Universe::with_classpath(vec![directory]);
+        let square = universe.load_class("Square").unwrap();
+        assert_eq!("Shape", square.superclass().unwrap().name());
+        assert_eq!(Some(Value::Class(square)), universe.global("Square"));
+    }
+
+    #[test]
+    fn test_load_class_with_inherited_fields() {
+        let directory = classpath("universe-fields");
+        fs::write(
+            directory.join("Point.som"),
+            "Point = ( | x y | ---- | count | )",
+        )
+        .unwrap();
+        fs::write(
+            directory.join("Point3D.som"),
+            "Point3D = Point (
+                | z |
+                run = ( x := 1. y := 2. z := 3. ^ x + y + z )
+                ----
+                | origin |
+                setUp = ( count := 0. origin := 1. ^ count + origin )
+            )",
+        )
+        .unwrap();
+
+        let mut universe = Universe::with_classpath(vec![directory]);
+        let class = universe.load_class("Point3D").unwrap();
+        assert_eq!(vec!["x", "y", "z"], class.instance_fields());
+        assert_eq!(
+            vec!["count", "origin"],
+            class.metaclass().unwrap().instance_fields()
+        );
+
+        assert_eq!(Value::Integer(6), universe.run("Point3D").unwrap());
+        assert_eq!(
+            Value::Integer(1),
+            universe.send(Value::Class(class), "setUp", vec![]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_define_class_rejects_stale_fields() {
+        let mut universe = Universe::new();
+        let compile = |source: &str| {
+            crate::compiler::compile_source(source.as_bytes(), "Test.som", &Default::default())
+                .unwrap()
+        };
+        universe.define_class(compile("Point = ( | x | )")).unwrap();
+
+        match universe.define_class(compile("Point3D = Point ( | z | )")) {
+            Err(LoadError::InheritedFieldsMismatch(name)) => assert_eq!("Point3D", name),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_run_program() {
+        let directory = classpath("universe-run");
+        fs::write(
+            directory.join("Main.som"),
+            "Main = ( run = ( ^ Helper new answer ) )",
+        )
+        .unwrap();
+        fs::write(
+            directory.join("Helper.som"),
+            "Helper = ( answer = ( ^ 42 ) )",
+        )
+        .unwrap();
+
+        let mut universe = Universe::with_classpath(vec![directory]);
+        assert_eq!(Value::Integer(42), universe.run("Main").unwrap());
+    }
+
+    #[test]
+    fn test_load_system_classes_extends_core_classes() {
+        let directory = classpath("universe-system");
+        fs::write(
+            directory.join("Integer.som"),
+            "Integer = ( + other = primitive double = ( ^ self + self ) )",
+        )
+        .unwrap();
+
+        let mut universe = Universe::with_classpath(vec![directory]);
+        universe.load_system_classes().unwrap();
+        assert_eq!(
+            Value::Integer(8),
+            universe.send(Value::Integer(4), "double", vec![]).unwrap()
+        );
+        let plus = universe.load_symbol("+");
+        match universe
+            .core_classes()
+            .integer
+            .lookup(&plus)
+            .unwrap()
+            .body()
+        {
+            MethodBody::Primitive(_) => {}
+            _ => panic!("primitive expected"),
+        }
+    }
+
+    #[test]
+    fn test_method_cache_flushed_on_redefine() {
+        let mut universe = Universe::new();
+        let compile = |source: &str| {
+            crate::compiler::compile_source(source.as_bytes(), "Test.som", &Default::default())
+                .unwrap()
+        };
+        let class = universe
+            .define_class(compile("Test = ( answer = ( ^ 1 ) )"))
+            .unwrap();
+        let answer = universe.load_symbol("answer");
+
+        let first = universe.lookup_method(&class, &answer).unwrap();
+        assert_eq!(1, universe.method_cache_size());
+        let cached = universe.lookup_method(&class, &answer).unwrap();
+        assert!(Rc::ptr_eq(&first, &cached));
+        assert!(universe
+            .lookup_method(&class.metaclass().unwrap(), &answer)
+            .is_none());
+
+        universe
+            .define_class(compile("Test = ( answer = ( ^ 2 ) )"))
+            .unwrap();
+        assert_eq!(0, universe.method_cache_size());
+        let redefined = universe.lookup_method(&class, &answer).unwrap();
+        assert!(!Rc::ptr_eq(&first, &redefined));
+    }
+
+    #[test]
+    fn test_missing_primitive() {
+        let mut universe = Universe::new();
+        let class = crate::compiler::compile_source(
+            b"Test = ( foo = primitive )".as_ref(),
+            "Test.som",
+            &Default::default(),
+        )
+        .unwrap();
+        let class = universe.define_class(class).unwrap();
+        let instance = universe.send(Value::Class(class), "new", vec![]).unwrap();
+
+        match universe.send(instance, "foo", vec![]) {
+            Err(InterpreterError::RuntimeError(e)) => {
+                assert_eq!("Primitive Test>>foo is not implemented", e)
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_load_source_and_path() {
+        let directory = classpath("universe-embed");
+        fs::write(directory.join("Base.som"), "Base = ( | x | x = ( ^ x ) )").unwrap();
+        let path = directory.join("elsewhere.som");
+        fs::write(&path, "Counter = Base ( set: n = ( x := n ) )").unwrap();
+
+        let mut universe = Universe::with_options(UniverseOptions {
+            classpath: vec![directory],
+            ..UniverseOptions::default()
+        })
+        .unwrap();
+        let class = universe
+            .load_source("Doubler = Base ( twice: n = ( ^ n * 2 ) )")
+            .unwrap();
+        assert_eq!(vec!["x"], class.instance_fields());
+        let doubler = universe.send(Value::Class(class), "new", vec![]).unwrap();
+        let result = universe.call::<i64>(doubler, "twice:", vec![21.into()]);
+        assert_eq!(42, result.unwrap());
+
+        let class = universe.load_path(&path).unwrap();
+        assert_eq!("Counter", class.name());
+        assert!(universe.global("Counter").is_some());
+    }
+
+    #[test]
+    fn test_call_reports_conversion_errors() {
+        let mut universe = Universe::new();
+        match universe.call::<bool>(Value::Integer(1), "+", vec![2.into()]) {
+            Err(InterpreterError::RuntimeError(e)) => {
+                assert_eq!("Expected a Boolean, got Integer(3)", e)
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_send_checks_argument_count() {
+        let mut universe = Universe::new();
+        let class = universe
+            .load_source("Doubler = ( twice: n = ( ^ n * 2 ) )")
+            .unwrap();
+        let doubler = universe.send(Value::Class(class), "new", vec![]).unwrap();
+
+        for arguments in [vec![], vec![1.into(), 2.into()]] {
+            let count = arguments.len();
+            match universe.call::<i64>(doubler.clone(), "twice:", arguments) {
+                Err(InterpreterError::RuntimeError(e)) => {
+                    assert_eq!(format!("#twice: takes 1 arguments, got {}", count), e)
+                }
+                r => panic!("unexpected result {:?}", r),
+            }
+        }
+    }
+
+    fn answer(_: &mut Universe, _: Vec<Value>) -> interpreter::Result<Value> {
+        Ok(Value::Integer(42))
+    }
+
+    fn greet(_: &mut Universe, arguments: Vec<Value>) -> interpreter::Result<Value> {
+        let name = String::try_from(arguments[1].clone())?;
+        Ok(format!("Hello, {}", name).into())
+    }
+
+    #[test]
+    fn test_register_primitive() {
+        let mut universe = Universe::new();
+        universe.register_primitive("Service", "greet:", greet);
+        universe.register_primitive("Service class", "answer", answer);
+        let class = universe
+            .load_source("Service = ( greet: name = primitive run = ( ^ self greet: 'SOM' ) )")
+            .unwrap();
+
+        let result = universe.call::<i64>(Value::Class(class.clone()), "answer", vec![]);
+        assert_eq!(42, result.unwrap());
+        let service = universe.send(Value::Class(class), "new", vec![]).unwrap();
+        let greeting = universe.call::<String>(service.clone(), "run", vec![]);
+        assert_eq!("Hello, SOM", greeting.unwrap());
+
+        // Registering on a loaded class replaces its method right away.
+        universe.register_primitive("Service", "run", answer);
+        assert_eq!(42, universe.call::<i64>(service, "run", vec![]).unwrap());
+    }
 }
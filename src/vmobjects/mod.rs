@@ -1,9 +1,17 @@
+mod sarray;
+mod sblock;
 mod sclass;
-mod sendable;
+mod smethod;
 mod sobject;
+mod sstring;
 mod ssymbol;
+mod value;
 
+pub use self::sarray::SArray;
+pub use self::sblock::SBlock;
 pub use self::sclass::SClass;
-pub use self::sendable::Sendable;
+pub use self::smethod::{MethodBody, PrimitiveFn, SMethod};
 pub use self::sobject::SObject;
-pub use self::ssymbol::SSymbol;
+pub use self::sstring::SString;
+pub use self::ssymbol::{SSymbol, SymbolId, SymbolTable};
+pub use self::value::Value;
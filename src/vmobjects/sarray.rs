@@ -0,0 +1,42 @@
+use crate::vmobjects::Value;
+use std::cell::RefCell;
+
+/// A fixed-size array of values, indexed from 0 on the Rust side.
+#[derive(Debug)]
+pub struct SArray {
+    values: RefCell<Vec<Value>>,
+}
+
+impl SArray {
+    pub fn new(values: Vec<Value>) -> SArray {
+        SArray {
+            values: RefCell::new(values),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.borrow().is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<Value> {
+        self.values.borrow().get(index).cloned()
+    }
+
+    pub fn set(&self, index: usize, value: Value) -> bool {
+        match self.values.borrow_mut().get_mut(index) {
+            Some(slot) => {
+                *slot = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn to_vec(&self) -> Vec<Value> {
+        self.values.borrow().clone()
+    }
+}
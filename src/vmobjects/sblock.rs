@@ -0,0 +1,41 @@
+use crate::interpreter::{Code, Frame};
+use crate::vmobjects::SMethod;
+use std::fmt;
+use std::rc::Rc;
+
+/// A block closure: the block's code together with the frame it was
+/// created in, through which it reaches outer locals and its home method.
+pub struct SBlock {
+    code: Rc<Code>,
+    context: Rc<Frame>,
+}
+
+impl SBlock {
+    pub fn new(code: Rc<Code>, context: Rc<Frame>) -> SBlock {
+        SBlock { code, context }
+    }
+
+    pub fn code(&self) -> &Rc<Code> {
+        &self.code
+    }
+
+    pub fn context(&self) -> &Rc<Frame> {
+        &self.context
+    }
+
+    /// The method whose body contains the block.
+    pub fn method(&self) -> &Rc<SMethod> {
+        self.context.method()
+    }
+
+    /// The number of arguments `value` and friends must supply.
+    pub fn num_arguments(&self) -> usize {
+        self.code.num_parameters()
+    }
+}
+
+impl fmt::Debug for SBlock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Block in {:?}", self.method())
+    }
+}
@@ -1,10 +1,189 @@
-use crate::vmobjects::{SObject, Sendable};
+use crate::vmobjects::{SMethod, SSymbol, SymbolId, Value};
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::fmt;
 use std::rc::Rc;
 
-#[derive(Debug)]
+/// A class at runtime. Every class loaded by the universe has a metaclass
+/// holding its class-side methods and the names of its class-side fields;
+/// the values of those fields live in the class itself.
 pub struct SClass {
-    pub superclass: Option<Rc<SObject>>,
-    pub name: String,
-    pub invokables: HashMap<String, Box<Sendable>>,
+    name: String,
+    metaclass: RefCell<Option<Rc<SClass>>>,
+    is_metaclass: bool,
+    superclass: RefCell<Option<Rc<SClass>>>,
+    instance_fields: RefCell<Vec<String>>,
+    invokables: RefCell<HashMap<SymbolId, Rc<SMethod>>>,
+    class_fields: RefCell<Vec<Value>>,
+}
+
+impl SClass {
+    pub fn new(name: &str, superclass: Option<Rc<SClass>>) -> SClass {
+        SClass {
+            name: name.into(),
+            metaclass: RefCell::new(None),
+            is_metaclass: false,
+            superclass: RefCell::new(superclass),
+            instance_fields: RefCell::new(vec![]),
+            invokables: RefCell::new(HashMap::new()),
+            class_fields: RefCell::new(vec![]),
+        }
+    }
+
+    /// Creates the metaclass of `class`. It inherits from the metaclass of
+    /// the superclass of `class`, or from `class_class` for a root class.
+    pub fn new_metaclass(class: &SClass, class_class: &Rc<SClass>) -> SClass {
+        SClass {
+            is_metaclass: true,
+            ..SClass::new(
+                &format!("{} class", class.name()),
+                Some(SClass::metaclass_superclass(class, class_class)),
+            )
+        }
+    }
+
+    /// The superclass the metaclass of `class` should have.
+    pub fn metaclass_superclass(class: &SClass, class_class: &Rc<SClass>) -> Rc<SClass> {
+        class
+            .superclass()
+            .and_then(|superclass| superclass.metaclass())
+            .unwrap_or_else(|| class_class.clone())
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The metaclass of this class. Metaclasses themselves have none; they
+    /// are all instances of `Metaclass`.
+    pub fn metaclass(&self) -> Option<Rc<SClass>> {
+        self.metaclass.borrow().clone()
+    }
+
+    pub fn set_metaclass(&self, metaclass: Rc<SClass>) {
+        *self.metaclass.borrow_mut() = Some(metaclass);
+    }
+
+    pub fn is_metaclass(&self) -> bool {
+        self.is_metaclass
+    }
+
+    pub fn superclass(&self) -> Option<Rc<SClass>> {
+        self.superclass.borrow().clone()
+    }
+
+    pub fn set_superclass(&self, superclass: Option<Rc<SClass>>) {
+        *self.superclass.borrow_mut() = superclass;
+    }
+
+    pub fn instance_fields(&self) -> Vec<String> {
+        self.instance_fields.borrow().clone()
+    }
+
+    pub fn num_instance_fields(&self) -> usize {
+        self.instance_fields.borrow().len()
+    }
+
+    pub fn set_instance_fields(&self, fields: Vec<String>) {
+        *self.instance_fields.borrow_mut() = fields;
+    }
+
+    pub fn class_field(&self, index: usize) -> Option<Value> {
+        self.class_fields.borrow().get(index).cloned()
+    }
+
+    pub fn set_class_field(&self, index: usize, value: Value) -> bool {
+        match self.class_fields.borrow_mut().get_mut(index) {
+            Some(field) => {
+                *field = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Resizes the class-side fields, keeping the values of fields that
+    /// survive a redefinition.
+    pub fn set_num_class_fields(&self, count: usize) {
+        self.class_fields.borrow_mut().resize(count, Value::Nil);
+    }
+
+    pub fn install_method(&self, method: Rc<SMethod>) {
+        self.invokables
+            .borrow_mut()
+            .insert(method.signature().id(), method);
+    }
+
+    /// Finds the method for `selector` in this class or its superclasses.
+    pub fn lookup(&self, selector: &SSymbol) -> Option<Rc<SMethod>> {
+        if let Some(method) = self.invokables.borrow().get(&selector.id()) {
+            return Some(method.clone());
+        }
+
+        self.superclass()
+            .and_then(|superclass| superclass.lookup(selector))
+    }
+
+    /// The methods defined in this class itself.
+    pub fn methods(&self) -> Vec<Rc<SMethod>> {
+        self.invokables.borrow().values().cloned().collect()
+    }
+}
+
+impl fmt::Debug for SClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SClass").field("name", &self.name).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vmobjects::{MethodBody, SymbolTable};
+
+    #[test]
+    fn test_lookup_walks_superclasses() {
+        let mut symbols = SymbolTable::new();
+        let foo = symbols.intern("foo");
+        let bar = symbols.intern("bar");
+
+        let object = Rc::new(SClass::new("Object", None));
+        let point = Rc::new(SClass::new("Point", Some(object.clone())));
+        object.install_method(Rc::new(SMethod::new(
+            foo.clone(),
+            &object,
+            MethodBody::MissingPrimitive,
+        )));
+        point.install_method(Rc::new(SMethod::new(
+            bar.clone(),
+            &point,
+            MethodBody::MissingPrimitive,
+        )));
+
+        let method = point.lookup(&foo).unwrap();
+        assert!(Rc::ptr_eq(&object, &method.holder().unwrap()));
+        assert!(point.lookup(&bar).is_some());
+        assert!(object.lookup(&bar).is_none());
+    }
+
+    #[test]
+    fn test_metaclass_inherits_from_superclass_metaclass() {
+        let class = Rc::new(SClass::new("Class", None));
+        let object = Rc::new(SClass::new("Object", None));
+        object.set_metaclass(Rc::new(SClass::new_metaclass(&object, &class)));
+        let point = SClass::new("Point", Some(object.clone()));
+        let metaclass = SClass::new_metaclass(&point, &class);
+
+        assert_eq!("Point class", metaclass.name());
+        assert!(metaclass.is_metaclass());
+        assert!(!point.is_metaclass());
+        assert!(Rc::ptr_eq(
+            &object.metaclass().unwrap(),
+            &metaclass.superclass().unwrap()
+        ));
+        assert!(Rc::ptr_eq(
+            &class,
+            &object.metaclass().unwrap().superclass().unwrap()
+        ));
+    }
 }
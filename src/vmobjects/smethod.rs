@@ -0,0 +1,56 @@
+use crate::interpreter::{self, Code};
+use crate::vm::Universe;
+use crate::vmobjects::{SClass, SSymbol, Value};
+use std::fmt;
+use std::rc::{Rc, Weak};
+
+/// A primitive receives the receiver followed by the arguments.
+pub type PrimitiveFn = fn(&mut Universe, Vec<Value>) -> interpreter::Result<Value>;
+
+pub enum MethodBody {
+    Bytecode(Rc<Code>),
+    Primitive(PrimitiveFn),
+    /// Declared `primitive` in SOM but not implemented by the VM.
+    MissingPrimitive,
+}
+
+pub struct SMethod {
+    signature: Rc<SSymbol>,
+    holder: Weak<SClass>,
+    body: MethodBody,
+}
+
+impl SMethod {
+    pub fn new(signature: Rc<SSymbol>, holder: &Rc<SClass>, body: MethodBody) -> SMethod {
+        SMethod {
+            signature,
+            holder: Rc::downgrade(holder),
+            body,
+        }
+    }
+
+    pub fn signature(&self) -> &Rc<SSymbol> {
+        &self.signature
+    }
+
+    pub fn holder(&self) -> Option<Rc<SClass>> {
+        self.holder.upgrade()
+    }
+
+    /// Whether the method is defined in a metaclass.
+    pub fn is_class_side(&self) -> bool {
+        self.holder().is_some_and(|holder| holder.is_metaclass())
+    }
+
+    pub fn body(&self) -> &MethodBody {
+        &self.body
+    }
+}
+
+impl fmt::Debug for SMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let holder = self.holder();
+        let holder = holder.as_ref().map_or("?", |holder| holder.name());
+        write!(f, "{}>>{}", holder, self.signature.as_str())
+    }
+}
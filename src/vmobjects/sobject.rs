@@ -1,8 +1,36 @@
-use crate::vmobjects::{SClass, Sendable};
+use crate::vmobjects::{SClass, Value};
+use std::cell::RefCell;
 use std::rc::Rc;
 
 #[derive(Debug)]
 pub struct SObject {
     class: Rc<SClass>,
-    fields: Vec<Box<Sendable>>,
+    fields: RefCell<Vec<Value>>,
+}
+
+impl SObject {
+    pub fn new(class: Rc<SClass>, num_fields: usize) -> SObject {
+        SObject {
+            class,
+            fields: RefCell::new(vec![Value::Nil; num_fields]),
+        }
+    }
+
+    pub fn class(&self) -> &Rc<SClass> {
+        &self.class
+    }
+
+    pub fn field(&self, index: usize) -> Option<Value> {
+        self.fields.borrow().get(index).cloned()
+    }
+
+    pub fn set_field(&self, index: usize, value: Value) -> bool {
+        match self.fields.borrow_mut().get_mut(index) {
+            Some(field) => {
+                *field = value;
+                true
+            }
+            None => false,
+        }
+    }
 }
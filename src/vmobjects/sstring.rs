@@ -0,0 +1,68 @@
+use std::fmt;
+
+/// An immutable string. Lengths and indices count characters, not bytes.
+#[derive(PartialEq)]
+pub struct SString {
+    text: String,
+}
+
+impl SString {
+    pub fn new<S: Into<String>>(text: S) -> SString {
+        SString { text: text.into() }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn len(&self) -> usize {
+        self.text.chars().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// The characters from `start` up to but excluding `end`, or `None` when
+    /// the range does not lie within the string.
+    pub fn substring(&self, start: usize, end: usize) -> Option<&str> {
+        if start > end {
+            return None;
+        }
+
+        let mut offsets = self
+            .text
+            .char_indices()
+            .map(|(offset, _)| offset)
+            .chain(Some(self.text.len()));
+        let from = offsets.nth(start)?;
+        let to = if end == start {
+            from
+        } else {
+            offsets.nth(end - start - 1)?
+        };
+        Some(&self.text[from..to])
+    }
+}
+
+impl fmt::Debug for SString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_characters() {
+        let string = SString::new("héllo wörld");
+        assert_eq!(11, string.len());
+        assert_eq!(Some("éllo"), string.substring(1, 5));
+        assert_eq!(Some("wörld"), string.substring(6, 11));
+        assert_eq!(Some(""), string.substring(11, 11));
+        assert_eq!(None, string.substring(6, 12));
+        assert_eq!(None, string.substring(3, 2));
+    }
+}
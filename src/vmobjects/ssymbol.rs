@@ -1,2 +1,69 @@
-#[derive(Debug, PartialEq)]
-pub struct SSymbol(pub String);
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// The identity of an interned symbol, a cheap key for method dictionaries.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SymbolId(u32);
+
+#[derive(Debug)]
+pub struct SSymbol {
+    id: SymbolId,
+    text: String,
+}
+
+impl SSymbol {
+    pub fn id(&self) -> SymbolId {
+        self.id
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+}
+
+impl PartialEq for SSymbol {
+    fn eq(&self, other: &SSymbol) -> bool {
+        self.id == other.id
+    }
+}
+
+/// Interns symbols so equal text always yields the same `SSymbol`.
+#[derive(Debug, Default)]
+pub struct SymbolTable {
+    symbols: HashMap<String, Rc<SSymbol>>,
+}
+
+impl SymbolTable {
+    pub fn new() -> SymbolTable {
+        SymbolTable::default()
+    }
+
+    pub fn intern(&mut self, text: &str) -> Rc<SSymbol> {
+        if let Some(symbol) = self.symbols.get(text) {
+            return symbol.clone();
+        }
+
+        let symbol = Rc::new(SSymbol {
+            id: SymbolId(self.symbols.len() as u32),
+            text: text.into(),
+        });
+        self.symbols.insert(text.into(), symbol.clone());
+        symbol
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intern_returns_same_symbol() {
+        let mut symbols = SymbolTable::new();
+        let foo = symbols.intern("foo");
+        let bar = symbols.intern("bar");
+
+        assert!(Rc::ptr_eq(&foo, &symbols.intern("foo")));
+        assert_ne!(foo.id(), bar.id());
+        assert_eq!("bar", bar.as_str());
+    }
+}
@@ -0,0 +1,178 @@
+use crate::interpreter::InterpreterError;
+use crate::vmobjects::{SArray, SBlock, SClass, SMethod, SObject, SString, SSymbol};
+use num_bigint::BigInt;
+use std::convert::TryFrom;
+use std::rc::Rc;
+
+#[derive(Clone, Debug)]
+pub enum Value {
+    Nil,
+    Boolean(bool),
+    Integer(i64),
+    /// An integer outside the `i64` range. Results that fit are always
+    /// demoted back to `Integer`.
+    LargeInteger(Rc<BigInt>),
+    Double(f64),
+    String(Rc<SString>),
+    /// Interned, so equal symbols are always the same object.
+    Symbol(Rc<SSymbol>),
+    Object(Rc<SObject>),
+    Class(Rc<SClass>),
+    Block(Rc<SBlock>),
+    Array(Rc<SArray>),
+    Method(Rc<SMethod>),
+}
+
+impl Value {
+    /// SOM `==`: immediates and integers compare by value, everything else
+    /// by identity.
+    pub fn is_identical(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Nil, Value::Nil) => true,
+            (Value::Boolean(a), Value::Boolean(b)) => a == b,
+            (Value::Integer(a), Value::Integer(b)) => a == b,
+            (Value::LargeInteger(a), Value::LargeInteger(b)) => a == b,
+            (Value::Double(a), Value::Double(b)) => a == b,
+            (Value::String(a), Value::String(b)) => Rc::ptr_eq(a, b),
+            (Value::Symbol(a), Value::Symbol(b)) => Rc::ptr_eq(a, b),
+            (Value::Object(a), Value::Object(b)) => Rc::ptr_eq(a, b),
+            (Value::Class(a), Value::Class(b)) => Rc::ptr_eq(a, b),
+            (Value::Block(a), Value::Block(b)) => Rc::ptr_eq(a, b),
+            (Value::Array(a), Value::Array(b)) => Rc::ptr_eq(a, b),
+            (Value::Method(a), Value::Method(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        self.is_identical(other)
+    }
+}
+
+// Conversions for passing Rust values into SOM and reading results back.
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Boolean(value)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Integer(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Double(value)
+    }
+}
+
+impl<'a> From<&'a str> for Value {
+    fn from(value: &'a str) -> Self {
+        Value::String(Rc::new(SString::new(value)))
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(Rc::new(SString::new(value)))
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(values: Vec<Value>) -> Self {
+        Value::Array(Rc::new(SArray::new(values)))
+    }
+}
+
+fn conversion_error(expected: &str, value: &Value) -> InterpreterError {
+    InterpreterError::RuntimeError(format!("Expected {}, got {:?}", expected, value))
+}
+
+impl TryFrom<Value> for bool {
+    type Error = InterpreterError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Boolean(value) => Ok(value),
+            value => Err(conversion_error("a Boolean", &value)),
+        }
+    }
+}
+
+/// Fails for large integers, which do not fit.
+impl TryFrom<Value> for i64 {
+    type Error = InterpreterError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Integer(value) => Ok(value),
+            value => Err(conversion_error("an Integer", &value)),
+        }
+    }
+}
+
+impl TryFrom<Value> for f64 {
+    type Error = InterpreterError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Double(value) => Ok(value),
+            value => Err(conversion_error("a Double", &value)),
+        }
+    }
+}
+
+/// Accepts symbols as well, since `Symbol` is a subclass of `String`.
+impl TryFrom<Value> for String {
+    type Error = InterpreterError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::String(string) => Ok(string.as_str().into()),
+            Value::Symbol(symbol) => Ok(symbol.as_str().into()),
+            value => Err(conversion_error("a String", &value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vmobjects::SymbolTable;
+
+    #[test]
+    fn test_value_identity() {
+        let mut symbols = SymbolTable::new();
+        let foo = Value::Symbol(symbols.intern("foo"));
+        assert!(Value::Integer(3).is_identical(&Value::Integer(3)));
+        assert!(foo.is_identical(&Value::Symbol(symbols.intern("foo"))));
+        assert!(!foo.is_identical(&Value::Symbol(symbols.intern("bar"))));
+        assert!(!Value::Class(Rc::new(SClass::new("Foo", None)))
+            .is_identical(&Value::Class(Rc::new(SClass::new("Foo", None)))));
+        assert!(!Value::Nil.is_identical(&Value::Boolean(false)));
+    }
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(Value::Integer(3), Value::from(3));
+        assert_eq!(Ok(3), i64::try_from(Value::from(3)).map_err(|_| ()));
+        assert_eq!(Ok(true), bool::try_from(Value::from(true)).map_err(|_| ()));
+        assert_eq!(Ok(0.5), f64::try_from(Value::from(0.5)).map_err(|_| ()));
+        assert_eq!(
+            Ok("hi".to_string()),
+            String::try_from(Value::from("hi")).map_err(|_| ())
+        );
+
+        match i64::try_from(Value::from("3")) {
+            Err(InterpreterError::RuntimeError(message)) => {
+                assert_eq!("Expected an Integer, got String(\"3\")", message)
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+}